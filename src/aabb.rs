@@ -0,0 +1,203 @@
+use crate::ray::*;
+use crate::vec3::*;
+
+/// Axis-aligned bounding box.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Aabb {
+    minimum: Point3,
+    maximum: Point3,
+}
+
+impl Default for Aabb {
+    fn default() -> Aabb {
+        Aabb::new()
+    }
+}
+
+impl Aabb {
+    /// The empty box, which contains nothing and leaves any box it is merged with unchanged.
+    pub fn new() -> Aabb {
+        Aabb {
+            minimum: Point3::from(f64::INFINITY, f64::INFINITY, f64::INFINITY),
+            maximum: Point3::from(f64::NEG_INFINITY, f64::NEG_INFINITY, f64::NEG_INFINITY),
+        }
+    }
+
+    /// The box spanned by two opposite corners, given in any order.
+    pub fn from(a: Point3, b: Point3) -> Aabb {
+        Aabb {
+            minimum: Point3::from(a.x().min(*b.x()), a.y().min(*b.y()), a.z().min(*b.z())),
+            maximum: Point3::from(a.x().max(*b.x()), a.y().max(*b.y()), a.z().max(*b.z())),
+        }
+    }
+
+    /// The smallest box containing all `points`.
+    pub fn from_points<I: IntoIterator<Item = Point3>>(points: I) -> Aabb {
+        points
+            .into_iter()
+            .fold(Aabb::new(), |bbox, p| bbox.include(p))
+    }
+
+    pub fn min(&self) -> Point3 {
+        self.minimum
+    }
+
+    pub fn max(&self) -> Point3 {
+        self.maximum
+    }
+
+    pub fn is_empty(&self) -> bool {
+        (0..3).any(|axis| self.minimum[axis] > self.maximum[axis])
+    }
+
+    pub fn surrounding_box(box0: &Aabb, box1: &Aabb) -> Aabb {
+        Aabb {
+            minimum: Point3::from(
+                box0.minimum.x().min(*box1.minimum.x()),
+                box0.minimum.y().min(*box1.minimum.y()),
+                box0.minimum.z().min(*box1.minimum.z()),
+            ),
+            maximum: Point3::from(
+                box0.maximum.x().max(*box1.maximum.x()),
+                box0.maximum.y().max(*box1.maximum.y()),
+                box0.maximum.z().max(*box1.maximum.z()),
+            ),
+        }
+    }
+
+    pub fn include(&self, p: Point3) -> Aabb {
+        Aabb::surrounding_box(
+            self,
+            &Aabb {
+                minimum: p,
+                maximum: p,
+            },
+        )
+    }
+
+    /// Grows every side thinner than `delta` to that width, so that flat primitives such as
+    /// axis-aligned triangles still have a volume a ray can enter.
+    pub fn pad(&self, delta: f64) -> Aabb {
+        let size = self.maximum - self.minimum;
+        let grow = |size: f64| {
+            if size < delta {
+                (delta - size) / 2f64
+            } else {
+                0f64
+            }
+        };
+        let offset = Vec3::from(grow(*size.x()), grow(*size.y()), grow(*size.z()));
+        Aabb {
+            minimum: self.minimum - offset,
+            maximum: self.maximum + offset,
+        }
+    }
+
+    pub fn centroid(&self) -> Point3 {
+        0.5 * (self.minimum + self.maximum)
+    }
+
+    pub fn surface_area(&self) -> f64 {
+        if self.is_empty() {
+            return 0f64;
+        }
+        let d = self.maximum - self.minimum;
+        2f64 * (d.x() * d.y() + d.y() * d.z() + d.z() * d.x())
+    }
+
+    /// Index of the axis along which the box is the widest.
+    pub fn longest_axis(&self) -> i32 {
+        let d = self.maximum - self.minimum;
+        if d.x() >= d.y() && d.x() >= d.z() {
+            0
+        } else if d.y() >= d.z() {
+            1
+        } else {
+            2
+        }
+    }
+
+    /// Slab test: whether `r` passes through the box for some `t` in `t_min..t_max`.
+    pub fn hit(&self, r: &Ray, t_min: f64, t_max: f64) -> bool {
+        self.hit_distance(r, t_min, t_max).is_some()
+    }
+
+    /// Like [`Aabb::hit`], returning where the ray enters the box.
+    pub fn hit_distance(&self, r: &Ray, mut t_min: f64, mut t_max: f64) -> Option<f64> {
+        for axis in 0..3 {
+            let inv_d = 1f64 / r.direction()[axis];
+            let mut t0 = (self.minimum[axis] - r.origin()[axis]) * inv_d;
+            let mut t1 = (self.maximum[axis] - r.origin()[axis]) * inv_d;
+            if inv_d < 0f64 {
+                std::mem::swap(&mut t0, &mut t1);
+            }
+            // `max` and `min` ignore the NaN of a ray lying in a slab boundary.
+            t_min = t_min.max(t0);
+            t_max = t_max.min(t1);
+            if t_max < t_min {
+                return None;
+            }
+        }
+        Some(t_min)
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn box_operations() {
+        let bbox = Aabb::from(
+            Point3::from(1f64, 2f64, 3f64),
+            Point3::from(-1f64, 0f64, 0f64),
+        );
+        assert_eq!(Point3::from(-1f64, 0f64, 0f64), bbox.min());
+        assert_eq!(Point3::from(0f64, 1f64, 1.5), bbox.centroid());
+        assert_eq!(
+            2f64 * (2f64 * 2f64 + 2f64 * 3f64 + 3f64 * 2f64),
+            bbox.surface_area()
+        );
+        assert_eq!(2, bbox.longest_axis());
+
+        let empty = Aabb::new();
+        assert!(empty.is_empty());
+        assert_eq!(0f64, empty.surface_area());
+        assert_eq!(bbox, Aabb::surrounding_box(&empty, &bbox));
+        assert_eq!(
+            bbox,
+            Aabb::from_points([
+                Point3::from(1f64, 0f64, 3f64),
+                Point3::from(-1f64, 2f64, 0f64)
+            ])
+        );
+
+        let flat = Aabb::from(
+            Point3::from(0f64, 0f64, 0f64),
+            Point3::from(1f64, 1f64, 0f64),
+        );
+        assert_eq!(Point3::from(0f64, 0f64, -0.5), flat.pad(1f64).min());
+    }
+
+    #[test]
+    fn slab_test() {
+        let bbox = Aabb::from(
+            Point3::from(-1f64, -1f64, -3f64),
+            Point3::from(1f64, 1f64, -1f64),
+        );
+        let r = Ray::from(Point3::new(), Vec3::from(0f64, 0f64, -1f64));
+        assert_eq!(Some(1f64), bbox.hit_distance(&r, 0f64, f64::INFINITY));
+        assert!(!bbox.hit(&r, 0f64, 0.5));
+        assert!(!bbox.hit(&r, 3.5, f64::INFINITY));
+
+        let miss = Ray::from(Point3::new(), Vec3::from(1f64, 0f64, -0.1));
+        assert!(!bbox.hit(&miss, 0f64, f64::INFINITY));
+
+        // Parallel to the x slabs, and lying exactly on one of them.
+        let grazing = Ray::from(
+            Point3::from(1f64, 0f64, 0f64),
+            Vec3::from(0f64, 0f64, -1f64),
+        );
+        assert!(bbox.hit(&grazing, 0f64, f64::INFINITY));
+    }
+}
@@ -0,0 +1,188 @@
+//! Rectangles lying in planes perpendicular to a coordinate axis.
+
+use std::sync::Arc;
+
+use crate::aabb::*;
+use crate::hittable::*;
+use crate::material::*;
+use crate::ray::*;
+use crate::vec3::*;
+
+/// The rectangle `a0..a1` by `b0..b1` in the plane where the coordinate along the normal axis is
+/// `k`. The outward normal points towards increasing coordinates, and `(u, v)` follow `a` and
+/// `b`.
+struct AxisRect {
+    /// Indices of the `a`, `b` and normal axes.
+    axes: [i32; 3],
+    a0: f64,
+    a1: f64,
+    b0: f64,
+    b1: f64,
+    k: f64,
+    material: Arc<dyn Material>,
+}
+
+impl AxisRect {
+    fn hit(&self, r: &Ray, t_min: f64, t_max: f64) -> Option<HitRecord<'_>> {
+        let [a_axis, b_axis, axis] = self.axes;
+        let t = (self.k - r.origin()[axis]) / r.direction()[axis];
+        // Also rejects the NaN of rays lying in the plane.
+        if !(t > t_min && t < t_max) {
+            return None;
+        }
+        let a = r.origin()[a_axis] + t * r.direction()[a_axis];
+        let b = r.origin()[b_axis] + t * r.direction()[b_axis];
+        if a < self.a0 || a > self.a1 || b < self.b0 || b > self.b1 {
+            return None;
+        }
+        let outward_normal = match axis {
+            0 => Vec3::from(1f64, 0f64, 0f64),
+            1 => Vec3::from(0f64, 1f64, 0f64),
+            _ => Vec3::from(0f64, 0f64, 1f64),
+        };
+        Some(HitRecord::from(
+            r,
+            t,
+            outward_normal,
+            (a - self.a0) / (self.a1 - self.a0),
+            (b - self.b0) / (self.b1 - self.b0),
+            self.material.as_ref(),
+        ))
+    }
+
+    /// The box is padded along the normal, so that it has a volume.
+    fn bounding_box(&self) -> Aabb {
+        let point = |a: f64, b: f64, k: f64| {
+            let mut p = [0f64; 3];
+            p[self.axes[0] as usize] = a;
+            p[self.axes[1] as usize] = b;
+            p[self.axes[2] as usize] = k;
+            Point3::from(p[0], p[1], p[2])
+        };
+        Aabb::from(
+            point(self.a0, self.b0, self.k),
+            point(self.a1, self.b1, self.k),
+        )
+        .pad(1e-4)
+    }
+}
+
+/// Rectangle `x0..x1` by `y0..y1` at `z = k`, facing +z.
+pub struct XyRect {
+    rect: AxisRect,
+}
+
+impl XyRect {
+    pub fn from(x0: f64, x1: f64, y0: f64, y1: f64, k: f64, material: Arc<dyn Material>) -> XyRect {
+        XyRect {
+            rect: AxisRect {
+                axes: [0, 1, 2],
+                a0: x0,
+                a1: x1,
+                b0: y0,
+                b1: y1,
+                k,
+                material,
+            },
+        }
+    }
+}
+
+impl Hittable for XyRect {
+    fn hit(&self, r: &Ray, t_min: f64, t_max: f64) -> Option<HitRecord<'_>> {
+        self.rect.hit(r, t_min, t_max)
+    }
+
+    fn bounding_box(&self) -> Option<Aabb> {
+        Some(self.rect.bounding_box())
+    }
+}
+
+/// Rectangle `x0..x1` by `z0..z1` at `y = k`, facing +y.
+pub struct XzRect {
+    rect: AxisRect,
+}
+
+impl XzRect {
+    pub fn from(x0: f64, x1: f64, z0: f64, z1: f64, k: f64, material: Arc<dyn Material>) -> XzRect {
+        XzRect {
+            rect: AxisRect {
+                axes: [0, 2, 1],
+                a0: x0,
+                a1: x1,
+                b0: z0,
+                b1: z1,
+                k,
+                material,
+            },
+        }
+    }
+}
+
+impl Hittable for XzRect {
+    fn hit(&self, r: &Ray, t_min: f64, t_max: f64) -> Option<HitRecord<'_>> {
+        self.rect.hit(r, t_min, t_max)
+    }
+
+    fn bounding_box(&self) -> Option<Aabb> {
+        Some(self.rect.bounding_box())
+    }
+}
+
+/// Rectangle `y0..y1` by `z0..z1` at `x = k`, facing +x.
+pub struct YzRect {
+    rect: AxisRect,
+}
+
+impl YzRect {
+    pub fn from(y0: f64, y1: f64, z0: f64, z1: f64, k: f64, material: Arc<dyn Material>) -> YzRect {
+        YzRect {
+            rect: AxisRect {
+                axes: [1, 2, 0],
+                a0: y0,
+                a1: y1,
+                b0: z0,
+                b1: z1,
+                k,
+                material,
+            },
+        }
+    }
+}
+
+impl Hittable for YzRect {
+    fn hit(&self, r: &Ray, t_min: f64, t_max: f64) -> Option<HitRecord<'_>> {
+        self.rect.hit(r, t_min, t_max)
+    }
+
+    fn bounding_box(&self) -> Option<Aabb> {
+        Some(self.rect.bounding_box())
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn rect_intersection() {
+        let material = Arc::new(Lambertian::from(Color::from(0.5, 0.5, 0.5)));
+        let rect = XzRect::from(0f64, 2f64, -1f64, 1f64, 3f64, material);
+
+        let r = Ray::from(Point3::from(0.5, 0f64, 0.5), Vec3::from(0f64, 1f64, 0f64));
+        let rec = rect.hit(&r, 0.001, f64::INFINITY).unwrap();
+        assert_eq!(3f64, rec.t);
+        assert_eq!((0.25, 0.75), (rec.u, rec.v));
+        assert_eq!(Vec3::from(0f64, -1f64, 0f64), rec.normal);
+        assert!(!rec.front_face);
+
+        let outside = Ray::from(Point3::from(-0.5, 0f64, 0.5), Vec3::from(0f64, 1f64, 0f64));
+        assert!(rect.hit(&outside, 0.001, f64::INFINITY).is_none());
+        let parallel = Ray::from(Point3::from(0.5, 3f64, 0.5), Vec3::from(1f64, 0f64, 0f64));
+        assert!(rect.hit(&parallel, 0.001, f64::INFINITY).is_none());
+
+        let bbox = rect.bounding_box().unwrap();
+        assert_eq!(Point3::from(0f64, 3f64 - 0.5e-4, -1f64), bbox.min());
+        assert_eq!(Point3::from(2f64, 3f64 + 0.5e-4, 1f64), bbox.max());
+    }
+}
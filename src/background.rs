@@ -0,0 +1,79 @@
+use std::sync::Arc;
+
+use crate::ray::*;
+use crate::sphere::*;
+use crate::texture::*;
+use crate::vec3::*;
+
+/// The light arriving along rays that leave the scene without hitting anything.
+#[derive(Clone)]
+pub enum Background {
+    /// The same color in every direction. Black leaves the scene lit by its lights alone.
+    Solid(Color),
+    /// Blends from `bottom`, looking straight down, to `top`, looking straight up.
+    Gradient { bottom: Color, top: Color },
+    /// A texture wrapped around the scene, looked up with the `(u, v)` a sphere has in the
+    /// direction of the ray. An image in the equirectangular projection has its left and right
+    /// edges towards -x, its middle towards +x and its top row towards +y.
+    Environment(Arc<dyn Texture>),
+}
+
+impl Default for Background {
+    /// The white to blue sky of the book.
+    fn default() -> Background {
+        Background::Gradient {
+            bottom: Color::from(1f64, 1f64, 1f64),
+            top: Color::from(0.5, 0.7, 1f64),
+        }
+    }
+}
+
+impl Background {
+    pub fn value(&self, r: &Ray) -> Color {
+        match self {
+            Background::Solid(color) => *color,
+            Background::Gradient { bottom, top } => {
+                let unit_direction: Vec3 = r.direction().unit_vector();
+                let t = 0.5 * (unit_direction.y() + 1f64);
+                (1f64 - t) * *bottom + t * *top
+            }
+            Background::Environment(texture) => {
+                let unit_direction = r.direction().unit_vector();
+                let (u, v) = Sphere::get_sphere_uv(&unit_direction);
+                texture.value(u, v, &unit_direction)
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn background_colors() {
+        let up = Ray::from(Point3::new(), Vec3::from(0f64, 2f64, 0f64));
+        let level = Ray::from(Point3::new(), Vec3::from(1f64, 0f64, 0f64));
+        assert_eq!(
+            Color::from(0.5, 0.7, 1f64),
+            Background::default().value(&up)
+        );
+        assert_eq!(
+            Color::from(0.75, 0.85, 1f64),
+            Background::default().value(&level)
+        );
+        assert_eq!(Color::new(), Background::Solid(Color::new()).value(&up));
+
+        // Left and right halves of the texture around the y axis.
+        let sky = Background::Environment(Arc::new(UvCheckerTexture::from(
+            2,
+            1,
+            Arc::new(SolidColor::from(Color::from(1f64, 0f64, 0f64))),
+            Arc::new(SolidColor::from(Color::from(0f64, 0f64, 1f64))),
+        )));
+        let forward = Ray::from(Point3::new(), Vec3::from(0.1, 0f64, -1f64));
+        let back = Ray::from(Point3::new(), Vec3::from(0.1, 0f64, 1f64));
+        assert_eq!(Color::from(0f64, 0f64, 1f64), sky.value(&forward));
+        assert_eq!(Color::from(1f64, 0f64, 0f64), sky.value(&back));
+    }
+}
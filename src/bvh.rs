@@ -0,0 +1,370 @@
+use std::fmt::Display;
+use std::sync::Arc;
+
+use crate::aabb::*;
+use crate::hittable::*;
+use crate::ray::*;
+use crate::vec3::*;
+
+/// Number of buckets the centroids are binned into when looking for the cheapest split.
+const BINS: usize = 12;
+/// Cost of visiting a node, relative to intersecting one primitive.
+const TRAVERSAL_COST: f64 = 1f64;
+/// Leaves may grow this large when splitting does not pay off.
+const MAX_LEAF_SIZE: usize = 8;
+
+/// Size and shape of a [`Bvh`].
+#[derive(Debug, Clone, Copy, Default, PartialEq)]
+pub struct BvhStats {
+    pub primitives: usize,
+    pub nodes: usize,
+    pub leaves: usize,
+    /// Number of nodes on the longest path from the root to a leaf.
+    pub max_depth: usize,
+}
+
+impl Display for BvhStats {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(
+            f,
+            "{} primitives, {} nodes ({} leaves), depth {}",
+            self.primitives, self.nodes, self.leaves, self.max_depth
+        )
+    }
+}
+
+#[derive(Debug, Clone, Copy)]
+struct BvhNode {
+    bbox: Aabb,
+    /// For leaves, the first entry in `indices`; for interior nodes, the right child. The left
+    /// child always directly follows its parent.
+    offset: u32,
+    /// Number of primitives in a leaf, 0 for interior nodes.
+    count: u32,
+    /// Axis an interior node was split along.
+    axis: u8,
+}
+
+/// Bounding volume hierarchy over primitives known only by their bounding boxes, built with the
+/// surface area heuristic. Primitives are referred to by their index in the slice it was built
+/// from.
+#[derive(Debug, Clone, Default)]
+pub struct Bvh {
+    nodes: Vec<BvhNode>,
+    indices: Vec<u32>,
+    stats: BvhStats,
+}
+
+impl Bvh {
+    pub fn build(boxes: &[Aabb]) -> Bvh {
+        let mut bvh = Bvh {
+            nodes: Vec::with_capacity(2 * boxes.len()),
+            indices: (0..boxes.len() as u32).collect(),
+            stats: BvhStats {
+                primitives: boxes.len(),
+                ..BvhStats::default()
+            },
+        };
+        if !boxes.is_empty() {
+            let centroids: Vec<Point3> = boxes.iter().map(Aabb::centroid).collect();
+            let mut indices = std::mem::take(&mut bvh.indices);
+            bvh.build_node(boxes, &centroids, &mut indices, 0, 1);
+            bvh.indices = indices;
+        }
+        bvh.stats.nodes = bvh.nodes.len();
+        bvh
+    }
+
+    pub fn stats(&self) -> BvhStats {
+        self.stats
+    }
+
+    /// Box around every primitive, `None` if there are none.
+    pub fn bounding_box(&self) -> Option<Aabb> {
+        self.nodes.first().map(|node| node.bbox)
+    }
+
+    fn build_node(
+        &mut self,
+        boxes: &[Aabb],
+        centroids: &[Point3],
+        indices: &mut [u32],
+        offset: usize,
+        depth: usize,
+    ) {
+        let bbox = indices.iter().fold(Aabb::new(), |b, &i| {
+            Aabb::surrounding_box(&b, &boxes[i as usize])
+        });
+        let node_index = self.nodes.len();
+        self.nodes.push(BvhNode {
+            bbox,
+            offset: offset as u32,
+            count: indices.len() as u32,
+            axis: 0,
+        });
+        self.stats.max_depth = self.stats.max_depth.max(depth);
+        if indices.len() <= 2 {
+            self.stats.leaves += 1;
+            return;
+        }
+
+        let centroid_box = Aabb::from_points(indices.iter().map(|&i| centroids[i as usize]));
+        let split = find_split(boxes, centroids, indices, &bbox, &centroid_box);
+        let mid = match split {
+            Some((axis, bin, cost))
+                if cost < indices.len() as f64 || indices.len() > MAX_LEAF_SIZE =>
+            {
+                let (min, extent) = (
+                    centroid_box.min()[axis],
+                    centroid_box.max()[axis] - centroid_box.min()[axis],
+                );
+                let mid = partition(indices, |i| {
+                    bin_of(centroids[i as usize][axis], min, extent) < bin
+                });
+                self.nodes[node_index].axis = axis as u8;
+                mid
+            }
+            // Every centroid coincides: split the list in half to keep leaves small.
+            None if indices.len() > MAX_LEAF_SIZE => indices.len() / 2,
+            _ => {
+                self.stats.leaves += 1;
+                return;
+            }
+        };
+
+        let (left, right) = indices.split_at_mut(mid);
+        self.build_node(boxes, centroids, left, offset, depth + 1);
+        let right_index = self.nodes.len();
+        self.build_node(boxes, centroids, right, offset + mid, depth + 1);
+        let node = &mut self.nodes[node_index];
+        node.offset = right_index as u32;
+        node.count = 0;
+    }
+
+    /// Finds the closest hit, calling `hit_primitive(index, t_max)` for the primitives whose
+    /// boxes the ray enters, nearest nodes first.
+    pub fn hit<'a, F>(
+        &self,
+        r: &Ray,
+        t_min: f64,
+        t_max: f64,
+        mut hit_primitive: F,
+    ) -> Option<HitRecord<'a>>
+    where
+        F: FnMut(usize, f64) -> Option<HitRecord<'a>>,
+    {
+        if self.nodes.is_empty() {
+            return None;
+        }
+        let mut closest_so_far = t_max;
+        let mut hit_record = None;
+        let mut stack = vec![0usize];
+
+        while let Some(node_index) = stack.pop() {
+            let node = &self.nodes[node_index];
+            if !node.bbox.hit(r, t_min, closest_so_far) {
+                continue;
+            }
+            if node.count > 0 {
+                let start = node.offset as usize;
+                for &index in &self.indices[start..start + node.count as usize] {
+                    if let Some(rec) = hit_primitive(index as usize, closest_so_far) {
+                        closest_so_far = rec.t;
+                        hit_record = Some(rec);
+                    }
+                }
+            } else if r.direction()[node.axis as i32] < 0f64 {
+                // Visit the right child, holding the larger coordinates, first.
+                stack.push(node_index + 1);
+                stack.push(node.offset as usize);
+            } else {
+                stack.push(node.offset as usize);
+                stack.push(node_index + 1);
+            }
+        }
+
+        hit_record
+    }
+}
+
+fn bin_of(coordinate: f64, min: f64, extent: f64) -> usize {
+    (((coordinate - min) / extent * BINS as f64) as usize).min(BINS - 1)
+}
+
+/// Returns the axis, the first bin of the right side and the cost of the cheapest binned split,
+/// in units of primitive intersections.
+fn find_split(
+    boxes: &[Aabb],
+    centroids: &[Point3],
+    indices: &[u32],
+    bbox: &Aabb,
+    centroid_box: &Aabb,
+) -> Option<(i32, usize, f64)> {
+    let mut best: Option<(i32, usize, f64)> = None;
+    for axis in 0..3 {
+        let min = centroid_box.min()[axis];
+        let extent = centroid_box.max()[axis] - min;
+        if extent <= 0f64 {
+            continue;
+        }
+
+        let mut bins = [(Aabb::new(), 0usize); BINS];
+        for &i in indices {
+            let bin = &mut bins[bin_of(centroids[i as usize][axis], min, extent)];
+            bin.0 = Aabb::surrounding_box(&bin.0, &boxes[i as usize]);
+            bin.1 += 1;
+        }
+
+        // Sweep from the right to know the cost of every right side, then from the left.
+        let mut right_costs = [0f64; BINS];
+        let (mut right_box, mut right_count) = (Aabb::new(), 0);
+        for bin in (1..BINS).rev() {
+            right_box = Aabb::surrounding_box(&right_box, &bins[bin].0);
+            right_count += bins[bin].1;
+            right_costs[bin] = right_box.surface_area() * right_count as f64;
+        }
+        let (mut left_box, mut left_count) = (Aabb::new(), 0);
+        for bin in 1..BINS {
+            left_box = Aabb::surrounding_box(&left_box, &bins[bin - 1].0);
+            left_count += bins[bin - 1].1;
+            if left_count == 0 || left_count == indices.len() {
+                continue;
+            }
+            let cost = TRAVERSAL_COST
+                + (left_box.surface_area() * left_count as f64 + right_costs[bin])
+                    / bbox.surface_area();
+            if best.is_none_or(|(_, _, best_cost)| cost < best_cost) {
+                best = Some((axis, bin, cost));
+            }
+        }
+    }
+    best
+}
+
+/// Moves the indices matching `left` to the front and returns how many there are.
+fn partition<F: Fn(u32) -> bool>(indices: &mut [u32], left: F) -> usize {
+    let mut mid = 0;
+    for i in 0..indices.len() {
+        if left(indices[i]) {
+            indices.swap(i, mid);
+            mid += 1;
+        }
+    }
+    mid
+}
+
+/// A set of objects behind a [`Bvh`]. Objects without a bounding box, such as planes, are kept
+/// aside and tested one by one.
+pub struct BvhList {
+    objects: Vec<Arc<dyn Hittable>>,
+    unbounded: Vec<Arc<dyn Hittable>>,
+    bvh: Bvh,
+}
+
+impl BvhList {
+    pub fn from(objects: &[Arc<dyn Hittable>]) -> BvhList {
+        let mut bounded = Vec::new();
+        let mut boxes = Vec::new();
+        let mut unbounded = Vec::new();
+        for object in objects {
+            match object.bounding_box() {
+                Some(bbox) => {
+                    bounded.push(object.clone());
+                    boxes.push(bbox);
+                }
+                None => unbounded.push(object.clone()),
+            }
+        }
+        BvhList {
+            objects: bounded,
+            unbounded,
+            bvh: Bvh::build(&boxes),
+        }
+    }
+
+    pub fn stats(&self) -> BvhStats {
+        self.bvh.stats()
+    }
+}
+
+impl Hittable for BvhList {
+    fn hit(&self, r: &Ray, t_min: f64, t_max: f64) -> Option<HitRecord<'_>> {
+        let mut closest_so_far = t_max;
+        let mut hit_record = None;
+
+        for object in &self.unbounded {
+            if let Some(rec) = object.hit(r, t_min, closest_so_far) {
+                closest_so_far = rec.t;
+                hit_record = Some(rec);
+            }
+        }
+
+        self.bvh
+            .hit(r, t_min, closest_so_far, |index, t_max| {
+                self.objects[index].hit(r, t_min, t_max)
+            })
+            .or(hit_record)
+    }
+
+    fn bounding_box(&self) -> Option<Aabb> {
+        if self.unbounded.is_empty() {
+            self.bvh.bounding_box()
+        } else {
+            None
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::hittable_list::*;
+    use crate::material::*;
+    use crate::rng::*;
+    use crate::sphere::*;
+
+    #[test]
+    fn matches_linear_search() {
+        seed(7);
+        let material = Arc::new(Lambertian::from(Color::from(0.5, 0.5, 0.5)));
+        let mut world = HittableList::new();
+        for _ in 0..500 {
+            let center = Point3::random_range(-10f64, 10f64);
+            world.add(Arc::new(Sphere::from(
+                center,
+                random_double_range(0.05, 0.5),
+                material.clone(),
+            )));
+        }
+        let bvh = BvhList::from(world.objects());
+
+        let stats = bvh.stats();
+        assert_eq!(500, stats.primitives);
+        assert_eq!(2 * stats.leaves - 1, stats.nodes);
+        assert!(stats.max_depth < 30);
+        assert_eq!(world.bounding_box(), bvh.bounding_box());
+
+        for _ in 0..1000 {
+            let r = Ray::from(
+                Point3::random_range(-12f64, 12f64),
+                Vec3::random_unit_vector(),
+            );
+            let expected = world
+                .hit(&r, 0.001, f64::INFINITY)
+                .map(|rec| (rec.t, rec.p));
+            let actual = bvh.hit(&r, 0.001, f64::INFINITY).map(|rec| (rec.t, rec.p));
+            assert_eq!(expected, actual);
+        }
+    }
+
+    #[test]
+    fn degenerate_input() {
+        assert_eq!(BvhStats::default(), Bvh::build(&[]).stats());
+
+        // Identical boxes cannot be separated but still end up in small leaves.
+        let bbox = Aabb::from(Point3::new(), Point3::from(1f64, 1f64, 1f64));
+        let stats = Bvh::build(&[bbox; 100]).stats();
+        assert_eq!(100, stats.primitives);
+        assert!(stats.leaves >= 100 / MAX_LEAF_SIZE);
+    }
+}
@@ -0,0 +1,214 @@
+use crate::ray::*;
+use crate::rng::*;
+use crate::vec3::*;
+
+pub struct Camera {
+    origin: Point3,
+    lower_left_corner: Point3,
+    horizontal: Vec3,
+    vertical: Vec3,
+    u: Vec3,
+    v: Vec3,
+    lens_radius: f64,
+    /// Shutter open and close times.
+    time0: f64,
+    time1: f64,
+}
+
+impl Camera {
+    /// Builds a thin lens camera at `lookfrom` looking towards `lookat`, with `vup` fixing the roll.
+    ///
+    /// `vfov` is the vertical field of view in degrees and `aspect_ratio` is width over height.
+    /// Objects at `focus_dist` from the lens are in perfect focus; the blur elsewhere grows with
+    /// `aperture`, the lens diameter. An aperture of 0 gives a pinhole camera.
+    pub fn from(
+        lookfrom: Point3,
+        lookat: Point3,
+        vup: Vec3,
+        vfov: f64,
+        aspect_ratio: f64,
+        aperture: f64,
+        focus_dist: f64,
+    ) -> Camera {
+        let theta = vfov.to_radians();
+        let h = (theta / 2f64).tan();
+        let viewport_height = 2f64 * h;
+        let viewport_width = aspect_ratio * viewport_height;
+
+        let w = (lookfrom - lookat).unit_vector();
+        let u = Vec3::cross(&vup, &w).unit_vector();
+        let v = Vec3::cross(&w, &u);
+
+        let origin = lookfrom;
+        let horizontal = focus_dist * viewport_width * u;
+        let vertical = focus_dist * viewport_height * v;
+        let lower_left_corner = origin - horizontal / 2f64 - vertical / 2f64 - focus_dist * w;
+
+        Camera {
+            origin,
+            lower_left_corner,
+            horizontal,
+            vertical,
+            u,
+            v,
+            lens_radius: aperture / 2f64,
+            time0: 0f64,
+            time1: 0f64,
+        }
+    }
+
+    /// Keeps the shutter open from `open` to `close`, casting each ray at a random time in
+    /// between so that moving objects are blurred. By default every ray is cast at time 0.
+    pub fn with_shutter(mut self, open: f64, close: f64) -> Camera {
+        self.time0 = open;
+        self.time1 = close;
+        self
+    }
+
+    /// Returns a ray through the viewport point `(s, t)`, where `(0, 0)` is the lower left corner
+    /// and `(1, 1)` the upper right one. The origin is sampled on the lens disk and the time
+    /// within the shutter interval.
+    pub fn get_ray(&self, s: f64, t: f64) -> Ray {
+        let rd = self.lens_radius * Vec3::random_in_unit_disk();
+        let offset = self.u * *rd.x() + self.v * *rd.y();
+        // An instantaneous shutter draws no random number, leaving still renders unchanged.
+        let time = if self.time1 > self.time0 {
+            random_double_range(self.time0, self.time1)
+        } else {
+            self.time0
+        };
+
+        Ray::from_time(
+            self.origin + offset,
+            self.lower_left_corner + s * self.horizontal + t * self.vertical - self.origin - offset,
+            time,
+        )
+    }
+
+    /// Like [`Camera::get_ray`] but without any sampling: the ray leaves the center of the lens
+    /// when the shutter opens.
+    pub fn get_pinhole_ray(&self, s: f64, t: f64) -> Ray {
+        Ray::from_time(
+            self.origin,
+            self.lower_left_corner + s * self.horizontal + t * self.vertical - self.origin,
+            self.time0,
+        )
+    }
+}
+
+/// Everything needed to build a [`Camera`] except the aspect ratio, which comes from the image.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct CameraSettings {
+    pub lookfrom: Point3,
+    pub lookat: Point3,
+    pub vup: Vec3,
+    /// Vertical field of view in degrees.
+    pub vfov: f64,
+    pub aperture: f64,
+    /// Distance to the plane in focus; `None` focuses on `lookat`.
+    pub focus_dist: Option<f64>,
+    /// Times at which the shutter opens and closes.
+    pub shutter: (f64, f64),
+}
+
+impl CameraSettings {
+    pub fn build(&self, aspect_ratio: f64) -> Camera {
+        let focus_dist = self
+            .focus_dist
+            .unwrap_or_else(|| (self.lookfrom - self.lookat).length());
+        Camera::from(
+            self.lookfrom,
+            self.lookat,
+            self.vup,
+            self.vfov,
+            aspect_ratio,
+            self.aperture,
+            focus_dist,
+        )
+        .with_shutter(self.shutter.0, self.shutter.1)
+    }
+}
+
+impl Default for CameraSettings {
+    fn default() -> Self {
+        CameraSettings {
+            lookfrom: Point3::from(0f64, 0f64, 0f64),
+            lookat: Point3::from(0f64, 0f64, -1f64),
+            vup: Vec3::from(0f64, 1f64, 0f64),
+            vfov: 90f64,
+            aperture: 0f64,
+            focus_dist: None,
+            shutter: (0f64, 0f64),
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::Camera;
+    use crate::vec3::*;
+
+    #[test]
+    fn frames_lookat() {
+        let lookfrom = Point3::from(3f64, 3f64, 2f64);
+        let lookat = Point3::from(0f64, 0f64, -1f64);
+        let vup = Vec3::from(0f64, 1f64, 0f64);
+        let camera = Camera::from(lookfrom, lookat, vup, 90f64, 2f64, 0f64, 1f64);
+
+        let center = camera.get_ray(0.5, 0.5);
+        assert_eq!(lookfrom, center.origin());
+        let dir = center.direction().unit_vector();
+        let expected = (lookat - lookfrom).unit_vector();
+        assert!((dir - expected).length() < 1e-12);
+
+        // A 90 degree field of view puts the top edge 45 degrees above the view direction.
+        let top = camera.get_ray(0.5, 1f64).direction().unit_vector();
+        assert!((Vec3::dot(&top, &expected) - 0.5f64.sqrt()).abs() < 1e-12);
+    }
+
+    #[test]
+    fn lens_rays_converge_on_focus_plane() {
+        let lookfrom = Point3::from(0f64, 0f64, 0f64);
+        let lookat = Point3::from(0f64, 0f64, -1f64);
+        let vup = Vec3::from(0f64, 1f64, 0f64);
+        let camera = Camera::from(lookfrom, lookat, vup, 40f64, 1.5, 2f64, 10f64);
+
+        let focus_point = camera.get_ray(0.3, 0.8).at(1f64);
+        for _ in 0..16 {
+            let r = camera.get_ray(0.3, 0.8);
+            assert!((r.origin() - lookfrom).length() <= 1f64);
+            assert_eq!(0f64, *r.origin().z());
+            assert!((r.at(1f64) - focus_point).length() < 1e-9);
+        }
+    }
+
+    #[test]
+    fn samples_shutter_interval() {
+        let lookfrom = Point3::from(0f64, 0f64, 0f64);
+        let lookat = Point3::from(0f64, 0f64, -1f64);
+        let vup = Vec3::from(0f64, 1f64, 0f64);
+        let camera = Camera::from(lookfrom, lookat, vup, 90f64, 1f64, 0f64, 1f64);
+        assert_eq!(0f64, camera.get_ray(0.5, 0.5).time());
+
+        let camera = camera.with_shutter(0.25, 0.75);
+        let times: Vec<f64> = (0..64).map(|_| camera.get_ray(0.5, 0.5).time()).collect();
+        assert!(times.iter().all(|t| (0.25..0.75).contains(t)));
+        assert!(times.iter().any(|&t| t != times[0]));
+    }
+
+    #[test]
+    fn pinhole_rays_are_fixed() {
+        let lookfrom = Point3::from(0f64, 0f64, 0f64);
+        let lookat = Point3::from(0f64, 0f64, -1f64);
+        let vup = Vec3::from(0f64, 1f64, 0f64);
+        let camera =
+            Camera::from(lookfrom, lookat, vup, 40f64, 1.5, 2f64, 10f64).with_shutter(0.25, 0.75);
+
+        let r = camera.get_pinhole_ray(0.3, 0.8);
+        assert_eq!(lookfrom, r.origin());
+        assert_eq!(0.25, r.time());
+        assert!((r.at(1f64) - camera.get_ray(0.3, 0.8).at(1f64)).length() < 1e-9);
+        let same = camera.get_pinhole_ray(0.3, 0.8);
+        assert_eq!((r.direction(), r.time()), (same.direction(), same.time()));
+    }
+}
@@ -0,0 +1,567 @@
+use std::fmt::Display;
+
+use crate::color::*;
+use crate::exr::*;
+use crate::image::*;
+use crate::sampler::*;
+use crate::vec3::*;
+
+pub const USAGE: &str = "\
+Usage: ray_tracing [OPTIONS]
+
+Renders a scene and writes the image to --output, or as ASCII PPM to stdout.
+
+Image:
+  --width <PIXELS>         Image width [default: 400]
+  --height <PIXELS>        Image height [default: width / aspect ratio]
+  --aspect-ratio <RATIO>   Width over height, as a number or W:H [default: 16:9]
+  --output <FILE>          Output file, whose extension picks the format:
+                           .ppm, .pfm, .png or .exr
+  --tone-map <OPERATOR>    clamp, reinhard, aces or exposure [default: clamp]
+  --exposure <VALUE>       Strength of the exposure tone map [default: 1]
+  --transfer <FUNCTION>    linear, srgb or gamma [default: srgb]
+  --gamma <VALUE>          Exponent of the gamma transfer function [default: 2.2]
+  --exr-type <TYPE>        Pixel type of .exr files: half or float [default: half]
+  --exr-compression <METHOD>
+                           Compression of .exr files: none or zip [default: zip]
+  --aovs <BOOL>            Write the depth and normal channels to .exr files:
+                           true or false [default: true]
+
+Rendering:
+  --spp <COUNT>            Samples per pixel [default: 100]
+  --max-depth <BOUNCES>    Maximum number of bounces per path [default: 50]
+  --rr-depth <BOUNCES>     Bounce from which Russian roulette starts [default: 3]
+  --sampler <NAME>         uniform, stratified, halton or sobol [default: stratified]
+  --seed <SEED>            Random seed [default: 0]
+  --threads <COUNT>        Worker threads, 0 for all cores [default: 0]
+  --tile-size <PIXELS>     Edge length of the render tiles [default: 16]
+
+Scene:
+  --scene <NAME|FILE>      Built-in scene (three-spheres, random-spheres or
+                           cornell-box) or a .toml scene file [default: three-spheres]
+  --lookfrom <X,Y,Z>       Camera position
+  --lookat <X,Y,Z>         Point the camera looks at
+  --vup <X,Y,Z>            Camera up direction
+  --vfov <DEGREES>         Vertical field of view
+  --aperture <DIAMETER>    Lens diameter, 0 for a pinhole camera
+  --focus-dist <DISTANCE>  Distance to the plane in focus [default: distance to lookat]
+  --shutter <OPEN,CLOSE>   Shutter interval for motion blur [default: 0,0]
+
+  -v, --verbose            Print statistics about the scene
+  -h, --help               Print this help
+";
+
+const OPTIONS: [&str; 31] = [
+    "--width",
+    "--height",
+    "--aspect-ratio",
+    "--output",
+    "--tone-map",
+    "--exposure",
+    "--transfer",
+    "--gamma",
+    "--exr-type",
+    "--exr-compression",
+    "--aovs",
+    "--spp",
+    "--max-depth",
+    "--rr-depth",
+    "--sampler",
+    "--seed",
+    "--threads",
+    "--tile-size",
+    "--scene",
+    "--lookfrom",
+    "--lookat",
+    "--vup",
+    "--vfov",
+    "--aperture",
+    "--focus-dist",
+    "--shutter",
+    "--verbose",
+    "-v",
+    "--help",
+    "-h",
+    "--",
+];
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum CliError {
+    UnknownOption {
+        option: String,
+        suggestion: Option<&'static str>,
+    },
+    MissingValue(String),
+    InvalidValue {
+        option: String,
+        value: String,
+        expected: String,
+    },
+    Conflict(String),
+}
+
+impl Display for CliError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            CliError::UnknownOption {
+                option,
+                suggestion: Some(suggestion),
+            } => write!(f, "unknown option '{option}', did you mean '{suggestion}'?"),
+            CliError::UnknownOption { option, .. } => write!(f, "unknown option '{option}'"),
+            CliError::MissingValue(option) => write!(f, "option '{option}' needs a value"),
+            CliError::InvalidValue {
+                option,
+                value,
+                expected,
+            } => write!(
+                f,
+                "invalid value '{value}' for '{option}': expected {expected}"
+            ),
+            CliError::Conflict(message) => f.write_str(message),
+        }
+    }
+}
+
+impl std::error::Error for CliError {}
+
+/// Render settings given on the command line or in a scene file. Anything left out is `None` and
+/// falls back to the scene or to the defaults listed in [`USAGE`].
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct Options {
+    pub help: bool,
+    pub verbose: bool,
+    pub width: Option<u32>,
+    pub height: Option<u32>,
+    pub aspect_ratio: Option<f64>,
+    pub output: Option<String>,
+    pub tone_map: Option<ToneMap>,
+    pub transfer: Option<Transfer>,
+    pub exr_pixel_type: Option<ExrPixelType>,
+    pub exr_compression: Option<ExrCompression>,
+    pub aovs: Option<bool>,
+    pub samples_per_pixel: Option<u32>,
+    pub max_depth: Option<u32>,
+    pub rr_depth: Option<u32>,
+    pub sampler: Option<SamplerKind>,
+    pub seed: Option<u64>,
+    pub threads: Option<usize>,
+    pub tile_size: Option<u32>,
+    pub scene: Option<String>,
+    pub lookfrom: Option<Point3>,
+    pub lookat: Option<Point3>,
+    pub vup: Option<Vec3>,
+    pub vfov: Option<f64>,
+    pub aperture: Option<f64>,
+    pub focus_dist: Option<f64>,
+    pub shutter: Option<(f64, f64)>,
+}
+
+impl Options {
+    /// Parses the arguments, not including the program name.
+    pub fn parse<I: IntoIterator<Item = String>>(args: I) -> Result<Options, CliError> {
+        let mut options = Options::default();
+        let mut tone_map = None;
+        let mut exposure = None;
+        let mut transfer = None;
+        let mut gamma = None;
+
+        let mut args = args.into_iter();
+        while let Some(arg) = args.next() {
+            if arg == "-h" || arg == "--help" {
+                options.help = true;
+                continue;
+            }
+            if arg == "-v" || arg == "--verbose" {
+                options.verbose = true;
+                continue;
+            }
+
+            // Both `--option value` and `--option=value` are accepted.
+            let (option, inline_value) = match arg.split_once('=') {
+                Some((option, value)) if option.starts_with("--") => {
+                    (option.to_string(), Some(value.to_string()))
+                }
+                _ => (arg.clone(), None),
+            };
+            if !OPTIONS.contains(&option.as_str()) || option == "--" {
+                return Err(CliError::UnknownOption {
+                    suggestion: suggest(&option),
+                    option,
+                });
+            }
+            let value = match inline_value.or_else(|| args.next()) {
+                Some(value) => Arg {
+                    option: &option,
+                    value,
+                },
+                None => return Err(CliError::MissingValue(option)),
+            };
+
+            match option.as_str() {
+                "--width" => options.width = Some(value.positive_integer()?),
+                "--height" => options.height = Some(value.positive_integer()?),
+                "--aspect-ratio" => options.aspect_ratio = Some(value.ratio()?),
+                "--output" => {
+                    if ImageFormat::from_path(&value.value).is_none() {
+                        return Err(value.invalid("a file ending in .ppm, .pfm, .png or .exr"));
+                    }
+                    options.output = Some(value.value);
+                }
+                "--tone-map" => {
+                    tone_map = Some(value.one_of(&["clamp", "reinhard", "aces", "exposure"])?)
+                }
+                "--exposure" => exposure = Some(value.positive_number()?),
+                "--transfer" => transfer = Some(value.one_of(&["linear", "srgb", "gamma"])?),
+                "--gamma" => gamma = Some(value.positive_number()?),
+                "--exr-type" => {
+                    let name = value.one_of(&ExrPixelType::NAMES)?;
+                    options.exr_pixel_type = ExrPixelType::from_name(name);
+                }
+                "--exr-compression" => {
+                    let name = value.one_of(&ExrCompression::NAMES)?;
+                    options.exr_compression = ExrCompression::from_name(name);
+                }
+                "--aovs" => options.aovs = Some(value.one_of(&["true", "false"])? == "true"),
+                "--spp" => options.samples_per_pixel = Some(value.positive_integer()?),
+                "--max-depth" => options.max_depth = Some(value.positive_integer()?),
+                "--rr-depth" => options.rr_depth = Some(value.integer()?),
+                "--sampler" => {
+                    let name = value.one_of(&SamplerKind::NAMES)?;
+                    options.sampler = SamplerKind::from_name(name);
+                }
+                "--seed" => options.seed = Some(value.integer()?),
+                "--threads" => options.threads = Some(value.integer()?),
+                "--tile-size" => options.tile_size = Some(value.positive_integer()?),
+                "--scene" => options.scene = Some(value.value),
+                "--lookfrom" => options.lookfrom = Some(value.vector()?),
+                "--lookat" => options.lookat = Some(value.vector()?),
+                "--vup" => {
+                    let vup = value.vector()?;
+                    if vup.near_zero() {
+                        return Err(value.invalid("a non zero vector"));
+                    }
+                    options.vup = Some(vup);
+                }
+                "--vfov" => {
+                    let vfov = value.number()?;
+                    if !(vfov > 0f64 && vfov < 180f64) {
+                        return Err(value.invalid("an angle between 0 and 180 degrees"));
+                    }
+                    options.vfov = Some(vfov);
+                }
+                "--aperture" => {
+                    let aperture = value.number()?;
+                    if aperture < 0f64 {
+                        return Err(value.invalid("a non negative number"));
+                    }
+                    options.aperture = Some(aperture);
+                }
+                "--focus-dist" => options.focus_dist = Some(value.positive_number()?),
+                "--shutter" => options.shutter = Some(value.interval()?),
+                _ => unreachable!(),
+            }
+        }
+
+        if options.width.is_some() && options.height.is_some() && options.aspect_ratio.is_some() {
+            return Err(CliError::Conflict(
+                "'--width', '--height' and '--aspect-ratio' cannot all be given".to_string(),
+            ));
+        }
+
+        options.tone_map = match (tone_map, exposure) {
+            (None | Some("exposure"), Some(exposure)) => Some(ToneMap::Exposure(exposure)),
+            (Some(_), Some(_)) => {
+                return Err(CliError::Conflict(
+                    "'--exposure' only applies to '--tone-map exposure'".to_string(),
+                ))
+            }
+            (Some("exposure"), None) => Some(ToneMap::Exposure(1f64)),
+            (Some("reinhard"), None) => Some(ToneMap::Reinhard),
+            (Some("aces"), None) => Some(ToneMap::Aces),
+            (Some(_), None) => Some(ToneMap::Clamp),
+            (None, None) => None,
+        };
+        options.transfer = match (transfer, gamma) {
+            (None | Some("gamma"), Some(gamma)) => Some(Transfer::Gamma(gamma)),
+            (Some(_), Some(_)) => {
+                return Err(CliError::Conflict(
+                    "'--gamma' only applies to '--transfer gamma'".to_string(),
+                ))
+            }
+            (Some("gamma"), None) => Some(Transfer::Gamma(2.2)),
+            (Some("linear"), None) => Some(Transfer::Linear),
+            (Some(_), None) => Some(Transfer::Srgb),
+            (None, None) => None,
+        };
+
+        Ok(options)
+    }
+
+    /// Fills every setting left out here from `fallback`.
+    ///
+    /// The image size is taken as a whole: a width or height given here drops the fallback's
+    /// width, height and aspect ratio, so the missing dimension is worked out again.
+    pub fn or(self, fallback: Options) -> Options {
+        let (width, height, aspect_ratio) = if self.width.is_some() || self.height.is_some() {
+            (self.width, self.height, self.aspect_ratio)
+        } else {
+            let aspect_ratio = self.aspect_ratio.or(fallback.aspect_ratio);
+            (fallback.width, fallback.height, aspect_ratio)
+        };
+        Options {
+            help: self.help || fallback.help,
+            verbose: self.verbose || fallback.verbose,
+            width,
+            height,
+            aspect_ratio,
+            output: self.output.or(fallback.output),
+            tone_map: self.tone_map.or(fallback.tone_map),
+            transfer: self.transfer.or(fallback.transfer),
+            exr_pixel_type: self.exr_pixel_type.or(fallback.exr_pixel_type),
+            exr_compression: self.exr_compression.or(fallback.exr_compression),
+            aovs: self.aovs.or(fallback.aovs),
+            samples_per_pixel: self.samples_per_pixel.or(fallback.samples_per_pixel),
+            max_depth: self.max_depth.or(fallback.max_depth),
+            rr_depth: self.rr_depth.or(fallback.rr_depth),
+            sampler: self.sampler.or(fallback.sampler),
+            seed: self.seed.or(fallback.seed),
+            threads: self.threads.or(fallback.threads),
+            tile_size: self.tile_size.or(fallback.tile_size),
+            scene: self.scene.or(fallback.scene),
+            lookfrom: self.lookfrom.or(fallback.lookfrom),
+            lookat: self.lookat.or(fallback.lookat),
+            vup: self.vup.or(fallback.vup),
+            vfov: self.vfov.or(fallback.vfov),
+            aperture: self.aperture.or(fallback.aperture),
+            focus_dist: self.focus_dist.or(fallback.focus_dist),
+            shutter: self.shutter.or(fallback.shutter),
+        }
+    }
+}
+
+/// The value given to an option, with the validating conversions the options need.
+struct Arg<'a> {
+    option: &'a str,
+    value: String,
+}
+
+impl Arg<'_> {
+    fn invalid(&self, expected: &str) -> CliError {
+        CliError::InvalidValue {
+            option: self.option.to_string(),
+            value: self.value.clone(),
+            expected: expected.to_string(),
+        }
+    }
+
+    fn integer<T: std::str::FromStr>(&self) -> Result<T, CliError> {
+        self.value
+            .parse()
+            .map_err(|_| self.invalid("a non negative integer"))
+    }
+
+    fn positive_integer(&self) -> Result<u32, CliError> {
+        match self.value.parse() {
+            Ok(n) if n > 0 => Ok(n),
+            _ => Err(self.invalid("a positive integer")),
+        }
+    }
+
+    fn number(&self) -> Result<f64, CliError> {
+        match self.value.parse::<f64>() {
+            Ok(x) if x.is_finite() => Ok(x),
+            _ => Err(self.invalid("a number")),
+        }
+    }
+
+    fn positive_number(&self) -> Result<f64, CliError> {
+        match self.number() {
+            Ok(x) if x > 0f64 => Ok(x),
+            _ => Err(self.invalid("a positive number")),
+        }
+    }
+
+    /// Accepts either a number or `width:height`.
+    fn ratio(&self) -> Result<f64, CliError> {
+        let ratio = match self.value.split_once(':') {
+            Some((w, h)) => match (w.trim().parse::<f64>(), h.trim().parse::<f64>()) {
+                (Ok(w), Ok(h)) => w / h,
+                _ => f64::NAN,
+            },
+            None => self.value.parse().unwrap_or(f64::NAN),
+        };
+        if ratio.is_finite() && ratio > 0f64 {
+            Ok(ratio)
+        } else {
+            Err(self.invalid("a positive ratio such as 1.5 or 16:9"))
+        }
+    }
+
+    fn vector(&self) -> Result<Vec3, CliError> {
+        let components: Vec<f64> = self
+            .value
+            .split(',')
+            .filter_map(|c| c.trim().parse().ok())
+            .filter(|c: &f64| c.is_finite())
+            .collect();
+        match components[..] {
+            [x, y, z] if self.value.split(',').count() == 3 => Ok(Vec3::from(x, y, z)),
+            _ => Err(self.invalid("three comma separated numbers such as 0,1,-2.5")),
+        }
+    }
+
+    /// Two comma separated numbers, the first not larger than the second.
+    fn interval(&self) -> Result<(f64, f64), CliError> {
+        let bounds = self
+            .value
+            .split_once(',')
+            .map(|(a, b)| (a.trim().parse::<f64>(), b.trim().parse::<f64>()));
+        match bounds {
+            Some((Ok(a), Ok(b))) if a.is_finite() && b.is_finite() && a <= b => Ok((a, b)),
+            _ => Err(self.invalid("two comma separated numbers such as 0,1 in increasing order")),
+        }
+    }
+
+    fn one_of<'a>(&self, names: &[&'a str]) -> Result<&'a str, CliError> {
+        names
+            .iter()
+            .find(|&&name| name == self.value)
+            .copied()
+            .ok_or_else(|| self.invalid(&format!("one of {}", names.join(", "))))
+    }
+}
+
+/// Returns the known option closest to `option`, if it is close enough to be a typo.
+fn suggest(option: &str) -> Option<&'static str> {
+    OPTIONS
+        .iter()
+        .filter(|known| known.len() > 2)
+        .map(|known| (edit_distance(option, known), *known))
+        .filter(|(distance, _)| *distance <= 2)
+        .min_by_key(|(distance, _)| *distance)
+        .map(|(_, known)| known)
+}
+
+/// Levenshtein distance between two strings.
+fn edit_distance(a: &str, b: &str) -> usize {
+    let b: Vec<char> = b.chars().collect();
+    let mut row: Vec<usize> = (0..=b.len()).collect();
+    for (i, ca) in a.chars().enumerate() {
+        let mut diagonal = row[0];
+        row[0] = i + 1;
+        for (j, &cb) in b.iter().enumerate() {
+            let substitution = diagonal + (ca != cb) as usize;
+            diagonal = row[j + 1];
+            row[j + 1] = substitution.min(row[j] + 1).min(row[j + 1] + 1);
+        }
+    }
+    row[b.len()]
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    fn parse(args: &str) -> Result<Options, CliError> {
+        Options::parse(args.split_whitespace().map(String::from))
+    }
+
+    #[test]
+    fn parses_options() {
+        let options = parse(
+            "--width 1920 --spp 256 --max-depth 50 --scene random-spheres --output out.exr \
+             --seed 7 --threads 8 --aspect-ratio=4:3 --lookfrom 13,2,3 --vfov 20 \
+             --tone-map aces --transfer gamma --gamma 2.4 --sampler sobol --shutter 0,0.5 \
+             --exr-type float --exr-compression none --aovs false",
+        )
+        .unwrap();
+        assert_eq!(Some(1920), options.width);
+        assert_eq!(Some(256), options.samples_per_pixel);
+        assert_eq!(Some(50), options.max_depth);
+        assert_eq!(Some("random-spheres".to_string()), options.scene);
+        assert_eq!(Some("out.exr".to_string()), options.output);
+        assert_eq!(Some(7), options.seed);
+        assert_eq!(Some(8), options.threads);
+        assert_eq!(Some(4f64 / 3f64), options.aspect_ratio);
+        assert_eq!(Some(Point3::from(13f64, 2f64, 3f64)), options.lookfrom);
+        assert_eq!(Some(20f64), options.vfov);
+        assert_eq!(Some(ToneMap::Aces), options.tone_map);
+        assert_eq!(Some(Transfer::Gamma(2.4)), options.transfer);
+        assert_eq!(Some(SamplerKind::Sobol), options.sampler);
+        assert_eq!(Some((0f64, 0.5)), options.shutter);
+        assert_eq!(Some(ExrPixelType::Float), options.exr_pixel_type);
+        assert_eq!(Some(ExrCompression::None), options.exr_compression);
+        assert_eq!(Some(false), options.aovs);
+        assert_eq!(None, options.height);
+
+        assert!(parse("-h").unwrap().help);
+        assert!(parse("--verbose").unwrap().verbose);
+        assert_eq!(Options::default(), parse("").unwrap());
+        assert_eq!(
+            Some(ToneMap::Exposure(2f64)),
+            parse("--exposure 2").unwrap().tone_map
+        );
+    }
+
+    #[test]
+    fn reports_errors() {
+        assert_eq!(
+            Err(CliError::UnknownOption {
+                option: "--widht".to_string(),
+                suggestion: Some("--width")
+            }),
+            parse("--widht 100")
+        );
+        assert_eq!(
+            "unknown option 'image.png'",
+            parse("image.png").unwrap_err().to_string()
+        );
+        assert_eq!(
+            Err(CliError::MissingValue("--spp".to_string())),
+            parse("--width 10 --spp")
+        );
+        assert_eq!(
+            "invalid value '0' for '--spp': expected a positive integer",
+            parse("--spp 0").unwrap_err().to_string()
+        );
+        assert_eq!(
+            "invalid value 'out.jpg' for '--output': expected a file ending in .ppm, .pfm, .png or .exr",
+            parse("--output out.jpg").unwrap_err().to_string()
+        );
+        assert!(parse("--lookat 1,2").is_err());
+        assert!(parse("--lookat 1,2,x").is_err());
+        assert!(parse("--vup 0,0,0").is_err());
+        assert!(parse("--vfov 180").is_err());
+        assert!(parse("--aperture -1").is_err());
+        assert!(parse("--sampler random").is_err());
+        assert!(parse("--exr-type double").is_err());
+        assert!(parse("--aovs yes").is_err());
+        assert!(parse("--shutter 1,0").is_err());
+        assert!(parse("--aspect-ratio 16:0").is_err());
+        assert!(parse("--width 10 --height 10 --aspect-ratio 2").is_err());
+        assert!(parse("--tone-map aces --exposure 2").is_err());
+    }
+
+    #[test]
+    fn merges_the_image_size_as_a_whole() {
+        let scene = parse("--width 400 --height 300 --spp 8").unwrap();
+        let options = parse("--width 800").unwrap().or(scene.clone());
+        assert_eq!(
+            (Some(800), None, None),
+            (options.width, options.height, options.aspect_ratio)
+        );
+        assert_eq!(Some(8), options.samples_per_pixel);
+
+        let scene = parse("--width 400 --aspect-ratio 2").unwrap();
+        let options = parse("--height 100").unwrap().or(scene.clone());
+        assert_eq!(
+            (None, Some(100), None),
+            (options.width, options.height, options.aspect_ratio)
+        );
+        let options = parse("--aspect-ratio 1").unwrap().or(scene);
+        assert_eq!(
+            (Some(400), Some(1f64)),
+            (options.width, options.aspect_ratio)
+        );
+    }
+}
@@ -1,10 +1,162 @@
 use crate::vec3::*;
 
-pub fn write_color<T: std::io::Write>(mut fmt: T, pixel_color: Color) -> std::io::Result<()> {
-    fmt.write_fmt(format_args!(
-        "{} {} {}\n",
-        (255.999 * pixel_color.x()) as i32,
-        (255.999 * pixel_color.y()) as i32,
-        (255.999 * pixel_color.z()) as i32
-    ))
+/// Compresses unbounded scene radiance into the displayable `[0, 1]` range.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum ToneMap {
+    /// Values above 1 are simply clipped.
+    Clamp,
+    /// `x / (1 + x)` per channel.
+    Reinhard,
+    /// Krzysztof Narkowicz's fit of the ACES filmic curve.
+    Aces,
+    /// `1 - exp(-exposure * x)`, mimicking film response.
+    Exposure(f64),
+}
+
+impl ToneMap {
+    pub fn apply(&self, x: f64) -> f64 {
+        let x = x.max(0f64);
+        let mapped = match *self {
+            ToneMap::Clamp => x,
+            ToneMap::Reinhard => x / (1f64 + x),
+            ToneMap::Aces => (x * (2.51 * x + 0.03)) / (x * (2.43 * x + 0.59) + 0.14),
+            ToneMap::Exposure(exposure) => 1f64 - (-exposure * x).exp(),
+        };
+        mapped.clamp(0f64, 1f64)
+    }
+}
+
+/// Encodes linear `[0, 1]` values for display.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Transfer {
+    Linear,
+    /// A pure power curve, `x^(1 / gamma)`; 2.2 approximates most displays.
+    Gamma(f64),
+    /// The exact piecewise sRGB OETF.
+    Srgb,
+}
+
+impl Transfer {
+    pub fn apply(&self, x: f64) -> f64 {
+        match *self {
+            Transfer::Linear => x,
+            Transfer::Gamma(gamma) => x.powf(1f64 / gamma),
+            Transfer::Srgb => {
+                if x <= 0.0031308 {
+                    12.92 * x
+                } else {
+                    1.055 * x.powf(1f64 / 2.4) - 0.055
+                }
+            }
+        }
+    }
+
+    /// The inverse of [`Transfer::apply`], turning encoded values back into linear ones.
+    pub fn decode(&self, x: f64) -> f64 {
+        match *self {
+            Transfer::Linear => x,
+            Transfer::Gamma(gamma) => x.powf(gamma),
+            Transfer::Srgb => {
+                if x <= 0.04045 {
+                    x / 12.92
+                } else {
+                    ((x + 0.055) / 1.055).powf(2.4)
+                }
+            }
+        }
+    }
+}
+
+/// Turns linear radiance into display values: tone mapping followed by the transfer function.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct DisplayTransform {
+    pub tone_map: ToneMap,
+    pub transfer: Transfer,
+}
+
+impl DisplayTransform {
+    pub fn from(tone_map: ToneMap, transfer: Transfer) -> DisplayTransform {
+        DisplayTransform { tone_map, transfer }
+    }
+
+    /// Maps a linear value to a display value in `[0, 1]`. NaNs become 0.
+    pub fn apply(&self, x: f64) -> f64 {
+        if x.is_nan() {
+            return 0f64;
+        }
+        self.transfer
+            .apply(self.tone_map.apply(x))
+            .clamp(0f64, 1f64)
+    }
+
+    /// Maps a linear color to rounded 8 bit display values.
+    pub fn to_rgb8(&self, color: Color) -> [u8; 3] {
+        let quantize = |x: f64| (255f64 * self.apply(x) + 0.5) as u8;
+        [
+            quantize(*color.x()),
+            quantize(*color.y()),
+            quantize(*color.z()),
+        ]
+    }
+}
+
+impl Default for DisplayTransform {
+    fn default() -> Self {
+        DisplayTransform::from(ToneMap::Clamp, Transfer::Srgb)
+    }
+}
+
+/// Writes one linear color as a P3 pixel.
+pub fn write_color<T: std::io::Write>(
+    mut fmt: T,
+    pixel_color: Color,
+    transform: &DisplayTransform,
+) -> std::io::Result<()> {
+    let [r, g, b] = transform.to_rgb8(pixel_color);
+    fmt.write_fmt(format_args!("{r} {g} {b}\n"))
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    fn close(a: f64, b: f64) -> bool {
+        (a - b).abs() < 1e-4
+    }
+
+    #[test]
+    fn transfer_functions() {
+        assert_eq!(0.25, Transfer::Linear.apply(0.25));
+        assert!(close(0.5, Transfer::Gamma(2f64).apply(0.25)));
+        assert!(close(0.7354, Transfer::Srgb.apply(0.5)));
+        assert!(close(0.0129, Transfer::Srgb.apply(0.001)));
+        assert!(close(1f64, Transfer::Srgb.apply(1f64)));
+
+        for transfer in [Transfer::Linear, Transfer::Gamma(2.2), Transfer::Srgb] {
+            for x in [0f64, 0.002, 0.2, 0.5, 1f64] {
+                assert!(close(x, transfer.decode(transfer.apply(x))));
+            }
+        }
+    }
+
+    #[test]
+    fn tone_maps() {
+        assert_eq!(1f64, ToneMap::Clamp.apply(4f64));
+        assert_eq!(0f64, ToneMap::Clamp.apply(-1f64));
+        assert_eq!(0.5, ToneMap::Reinhard.apply(1f64));
+        assert!(close(0.8038, ToneMap::Aces.apply(1f64)));
+        assert_eq!(1f64, ToneMap::Aces.apply(100f64));
+        assert!(close(
+            1f64 - (-2f64).exp(),
+            ToneMap::Exposure(2f64).apply(1f64)
+        ));
+    }
+
+    #[test]
+    fn write_clamps() {
+        let mut out = Vec::new();
+        let transform = DisplayTransform::from(ToneMap::Clamp, Transfer::Linear);
+        write_color(&mut out, Color::from(0.25, 8f64, f64::NAN), &transform).unwrap();
+        assert_eq!("64 255 0\n", String::from_utf8(out).unwrap());
+    }
 }
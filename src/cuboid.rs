@@ -0,0 +1,91 @@
+use std::sync::Arc;
+
+use crate::aabb::*;
+use crate::hittable::*;
+use crate::hittable_list::*;
+use crate::material::*;
+use crate::quad::*;
+use crate::ray::*;
+use crate::vec3::*;
+
+/// Axis-aligned box made of six outward facing quads.
+pub struct Cuboid {
+    bbox: Aabb,
+    sides: HittableList,
+}
+
+impl Cuboid {
+    /// The box with opposite corners `a` and `b`.
+    ///
+    /// # Panics
+    ///
+    /// If the corners share a coordinate, which would flatten the box.
+    pub fn from(a: Point3, b: Point3, material: Arc<dyn Material>) -> Cuboid {
+        assert!(a.x() != b.x() && a.y() != b.y() && a.z() != b.z());
+        let bbox = Aabb::from(a, b);
+        let (min, max) = (bbox.min(), bbox.max());
+        let dx = Vec3::from(max.x() - min.x(), 0f64, 0f64);
+        let dy = Vec3::from(0f64, max.y() - min.y(), 0f64);
+        let dz = Vec3::from(0f64, 0f64, max.z() - min.z());
+
+        let mut sides = HittableList::new();
+        let mut add = |q: Point3, u: Vec3, v: Vec3| {
+            sides.add(Arc::new(Quad::from(q, u, v, material.clone())));
+        };
+        add(Point3::from(*min.x(), *min.y(), *max.z()), dx, dy); // front
+        add(Point3::from(*max.x(), *min.y(), *max.z()), -dz, dy); // right
+        add(Point3::from(*max.x(), *min.y(), *min.z()), -dx, dy); // back
+        add(Point3::from(*min.x(), *min.y(), *min.z()), dz, dy); // left
+        add(Point3::from(*min.x(), *max.y(), *max.z()), dx, -dz); // top
+        add(Point3::from(*min.x(), *min.y(), *min.z()), dx, dz); // bottom
+
+        Cuboid { bbox, sides }
+    }
+}
+
+impl Hittable for Cuboid {
+    fn hit(&self, r: &Ray, t_min: f64, t_max: f64) -> Option<HitRecord<'_>> {
+        if !self.bbox.pad(1e-4).hit(r, t_min, t_max) {
+            return None;
+        }
+        self.sides.hit(r, t_min, t_max)
+    }
+
+    fn bounding_box(&self) -> Option<Aabb> {
+        Some(self.bbox)
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn sides_face_outwards() {
+        let material = Arc::new(Lambertian::from(Color::from(0.5, 0.5, 0.5)));
+        let cuboid = Cuboid::from(
+            Point3::from(1f64, 1f64, 1f64),
+            Point3::from(-1f64, -1f64, -1f64),
+            material,
+        );
+        let directions = [
+            Vec3::from(1f64, 0f64, 0f64),
+            Vec3::from(0f64, 1f64, 0f64),
+            Vec3::from(0f64, 0f64, 1f64),
+        ];
+        for direction in directions.into_iter().flat_map(|d| [d, -d]) {
+            // From outside, every side faces the ray.
+            let r = Ray::from(3f64 * direction, -direction);
+            let rec = cuboid.hit(&r, 0.001, f64::INFINITY).unwrap();
+            assert_eq!(2f64, rec.t);
+            assert_eq!(direction, rec.normal);
+            assert!(rec.front_face);
+
+            // From the center, the ray sees the back of the side it leaves through.
+            let r = Ray::from(Point3::new(), direction);
+            let rec = cuboid.hit(&r, 0.001, f64::INFINITY).unwrap();
+            assert_eq!(1f64, rec.t);
+            assert!(!rec.front_face);
+        }
+    }
+}
@@ -0,0 +1,544 @@
+//! Just enough of zlib (RFC 1950) and deflate (RFC 1951) to read and write PNG and EXR files.
+
+/// Base match length of the length symbols 257..=285 and their number of extra bits.
+const LENGTH_BASE: [u16; 29] = [
+    3, 4, 5, 6, 7, 8, 9, 10, 11, 13, 15, 17, 19, 23, 27, 31, 35, 43, 51, 59, 67, 83, 99, 115, 131,
+    163, 195, 227, 258,
+];
+const LENGTH_EXTRA: [u8; 29] = [
+    0, 0, 0, 0, 0, 0, 0, 0, 1, 1, 1, 1, 2, 2, 2, 2, 3, 3, 3, 3, 4, 4, 4, 4, 5, 5, 5, 5, 0,
+];
+
+/// Base distance of the distance symbols 0..=29 and their number of extra bits.
+const DIST_BASE: [u16; 30] = [
+    1, 2, 3, 4, 5, 7, 9, 13, 17, 25, 33, 49, 65, 97, 129, 193, 257, 385, 513, 769, 1025, 1537,
+    2049, 3073, 4097, 6145, 8193, 12289, 16385, 24577,
+];
+const DIST_EXTRA: [u8; 30] = [
+    0, 0, 0, 0, 1, 1, 2, 2, 3, 3, 4, 4, 5, 5, 6, 6, 7, 7, 8, 8, 9, 9, 10, 10, 11, 11, 12, 12, 13,
+    13,
+];
+
+const WINDOW_SIZE: usize = 1 << 15;
+const MIN_MATCH: usize = 3;
+const MAX_MATCH: usize = 258;
+const MAX_CHAIN: usize = 64;
+const HASH_BITS: u32 = 15;
+
+struct BitWriter {
+    out: Vec<u8>,
+    bits: u64,
+    count: u32,
+}
+
+impl BitWriter {
+    fn new() -> BitWriter {
+        BitWriter {
+            out: Vec::new(),
+            bits: 0,
+            count: 0,
+        }
+    }
+
+    /// Appends the `count` low bits of `bits`, least significant first.
+    fn write(&mut self, bits: u32, count: u32) {
+        self.bits |= (bits as u64) << self.count;
+        self.count += count;
+        while self.count >= 8 {
+            self.out.push(self.bits as u8);
+            self.bits >>= 8;
+            self.count -= 8;
+        }
+    }
+
+    /// Appends a Huffman code, which deflate stores most significant bit first.
+    fn write_code(&mut self, code: u32, len: u32) {
+        self.write(code.reverse_bits() >> (32 - len), len);
+    }
+
+    fn finish(mut self) -> Vec<u8> {
+        if self.count > 0 {
+            self.out.push(self.bits as u8);
+        }
+        self.out
+    }
+}
+
+/// Writes a literal/length symbol with the fixed Huffman code of RFC 1951 3.2.6.
+fn write_fixed_literal(writer: &mut BitWriter, symbol: u32) {
+    match symbol {
+        0..=143 => writer.write_code(0x30 + symbol, 8),
+        144..=255 => writer.write_code(0x190 + symbol - 144, 9),
+        256..=279 => writer.write_code(symbol - 256, 7),
+        _ => writer.write_code(0xC0 + symbol - 280, 8),
+    }
+}
+
+fn write_match(writer: &mut BitWriter, length: usize, distance: usize) {
+    let code = LENGTH_BASE.partition_point(|&base| base as usize <= length) - 1;
+    write_fixed_literal(writer, 257 + code as u32);
+    writer.write(
+        (length - LENGTH_BASE[code] as usize) as u32,
+        LENGTH_EXTRA[code] as u32,
+    );
+
+    let code = DIST_BASE.partition_point(|&base| base as usize <= distance) - 1;
+    writer.write_code(code as u32, 5);
+    writer.write(
+        (distance - DIST_BASE[code] as usize) as u32,
+        DIST_EXTRA[code] as u32,
+    );
+}
+
+/// Hash chains over the last 32 KiB of input, used to find earlier occurrences of the bytes at
+/// the current position.
+struct MatchFinder<'a> {
+    data: &'a [u8],
+    head: Vec<usize>,
+    prev: Vec<usize>,
+}
+
+impl<'a> MatchFinder<'a> {
+    fn from(data: &'a [u8]) -> MatchFinder<'a> {
+        MatchFinder {
+            data,
+            head: vec![usize::MAX; 1 << HASH_BITS],
+            prev: vec![usize::MAX; WINDOW_SIZE],
+        }
+    }
+
+    fn hash(&self, pos: usize) -> usize {
+        let d = self.data;
+        let v = (d[pos] as u32) << 16 | (d[pos + 1] as u32) << 8 | d[pos + 2] as u32;
+        (v.wrapping_mul(0x9E37_79B1) >> (32 - HASH_BITS)) as usize
+    }
+
+    fn insert(&mut self, pos: usize) {
+        if pos + MIN_MATCH <= self.data.len() {
+            let h = self.hash(pos);
+            self.prev[pos % WINDOW_SIZE] = self.head[h];
+            self.head[h] = pos;
+        }
+    }
+
+    /// Returns the length and distance of the longest match for `pos`, if any.
+    fn find(&self, pos: usize) -> Option<(usize, usize)> {
+        let data = self.data;
+        if pos + MIN_MATCH > data.len() {
+            return None;
+        }
+
+        let max_len = MAX_MATCH.min(data.len() - pos);
+        let mut best = None;
+        let mut best_len = MIN_MATCH - 1;
+        let mut candidate = self.head[self.hash(pos)];
+        let mut chain = 0;
+        while candidate != usize::MAX && pos - candidate <= WINDOW_SIZE && chain < MAX_CHAIN {
+            let len = data[candidate..]
+                .iter()
+                .zip(&data[pos..pos + max_len])
+                .take_while(|(a, b)| a == b)
+                .count();
+            if len > best_len {
+                best_len = len;
+                best = Some((len, pos - candidate));
+                if len == max_len {
+                    break;
+                }
+            }
+            let next = self.prev[candidate % WINDOW_SIZE];
+            // Older entries of the ring buffer may have been overwritten by newer positions.
+            if next == usize::MAX || next >= candidate {
+                break;
+            }
+            candidate = next;
+            chain += 1;
+        }
+        best
+    }
+}
+
+/// Compresses `data` into a raw deflate stream: greedy LZ77 matching over a 32 KiB window,
+/// coded as a single block with the fixed Huffman tables.
+pub fn deflate(data: &[u8]) -> Vec<u8> {
+    let mut writer = BitWriter::new();
+    // BFINAL = 1, BTYPE = 01 (fixed Huffman codes)
+    writer.write(1, 1);
+    writer.write(1, 2);
+
+    let mut finder = MatchFinder::from(data);
+    let mut pos = 0;
+    while pos < data.len() {
+        match finder.find(pos) {
+            Some((len, dist)) => {
+                write_match(&mut writer, len, dist);
+                for p in pos..pos + len {
+                    finder.insert(p);
+                }
+                pos += len;
+            }
+            None => {
+                write_fixed_literal(&mut writer, data[pos] as u32);
+                finder.insert(pos);
+                pos += 1;
+            }
+        }
+    }
+
+    write_fixed_literal(&mut writer, 256);
+    writer.finish()
+}
+
+/// Compresses `data` into a zlib stream.
+pub fn zlib_compress(data: &[u8]) -> Vec<u8> {
+    // CM = 8 (deflate), CINFO = 7 (32 KiB window), default compression level, no dictionary.
+    let mut out = vec![0x78, 0x9C];
+    out.extend(deflate(data));
+    out.extend(adler32(data).to_be_bytes());
+    out
+}
+
+struct BitReader<'a> {
+    data: &'a [u8],
+    pos: usize,
+    bits: u32,
+    count: u32,
+}
+
+impl<'a> BitReader<'a> {
+    fn from(data: &'a [u8]) -> BitReader<'a> {
+        BitReader {
+            data,
+            pos: 0,
+            bits: 0,
+            count: 0,
+        }
+    }
+
+    /// Reads `count` bits, least significant first.
+    fn read(&mut self, count: u32) -> std::io::Result<u32> {
+        while self.count < count {
+            let byte = *self
+                .data
+                .get(self.pos)
+                .ok_or_else(|| invalid("unexpected end of deflate stream"))?;
+            self.pos += 1;
+            self.bits |= (byte as u32) << self.count;
+            self.count += 8;
+        }
+        let value = self.bits & ((1u64 << count) - 1) as u32;
+        self.bits >>= count;
+        self.count -= count;
+        Ok(value)
+    }
+
+    /// Drops the bits left in the current byte.
+    fn align(&mut self) {
+        self.bits = 0;
+        self.count = 0;
+    }
+}
+
+/// A canonical Huffman code, stored as the number of codes of each length and the symbols
+/// ordered by code.
+struct Huffman {
+    counts: [u16; 16],
+    symbols: Vec<u16>,
+}
+
+impl Huffman {
+    fn from(lengths: &[u8]) -> std::io::Result<Huffman> {
+        let mut counts = [0u16; 16];
+        for &len in lengths {
+            counts[len as usize] += 1;
+        }
+        counts[0] = 0;
+
+        // Reject over subscribed codes; incomplete ones are legal (e.g. a single distance code).
+        let mut left = 1i32;
+        for &count in &counts[1..] {
+            left = 2 * left - count as i32;
+            if left < 0 {
+                return Err(invalid("over subscribed Huffman code"));
+            }
+        }
+
+        let mut offsets = [0u16; 16];
+        for len in 1..15 {
+            offsets[len + 1] = offsets[len] + counts[len];
+        }
+        let mut symbols = vec![0u16; lengths.len()];
+        for (symbol, &len) in lengths.iter().enumerate() {
+            if len != 0 {
+                symbols[offsets[len as usize] as usize] = symbol as u16;
+                offsets[len as usize] += 1;
+            }
+        }
+        Ok(Huffman { counts, symbols })
+    }
+
+    fn decode(&self, reader: &mut BitReader) -> std::io::Result<u16> {
+        // Codes are read most significant bit first, one bit at a time.
+        let (mut code, mut first, mut index) = (0i32, 0i32, 0i32);
+        for len in 1..16 {
+            code |= reader.read(1)? as i32;
+            let count = self.counts[len] as i32;
+            if code - count < first {
+                return Ok(self.symbols[(index + code - first) as usize]);
+            }
+            index += count;
+            first = (first + count) << 1;
+            code <<= 1;
+        }
+        Err(invalid("invalid Huffman code"))
+    }
+}
+
+fn fixed_tables() -> (Huffman, Huffman) {
+    let mut lengths = [0u8; 288];
+    lengths[..144].fill(8);
+    lengths[144..256].fill(9);
+    lengths[256..280].fill(7);
+    lengths[280..].fill(8);
+    (
+        Huffman::from(&lengths).unwrap(),
+        Huffman::from(&[5u8; 30]).unwrap(),
+    )
+}
+
+fn dynamic_tables(reader: &mut BitReader) -> std::io::Result<(Huffman, Huffman)> {
+    const ORDER: [usize; 19] = [
+        16, 17, 18, 0, 8, 7, 9, 6, 10, 5, 11, 4, 12, 3, 13, 2, 14, 1, 15,
+    ];
+
+    let hlit = reader.read(5)? as usize + 257;
+    let hdist = reader.read(5)? as usize + 1;
+    let hclen = reader.read(4)? as usize + 4;
+
+    let mut code_lengths = [0u8; 19];
+    for &symbol in &ORDER[..hclen] {
+        code_lengths[symbol] = reader.read(3)? as u8;
+    }
+    let code_length_code = Huffman::from(&code_lengths)?;
+
+    let mut lengths = vec![0u8; hlit + hdist];
+    let mut i = 0;
+    while i < lengths.len() {
+        let symbol = code_length_code.decode(reader)?;
+        let (value, repeat) = match symbol {
+            0..=15 => (symbol as u8, 1),
+            16 => {
+                let previous = *i
+                    .checked_sub(1)
+                    .and_then(|p| lengths.get(p))
+                    .ok_or_else(|| invalid("repeat with no previous length"))?;
+                (previous, 3 + reader.read(2)? as usize)
+            }
+            17 => (0, 3 + reader.read(3)? as usize),
+            _ => (0, 11 + reader.read(7)? as usize),
+        };
+        if i + repeat > lengths.len() {
+            return Err(invalid("too many code lengths"));
+        }
+        lengths[i..i + repeat].fill(value);
+        i += repeat;
+    }
+
+    if lengths[256] == 0 {
+        return Err(invalid("missing end of block code"));
+    }
+    Ok((
+        Huffman::from(&lengths[..hlit])?,
+        Huffman::from(&lengths[hlit..])?,
+    ))
+}
+
+/// Decompresses a raw deflate stream.
+pub fn inflate(data: &[u8]) -> std::io::Result<Vec<u8>> {
+    let mut reader = BitReader::from(data);
+    let mut out = Vec::new();
+
+    loop {
+        let last = reader.read(1)? == 1;
+        match reader.read(2)? {
+            0 => {
+                reader.align();
+                let pos = reader.pos;
+                let header = data
+                    .get(pos..pos + 4)
+                    .ok_or_else(|| invalid("truncated stored block"))?;
+                let len = u16::from_le_bytes([header[0], header[1]]) as usize;
+                let nlen = u16::from_le_bytes([header[2], header[3]]) as usize;
+                if len != !nlen & 0xFFFF {
+                    return Err(invalid("corrupt stored block length"));
+                }
+                let block = data
+                    .get(pos + 4..pos + 4 + len)
+                    .ok_or_else(|| invalid("truncated stored block"))?;
+                out.extend_from_slice(block);
+                reader.pos = pos + 4 + len;
+            }
+            kind @ (1 | 2) => {
+                let (literals, distances) = if kind == 1 {
+                    fixed_tables()
+                } else {
+                    dynamic_tables(&mut reader)?
+                };
+                loop {
+                    let symbol = literals.decode(&mut reader)? as usize;
+                    if symbol < 256 {
+                        out.push(symbol as u8);
+                        continue;
+                    }
+                    if symbol == 256 {
+                        break;
+                    }
+
+                    let code = symbol - 257;
+                    if code >= LENGTH_BASE.len() {
+                        return Err(invalid("invalid length symbol"));
+                    }
+                    let length = LENGTH_BASE[code] as usize
+                        + reader.read(LENGTH_EXTRA[code] as u32)? as usize;
+                    let code = distances.decode(&mut reader)? as usize;
+                    if code >= DIST_BASE.len() {
+                        return Err(invalid("invalid distance symbol"));
+                    }
+                    let distance =
+                        DIST_BASE[code] as usize + reader.read(DIST_EXTRA[code] as u32)? as usize;
+                    if distance > out.len() {
+                        return Err(invalid("distance too far back"));
+                    }
+                    // Copy byte by byte: the match may overlap the bytes it produces.
+                    let start = out.len() - distance;
+                    for k in 0..length {
+                        out.push(out[start + k]);
+                    }
+                }
+            }
+            _ => return Err(invalid("invalid deflate block type")),
+        }
+        if last {
+            return Ok(out);
+        }
+    }
+}
+
+/// Decompresses a zlib stream, checking its header and checksum.
+pub fn zlib_decompress(data: &[u8]) -> std::io::Result<Vec<u8>> {
+    if data.len() < 6 {
+        return Err(invalid("truncated zlib stream"));
+    }
+    let (cmf, flg) = (data[0], data[1]);
+    if cmf & 0x0F != 8 || !(cmf as u16 * 256 + flg as u16).is_multiple_of(31) {
+        return Err(invalid("invalid zlib header"));
+    }
+    if flg & 0x20 != 0 {
+        return Err(invalid("zlib preset dictionaries are not supported"));
+    }
+
+    let out = inflate(&data[2..data.len() - 4])?;
+    let expected = u32::from_be_bytes(data[data.len() - 4..].try_into().unwrap());
+    if adler32(&out) != expected {
+        return Err(invalid("zlib checksum mismatch"));
+    }
+    Ok(out)
+}
+
+fn invalid(message: &str) -> std::io::Error {
+    std::io::Error::new(std::io::ErrorKind::InvalidData, message)
+}
+
+pub fn adler32(data: &[u8]) -> u32 {
+    const MOD_ADLER: u32 = 65521;
+    let (mut a, mut b) = (1u32, 0u32);
+    // 5552 is the largest block for which `b` cannot overflow before the modulo.
+    for chunk in data.chunks(5552) {
+        for &byte in chunk {
+            a += byte as u32;
+            b += a;
+        }
+        a %= MOD_ADLER;
+        b %= MOD_ADLER;
+    }
+    b << 16 | a
+}
+
+pub fn crc32(data: &[u8]) -> u32 {
+    let mut crc = 0xFFFF_FFFFu32;
+    for &byte in data {
+        crc ^= byte as u32;
+        for _ in 0..8 {
+            crc = if crc & 1 != 0 {
+                (crc >> 1) ^ 0xEDB8_8320
+            } else {
+                crc >> 1
+            };
+        }
+    }
+    !crc
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn checksums() {
+        assert_eq!(0xCBF4_3926, crc32(b"123456789"));
+        assert_eq!(0, crc32(b""));
+        assert_eq!(0x11E6_0398, adler32(b"Wikipedia"));
+        assert_eq!(1, adler32(b""));
+    }
+
+    #[test]
+    fn compresses_repetitions() {
+        // An empty fixed block is just the header and the end of block code.
+        assert_eq!(vec![0x03, 0x00], deflate(b""));
+
+        let data: Vec<u8> = b"ray tracing "
+            .iter()
+            .cycle()
+            .take(12_000)
+            .cloned()
+            .collect();
+        let compressed = zlib_compress(&data);
+        assert!(compressed.len() < data.len() / 20);
+        assert_eq!(
+            adler32(&data).to_be_bytes(),
+            compressed[compressed.len() - 4..]
+        );
+        assert_eq!(data, zlib_decompress(&compressed).unwrap());
+    }
+
+    #[test]
+    fn round_trips() {
+        let mut state = 1u32;
+        let noise: Vec<u8> = (0..70_000u32)
+            .map(|i| {
+                state = state.wrapping_mul(1_103_515_245).wrapping_add(12345);
+                if i % 3 == 0 {
+                    (state >> 24) as u8
+                } else {
+                    (i / 7) as u8
+                }
+            })
+            .collect();
+        for data in [&b""[..], b"a", b"abcabcabcabcabcabcab", &noise] {
+            assert_eq!(data, zlib_decompress(&zlib_compress(data)).unwrap());
+        }
+    }
+
+    #[test]
+    fn inflates_stored_blocks() {
+        let stored = [
+            0x78, 0x01, 0x01, 0x03, 0x00, 0xFC, 0xFF, 0x61, 0x62, 0x63, 0x02, 0x4D, 0x01, 0x27,
+        ];
+        assert_eq!(b"abc".to_vec(), zlib_decompress(&stored).unwrap());
+
+        let mut corrupt = stored;
+        corrupt[8] = b'x';
+        assert!(zlib_decompress(&corrupt).is_err());
+        assert!(zlib_decompress(&stored[..9]).is_err());
+    }
+}
@@ -0,0 +1,363 @@
+//! A minimal single part, scanline OpenEXR writer.
+
+use std::io::Write;
+
+use crate::deflate::*;
+use crate::image::*;
+
+const MAGIC: [u8; 4] = [0x76, 0x2F, 0x31, 0x01];
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum ExrPixelType {
+    /// 16 bit floats: half the size, with about three significant digits.
+    Half,
+    /// 32 bit floats.
+    Float,
+}
+
+impl ExrPixelType {
+    pub const NAMES: [&'static str; 2] = ["half", "float"];
+
+    pub fn from_name(name: &str) -> Option<ExrPixelType> {
+        match name {
+            "half" => Some(ExrPixelType::Half),
+            "float" => Some(ExrPixelType::Float),
+            _ => None,
+        }
+    }
+
+    fn id(&self) -> i32 {
+        match self {
+            ExrPixelType::Half => 1,
+            ExrPixelType::Float => 2,
+        }
+    }
+
+    fn size(&self) -> usize {
+        match self {
+            ExrPixelType::Half => 2,
+            ExrPixelType::Float => 4,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum ExrCompression {
+    None,
+    /// Lossless zlib compression of blocks of 16 scanlines.
+    Zip,
+}
+
+impl ExrCompression {
+    pub const NAMES: [&'static str; 2] = ["none", "zip"];
+
+    pub fn from_name(name: &str) -> Option<ExrCompression> {
+        match name {
+            "none" => Some(ExrCompression::None),
+            "zip" => Some(ExrCompression::Zip),
+            _ => None,
+        }
+    }
+
+    fn id(&self) -> u8 {
+        match self {
+            ExrCompression::None => 0,
+            ExrCompression::Zip => 3,
+        }
+    }
+
+    fn lines_per_block(&self) -> u32 {
+        match self {
+            ExrCompression::None => 1,
+            ExrCompression::Zip => 16,
+        }
+    }
+}
+
+/// How [`Image::save`] encodes OpenEXR files.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct ExrSettings {
+    pub pixel_type: ExrPixelType,
+    pub compression: ExrCompression,
+}
+
+impl Default for ExrSettings {
+    /// ZIP compressed half floats.
+    fn default() -> ExrSettings {
+        ExrSettings {
+            pixel_type: ExrPixelType::Half,
+            compression: ExrCompression::Zip,
+        }
+    }
+}
+
+/// Converts to the nearest half precision float, rounding ties to even.
+pub fn f32_to_half(value: f32) -> u16 {
+    let bits = value.to_bits();
+    let sign = ((bits >> 16) & 0x8000) as u16;
+    let exponent = ((bits >> 23) & 0xFF) as i32;
+    let mantissa = bits & 0x007F_FFFF;
+
+    if exponent == 0xFF {
+        // Infinity stays infinity, NaN keeps a non zero mantissa.
+        let nan = if mantissa != 0 { 0x0200 } else { 0 };
+        return sign | 0x7C00 | nan;
+    }
+
+    let half_exponent = exponent - 127 + 15;
+    if half_exponent >= 0x1F {
+        return sign | 0x7C00;
+    }
+    if half_exponent <= 0 {
+        // Subnormal half, or too small and flushed to zero.
+        if half_exponent < -10 {
+            return sign;
+        }
+        let mantissa = mantissa | 0x0080_0000;
+        let shift = (14 - half_exponent) as u32;
+        let half_mantissa = mantissa >> shift;
+        let remainder = mantissa & ((1 << shift) - 1);
+        let halfway = 1 << (shift - 1);
+        let round_up = remainder > halfway || (remainder == halfway && half_mantissa & 1 == 1);
+        return sign | (half_mantissa + round_up as u32) as u16;
+    }
+
+    let half = ((half_exponent as u32) << 10) | (mantissa >> 13);
+    let remainder = mantissa & 0x1FFF;
+    let round_up = remainder > 0x1000 || (remainder == 0x1000 && half & 1 == 1);
+    // A carry out of the mantissa correctly bumps the exponent, up to infinity.
+    sign | (half + round_up as u32) as u16
+}
+
+fn write_attribute<W: Write>(
+    out: &mut W,
+    name: &str,
+    kind: &str,
+    value: &[u8],
+) -> std::io::Result<()> {
+    out.write_all(name.as_bytes())?;
+    out.write_all(&[0])?;
+    out.write_all(kind.as_bytes())?;
+    out.write_all(&[0])?;
+    out.write_all(&(value.len() as i32).to_le_bytes())?;
+    out.write_all(value)
+}
+
+/// Applies the byte reordering and delta predictor OpenEXR uses before zlib compression.
+fn zip_compress(raw: &[u8]) -> Vec<u8> {
+    let mut reordered = Vec::with_capacity(raw.len());
+    reordered.extend(raw.iter().step_by(2));
+    reordered.extend(raw.iter().skip(1).step_by(2));
+
+    let mut previous = reordered.first().copied().unwrap_or(0);
+    for byte in reordered.iter_mut().skip(1) {
+        let current = *byte;
+        *byte = current.wrapping_sub(previous).wrapping_add(128);
+        previous = current;
+    }
+
+    zlib_compress(&reordered)
+}
+
+/// Where the values of an output channel come from.
+enum Source<'a> {
+    /// A component of the color.
+    Color(usize),
+    Extra(&'a [f32]),
+}
+
+/// Writes `image` as linear, unclamped RGB plus all of its extra channels.
+pub fn write_exr<W: Write>(
+    mut out: W,
+    image: &Image,
+    pixel_type: ExrPixelType,
+    compression: ExrCompression,
+) -> std::io::Result<()> {
+    let (width, height) = (image.width(), image.height());
+
+    // Channels are stored in alphabetical order.
+    let mut channels = vec![
+        ("R", Source::Color(0)),
+        ("G", Source::Color(1)),
+        ("B", Source::Color(2)),
+    ];
+    for channel in image.channels() {
+        channels.push((&channel.name, Source::Extra(&channel.data)));
+    }
+    channels.sort_by(|a, b| a.0.cmp(b.0));
+
+    let mut header = Vec::new();
+    header.extend(MAGIC);
+    // Version 2, single part scanline file; flag 0x400 allows names longer than 31 bytes.
+    let long_names = channels.iter().any(|(name, _)| name.len() > 31);
+    let version: u32 = 2 | if long_names { 0x400 } else { 0 };
+    header.extend(version.to_le_bytes());
+
+    let mut chlist = Vec::new();
+    for (name, _) in &channels {
+        chlist.extend(name.as_bytes());
+        chlist.push(0);
+        chlist.extend(pixel_type.id().to_le_bytes());
+        // pLinear and three reserved bytes, then x and y sampling.
+        chlist.extend([0, 0, 0, 0]);
+        chlist.extend(1i32.to_le_bytes());
+        chlist.extend(1i32.to_le_bytes());
+    }
+    chlist.push(0);
+    write_attribute(&mut header, "channels", "chlist", &chlist)?;
+    write_attribute(
+        &mut header,
+        "compression",
+        "compression",
+        &[compression.id()],
+    )?;
+
+    let mut window = Vec::new();
+    for v in [0, 0, width as i32 - 1, height as i32 - 1] {
+        window.extend(v.to_le_bytes());
+    }
+    write_attribute(&mut header, "dataWindow", "box2i", &window)?;
+    write_attribute(&mut header, "displayWindow", "box2i", &window)?;
+    // Increasing y: blocks are stored from the top row down.
+    write_attribute(&mut header, "lineOrder", "lineOrder", &[0])?;
+    write_attribute(
+        &mut header,
+        "pixelAspectRatio",
+        "float",
+        &1f32.to_le_bytes(),
+    )?;
+    write_attribute(&mut header, "screenWindowCenter", "v2f", &[0; 8])?;
+    write_attribute(
+        &mut header,
+        "screenWindowWidth",
+        "float",
+        &1f32.to_le_bytes(),
+    )?;
+    header.push(0);
+
+    let lines_per_block = compression.lines_per_block();
+    let mut blocks = Vec::new();
+    for y0 in (0..height).step_by(lines_per_block as usize) {
+        let y1 = (y0 + lines_per_block).min(height);
+        let mut raw = Vec::with_capacity(
+            (y1 - y0) as usize * width as usize * channels.len() * pixel_type.size(),
+        );
+        for y in y0..y1 {
+            for (_, source) in &channels {
+                for x in 0..width {
+                    let i = (y * width + x) as usize;
+                    let v = match source {
+                        Source::Color(c) => image.pixels()[i][*c as i32] as f32,
+                        Source::Extra(data) => data[i],
+                    };
+                    match pixel_type {
+                        ExrPixelType::Half => raw.extend(f32_to_half(v).to_le_bytes()),
+                        ExrPixelType::Float => raw.extend(v.to_le_bytes()),
+                    }
+                }
+            }
+        }
+
+        let data = match compression {
+            ExrCompression::None => raw,
+            ExrCompression::Zip => {
+                // Blocks that do not shrink are stored raw, which readers detect by their size.
+                let compressed = zip_compress(&raw);
+                if compressed.len() < raw.len() {
+                    compressed
+                } else {
+                    raw
+                }
+            }
+        };
+        blocks.push((y0, data));
+    }
+
+    out.write_all(&header)?;
+    // The offset table holds the absolute file position of every block.
+    let mut offset = (header.len() + 8 * blocks.len()) as u64;
+    for (_, data) in &blocks {
+        out.write_all(&offset.to_le_bytes())?;
+        offset += 8 + data.len() as u64;
+    }
+    for (y0, data) in &blocks {
+        out.write_all(&(*y0 as i32).to_le_bytes())?;
+        out.write_all(&(data.len() as i32).to_le_bytes())?;
+        out.write_all(data)?;
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn half_conversion() {
+        assert_eq!(0x0000, f32_to_half(0f32));
+        assert_eq!(0x8000, f32_to_half(-0f32));
+        assert_eq!(0x3C00, f32_to_half(1f32));
+        assert_eq!(0xC000, f32_to_half(-2f32));
+        assert_eq!(0x3555, f32_to_half(1f32 / 3f32));
+        assert_eq!(0x7BFF, f32_to_half(65504f32));
+        assert_eq!(0x7C00, f32_to_half(65520f32));
+        assert_eq!(0x7C00, f32_to_half(f32::INFINITY));
+        assert_eq!(0x7E00, f32_to_half(f32::NAN));
+        // Smallest subnormal, and ties rounding to even.
+        assert_eq!(0x0001, f32_to_half(2f32.powi(-24)));
+        assert_eq!(0x0000, f32_to_half(2f32.powi(-25)));
+        assert_eq!(0x3C00, f32_to_half(1f32 + 2f32.powi(-11)));
+        assert_eq!(0x3C02, f32_to_half(1f32 + 3f32 * 2f32.powi(-11)));
+    }
+
+    /// Skips the header attributes and returns the offset table position.
+    fn header_end(file: &[u8]) -> usize {
+        let mut pos = 8;
+        loop {
+            let name_len = file[pos..].iter().position(|&b| b == 0).unwrap();
+            pos += name_len + 1;
+            if name_len == 0 {
+                return pos;
+            }
+            pos += file[pos..].iter().position(|&b| b == 0).unwrap() + 1;
+            let size = i32::from_le_bytes(file[pos..pos + 4].try_into().unwrap());
+            pos += 4 + size as usize;
+        }
+    }
+
+    #[test]
+    fn layout() {
+        let mut image = Image::new(3, 20);
+        let depth = image.add_channel("Z");
+        image.set_channel_value(depth, 2, 19, 7.5);
+
+        for (compression, block_count) in [(ExrCompression::None, 20), (ExrCompression::Zip, 2)] {
+            let lines = compression.lines_per_block() as usize;
+            let mut file = Vec::new();
+            write_exr(&mut file, &image, ExrPixelType::Float, compression).unwrap();
+            assert_eq!(MAGIC, file[..4]);
+            assert_eq!([2, 0, 0, 0], file[4..8]);
+            let chlist = b"channels\0chlist\0";
+            assert_eq!(chlist, &file[8..8 + chlist.len()]);
+
+            let table = header_end(&file);
+            let mut expected = table + 8 * block_count;
+            for block in 0..block_count {
+                let entry = &file[table + 8 * block..table + 8 * block + 8];
+                let offset = u64::from_le_bytes(entry.try_into().unwrap()) as usize;
+                assert_eq!(expected, offset);
+                let y = i32::from_le_bytes(file[offset..offset + 4].try_into().unwrap());
+                assert_eq!((block * lines) as i32, y);
+                let size = i32::from_le_bytes(file[offset + 4..offset + 8].try_into().unwrap());
+                expected = offset + 8 + size as usize;
+            }
+            assert_eq!(file.len(), expected);
+
+            if compression == ExrCompression::None {
+                // The last scanline holds B, G, R then Z for each of the 3 pixels.
+                let z = &file[file.len() - 4..];
+                assert_eq!(7.5f32, f32::from_le_bytes(z.try_into().unwrap()));
+            }
+        }
+    }
+}
@@ -0,0 +1,59 @@
+use crate::aabb::*;
+use crate::material::*;
+use crate::ray::*;
+use crate::vec3::*;
+
+/// Everything a shader needs to know about a single ray-object intersection.
+#[derive(Clone, Copy)]
+pub struct HitRecord<'a> {
+    pub p: Point3,
+    /// Unit normal, always pointing against the incoming ray.
+    pub normal: Vec3,
+    pub t: f64,
+    pub u: f64,
+    pub v: f64,
+    /// `true` if the ray hit the outside of the surface.
+    pub front_face: bool,
+    pub material: &'a dyn Material,
+}
+
+impl<'a> HitRecord<'a> {
+    /// Builds a record from the geometric (outward) normal, flipping it when the ray comes from inside.
+    pub fn from(
+        r: &Ray,
+        t: f64,
+        outward_normal: Vec3,
+        u: f64,
+        v: f64,
+        material: &'a dyn Material,
+    ) -> HitRecord<'a> {
+        let mut rec = HitRecord {
+            p: r.at(t),
+            normal: outward_normal,
+            t,
+            u,
+            v,
+            front_face: true,
+            material,
+        };
+        rec.set_face_normal(r, outward_normal);
+        rec
+    }
+
+    pub fn set_face_normal(&mut self, r: &Ray, outward_normal: Vec3) {
+        self.front_face = Vec3::dot(&r.direction(), &outward_normal) < 0f64;
+        self.normal = if self.front_face {
+            outward_normal
+        } else {
+            -outward_normal
+        };
+    }
+}
+
+pub trait Hittable: Send + Sync {
+    /// Returns the intersection of `r` with the object, if any, for `t` in `t_min..t_max`.
+    fn hit(&self, r: &Ray, t_min: f64, t_max: f64) -> Option<HitRecord<'_>>;
+
+    /// Returns a box enclosing the object, or `None` if it is unbounded.
+    fn bounding_box(&self) -> Option<Aabb>;
+}
@@ -0,0 +1,118 @@
+use std::sync::Arc;
+
+use crate::aabb::*;
+use crate::hittable::*;
+use crate::ray::*;
+
+/// A collection of objects, hit as a whole by reporting the closest intersection.
+#[derive(Default)]
+pub struct HittableList {
+    objects: Vec<Arc<dyn Hittable>>,
+}
+
+impl HittableList {
+    pub fn new() -> HittableList {
+        HittableList {
+            objects: Vec::new(),
+        }
+    }
+
+    pub fn from(object: Arc<dyn Hittable>) -> HittableList {
+        let mut list = HittableList::new();
+        list.add(object);
+        list
+    }
+
+    pub fn add(&mut self, object: Arc<dyn Hittable>) {
+        self.objects.push(object);
+    }
+
+    pub fn add_boxed(&mut self, object: Box<dyn Hittable>) {
+        self.objects.push(Arc::from(object));
+    }
+
+    pub fn clear(&mut self) {
+        self.objects.clear();
+    }
+
+    pub fn objects(&self) -> &[Arc<dyn Hittable>] {
+        &self.objects
+    }
+
+    pub fn len(&self) -> usize {
+        self.objects.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.objects.is_empty()
+    }
+}
+
+impl Hittable for HittableList {
+    fn hit(&self, r: &Ray, t_min: f64, t_max: f64) -> Option<HitRecord<'_>> {
+        let mut closest_so_far = t_max;
+        let mut hit_record = None;
+
+        for object in &self.objects {
+            if let Some(rec) = object.hit(r, t_min, closest_so_far) {
+                closest_so_far = rec.t;
+                hit_record = Some(rec);
+            }
+        }
+
+        hit_record
+    }
+
+    /// The box around all objects, or `None` if the list is empty or holds an unbounded object.
+    fn bounding_box(&self) -> Option<Aabb> {
+        if self.objects.is_empty() {
+            return None;
+        }
+        self.objects.iter().try_fold(Aabb::new(), |bbox, object| {
+            Some(Aabb::surrounding_box(&bbox, &object.bounding_box()?))
+        })
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use std::sync::Arc;
+
+    use super::HittableList;
+    use crate::hittable::Hittable;
+    use crate::material::Lambertian;
+    use crate::ray::Ray;
+    use crate::sphere::Sphere;
+    use crate::vec3::*;
+
+    #[test]
+    fn closest_hit_wins() {
+        let material = Arc::new(Lambertian::from(Color::from(0.5, 0.5, 0.5)));
+        let mut world = HittableList::new();
+        world.add(Arc::new(Sphere::from(
+            Point3::from(0f64, 0f64, -5f64),
+            1f64,
+            material.clone(),
+        )));
+        world.add_boxed(Box::new(Sphere::from(
+            Point3::from(0f64, 0f64, -2f64),
+            0.5,
+            material.clone(),
+        )));
+        world.add(Arc::new(Sphere::from(
+            Point3::from(0f64, 0f64, -10f64),
+            1f64,
+            material.clone(),
+        )));
+        assert_eq!(3, world.len());
+
+        let r = Ray::from(Point3::new(), Vec3::from(0f64, 0f64, -1f64));
+        assert_eq!(1.5, world.hit(&r, 0f64, f64::INFINITY).unwrap().t);
+        assert_eq!(4f64, world.hit(&r, 2.6, f64::INFINITY).unwrap().t);
+        assert!(world.hit(&r, 0f64, 1f64).is_none());
+
+        world.clear();
+        assert!(world.is_empty());
+        assert!(world.hit(&r, 0f64, f64::INFINITY).is_none());
+    }
+}
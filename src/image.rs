@@ -0,0 +1,227 @@
+use std::fs::File;
+use std::io::{BufReader, BufWriter, Write};
+use std::path::Path;
+
+use crate::color::*;
+use crate::exr::*;
+use crate::pfm::*;
+use crate::png::*;
+use crate::ppm::*;
+use crate::vec3::*;
+
+/// The image file formats the renderer can write.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum ImageFormat {
+    /// Binary (P6) PPM.
+    Ppm,
+    /// Floating point PFM, keeping the unclamped radiance.
+    Pfm,
+    Png,
+    /// OpenEXR, including the extra channels.
+    Exr,
+}
+
+impl ImageFormat {
+    /// Guesses the format from the extension of `path`, ignoring case.
+    pub fn from_path<P: AsRef<Path>>(path: P) -> Option<ImageFormat> {
+        let extension = path.as_ref().extension()?.to_str()?.to_ascii_lowercase();
+        match extension.as_str() {
+            "ppm" => Some(ImageFormat::Ppm),
+            "pfm" => Some(ImageFormat::Pfm),
+            "png" => Some(ImageFormat::Png),
+            "exr" => Some(ImageFormat::Exr),
+            _ => None,
+        }
+    }
+}
+
+/// An extra named value per pixel stored alongside the colors, such as depth or normals.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Channel {
+    pub name: String,
+    pub data: Vec<f32>,
+}
+
+/// A width by height grid of linear colors, stored row by row starting from the top left.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Image {
+    width: u32,
+    height: u32,
+    pixels: Vec<Color>,
+    channels: Vec<Channel>,
+}
+
+impl Image {
+    /// Creates a black image.
+    pub fn new(width: u32, height: u32) -> Image {
+        Image {
+            width,
+            height,
+            pixels: vec![Color::new(); (width as usize) * (height as usize)],
+            channels: Vec::new(),
+        }
+    }
+
+    /// Wraps `pixels`, which must hold exactly `width * height` colors in row major order.
+    pub fn from(width: u32, height: u32, pixels: Vec<Color>) -> Image {
+        assert_eq!((width as usize) * (height as usize), pixels.len());
+        Image {
+            width,
+            height,
+            pixels,
+            channels: Vec::new(),
+        }
+    }
+
+    pub fn width(&self) -> u32 {
+        self.width
+    }
+
+    pub fn height(&self) -> u32 {
+        self.height
+    }
+
+    /// `(x, y)` counts from the top left corner.
+    pub fn get_pixel(&self, x: u32, y: u32) -> Color {
+        self.pixels[self.index(x, y)]
+    }
+
+    pub fn set_pixel(&mut self, x: u32, y: u32, color: Color) {
+        let index = self.index(x, y);
+        self.pixels[index] = color;
+    }
+
+    pub fn pixels(&self) -> &[Color] {
+        &self.pixels
+    }
+
+    pub fn pixels_mut(&mut self) -> &mut [Color] {
+        &mut self.pixels
+    }
+
+    /// Adds a zero filled extra channel and returns its index.
+    pub fn add_channel(&mut self, name: &str) -> usize {
+        self.channels.push(Channel {
+            name: name.to_string(),
+            data: vec![0f32; self.pixels.len()],
+        });
+        self.channels.len() - 1
+    }
+
+    pub fn set_channel_value(&mut self, channel: usize, x: u32, y: u32, value: f32) {
+        let index = self.index(x, y);
+        self.channels[channel].data[index] = value;
+    }
+
+    pub fn channels(&self) -> &[Channel] {
+        &self.channels
+    }
+
+    pub fn channel(&self, name: &str) -> Option<&Channel> {
+        self.channels.iter().find(|channel| channel.name == name)
+    }
+
+    /// Iterates over the rows from top to bottom.
+    pub fn rows(&self) -> std::slice::Chunks<'_, Color> {
+        self.pixels.chunks(self.width.max(1) as usize)
+    }
+
+    /// Decodes the image file at `path`, whose format is guessed from its extension. The values
+    /// of 8 bit formats are turned back into linear ones with `transfer`.
+    pub fn load<P: AsRef<Path>>(path: P, transfer: Transfer) -> std::io::Result<Image> {
+        let path = path.as_ref();
+        let input = BufReader::new(File::open(path)?);
+        match ImageFormat::from_path(path) {
+            Some(ImageFormat::Ppm) => read_ppm(input, transfer),
+            Some(ImageFormat::Pfm) => read_pfm(input),
+            Some(ImageFormat::Png) => read_png(input, transfer),
+            _ => Err(std::io::Error::new(
+                std::io::ErrorKind::InvalidInput,
+                format!("cannot read image format: {}", path.display()),
+            )),
+        }
+    }
+
+    /// Encodes the image in the format matching the extension of `path`. The display transform
+    /// is only used by the 8 bit formats, and `exr` only by OpenEXR.
+    pub fn save<P: AsRef<Path>>(
+        &self,
+        path: P,
+        display: &DisplayTransform,
+        exr: &ExrSettings,
+    ) -> std::io::Result<()> {
+        let path = path.as_ref();
+        let format = ImageFormat::from_path(path).ok_or_else(|| {
+            std::io::Error::new(
+                std::io::ErrorKind::InvalidInput,
+                format!("unsupported image format: {}", path.display()),
+            )
+        })?;
+
+        let mut out = BufWriter::new(File::create(path)?);
+        match format {
+            ImageFormat::Ppm => write_ppm_binary(&mut out, self, display)?,
+            ImageFormat::Pfm => write_pfm(&mut out, self)?,
+            ImageFormat::Png => write_png(&mut out, self, display)?,
+            ImageFormat::Exr => write_exr(&mut out, self, exr.pixel_type, exr.compression)?,
+        }
+        out.flush()
+    }
+
+    fn index(&self, x: u32, y: u32) -> usize {
+        assert!(x < self.width && y < self.height);
+        y as usize * self.width as usize + x as usize
+    }
+}
+
+/// Builds the error returned for malformed image files.
+pub(crate) fn invalid_data(message: impl Into<String>) -> std::io::Error {
+    std::io::Error::new(std::io::ErrorKind::InvalidData, message.into())
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn format_from_extension() {
+        assert_eq!(
+            Some(ImageFormat::Ppm),
+            ImageFormat::from_path("out/image.ppm")
+        );
+        assert_eq!(Some(ImageFormat::Pfm), ImageFormat::from_path("image.PFM"));
+        assert_eq!(Some(ImageFormat::Png), ImageFormat::from_path("image.png"));
+        assert_eq!(Some(ImageFormat::Exr), ImageFormat::from_path("image.exr"));
+        assert_eq!(None, ImageFormat::from_path("image.bmp"));
+        assert_eq!(None, ImageFormat::from_path("image"));
+    }
+
+    #[test]
+    fn save_and_load() {
+        let mut image = Image::new(3, 2);
+        image.set_pixel(0, 0, Color::from(0.25, 0.5, 1.5));
+        image.set_pixel(2, 1, Color::from(1f64, 0f64, 0.75));
+
+        let dir = std::env::temp_dir();
+        let display = DisplayTransform::default();
+        for extension in ["ppm", "pfm", "png"] {
+            let path = dir.join(format!("ray_tracing_save_and_load.{extension}"));
+            image
+                .save(&path, &display, &ExrSettings::default())
+                .unwrap();
+            let loaded = Image::load(&path, Transfer::Srgb).unwrap();
+            std::fs::remove_file(&path).unwrap();
+
+            assert_eq!((3, 2), (loaded.width(), loaded.height()));
+            for (a, b) in image.pixels().iter().zip(loaded.pixels()) {
+                // 8 bit formats clip and quantize, PFM only rounds to single precision.
+                let expected = if extension == "pfm" {
+                    *a
+                } else {
+                    Color::from(a.x().min(1f64), a.y().min(1f64), a.z().min(1f64))
+                };
+                assert!((expected - *b).length() < 0.01);
+            }
+        }
+    }
+}
@@ -0,0 +1,147 @@
+use std::sync::Arc;
+
+use crate::aabb::*;
+use crate::hittable::*;
+use crate::ray::*;
+use crate::transform::*;
+
+/// Places a shared object in the world with a transform. The object itself is intersected in its
+/// own space, so many instances can share one copy of heavy geometry such as a mesh.
+///
+/// An animated instance moves with the time of each ray, blurring it when the camera shutter is
+/// open.
+pub struct Instance {
+    object: Arc<dyn Hittable>,
+    transform: Transform,
+    animation: Option<AnimatedTransform>,
+    bbox: Option<Aabb>,
+}
+
+impl Instance {
+    pub fn from(object: Arc<dyn Hittable>, transform: Transform) -> Instance {
+        let bbox = object
+            .bounding_box()
+            .map(|bbox| transform.bounding_box(&bbox));
+        Instance {
+            object,
+            transform,
+            animation: None,
+            bbox,
+        }
+    }
+
+    pub fn animated(object: Arc<dyn Hittable>, animation: AnimatedTransform) -> Instance {
+        let bbox = object
+            .bounding_box()
+            .map(|bbox| animation.bounding_box(&bbox));
+        Instance {
+            object,
+            transform: animation.start().to_transform().unwrap(),
+            animation: Some(animation),
+            bbox,
+        }
+    }
+
+    /// The transform of a still instance, or the one an animated instance starts from.
+    pub fn transform(&self) -> &Transform {
+        &self.transform
+    }
+
+    pub fn animation(&self) -> Option<&AnimatedTransform> {
+        self.animation.as_ref()
+    }
+}
+
+impl Hittable for Instance {
+    fn hit(&self, r: &Ray, t_min: f64, t_max: f64) -> Option<HitRecord<'_>> {
+        let transform = match &self.animation {
+            Some(animation) => animation.at(r.time()),
+            None => self.transform,
+        };
+        // The object space ray keeps the same parametrization, so t needs no conversion.
+        let object_ray = transform.inverse().ray(r);
+        let mut rec = self.object.hit(&object_ray, t_min, t_max)?;
+
+        // The normal transform preserves which side of the surface the ray is on, so the face
+        // orientation found in object space still holds.
+        rec.p = transform.point(&rec.p);
+        rec.normal = transform.normal(&rec.normal).unit_vector();
+        Some(rec)
+    }
+
+    fn bounding_box(&self) -> Option<Aabb> {
+        self.bbox
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::material::*;
+    use crate::sphere::*;
+    use crate::vec3::*;
+
+    #[test]
+    fn hits_transformed_object() {
+        let material = Arc::new(Lambertian::from(Color::from(0.5, 0.5, 0.5)));
+        let sphere: Arc<dyn Hittable> = Arc::new(Sphere::from(Point3::new(), 1f64, material));
+        // An ellipsoid stretched along x, moved to x = 10.
+        let transform = Transform::translate(Vec3::from(10f64, 0f64, 0f64))
+            * Transform::scale(Vec3::from(3f64, 1f64, 1f64)).unwrap();
+        let instance = Instance::from(sphere.clone(), transform);
+
+        let r = Ray::from(Point3::new(), Vec3::from(1f64, 0f64, 0f64));
+        let rec = instance.hit(&r, 0.001, f64::INFINITY).unwrap();
+        assert!((rec.t - 7f64).abs() < 1e-9);
+        assert!((rec.p - Point3::from(7f64, 0f64, 0f64)).near_zero());
+        assert!((rec.normal - Vec3::from(-1f64, 0f64, 0f64)).near_zero());
+        assert!(rec.front_face);
+
+        let r = Ray::from(
+            Point3::from(10f64, 5f64, 0f64),
+            Vec3::from(0f64, -1f64, 0f64),
+        );
+        let rec = instance.hit(&r, 0.001, f64::INFINITY).unwrap();
+        assert!((rec.t - 4f64).abs() < 1e-9);
+        assert!((rec.normal - Vec3::from(0f64, 1f64, 0f64)).near_zero());
+
+        let bbox = instance.bounding_box().unwrap();
+        assert_eq!(Point3::from(7f64, -1f64, -1f64), bbox.min());
+        assert_eq!(Point3::from(13f64, 1f64, 1f64), bbox.max());
+    }
+
+    #[test]
+    fn hits_animated_object() {
+        let material = Arc::new(Lambertian::from(Color::from(0.5, 0.5, 0.5)));
+        let sphere: Arc<dyn Hittable> = Arc::new(Sphere::from(Point3::new(), 1f64, material));
+        let end = TransformComponents {
+            translate: Vec3::from(0f64, 10f64, 0f64),
+            ..TransformComponents::new()
+        };
+        let animation =
+            AnimatedTransform::from(TransformComponents::new(), end, 0f64, 1f64).unwrap();
+        let instance = Instance::animated(sphere, animation);
+
+        let direction = Vec3::from(0f64, 0f64, -1f64);
+        let origin = Point3::from(0f64, 5f64, 5f64);
+        assert!(instance
+            .hit(
+                &Ray::from_time(origin, direction, 0f64),
+                0.001,
+                f64::INFINITY
+            )
+            .is_none());
+        let rec = instance
+            .hit(
+                &Ray::from_time(origin, direction, 0.5),
+                0.001,
+                f64::INFINITY,
+            )
+            .unwrap();
+        assert!((rec.t - 4f64).abs() < 1e-9);
+        assert!((rec.p - Point3::from(0f64, 5f64, 1f64)).near_zero());
+
+        let bbox = instance.bounding_box().unwrap();
+        assert!(*bbox.min().y() <= -1f64 && *bbox.max().y() >= 11f64);
+    }
+}
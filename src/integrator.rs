@@ -0,0 +1,123 @@
+use crate::background::*;
+use crate::hittable::*;
+use crate::ray::*;
+use crate::rng::*;
+use crate::vec3::*;
+
+/// Unidirectional path tracer, lit by emissive materials and the background.
+pub struct PathTracer {
+    max_depth: u32,
+    rr_depth: u32,
+    background: Background,
+}
+
+impl PathTracer {
+    /// Follows at most `max_depth` bounces. From bounce `rr_depth` on, paths are terminated by
+    /// Russian roulette with a probability based on how much light the last bounce absorbed.
+    pub fn from(max_depth: u32, rr_depth: u32) -> PathTracer {
+        PathTracer {
+            max_depth,
+            rr_depth,
+            background: Background::default(),
+        }
+    }
+
+    /// Replaces the default sky gradient.
+    pub fn with_background(mut self, background: Background) -> PathTracer {
+        self.background = background;
+        self
+    }
+
+    pub fn ray_color(&self, r: &Ray, world: &dyn Hittable) -> Color {
+        self.trace(r, world, 0)
+    }
+
+    fn trace(&self, r: &Ray, world: &dyn Hittable, depth: u32) -> Color {
+        // If we've exceeded the ray bounce limit, no more light is gathered.
+        if depth >= self.max_depth {
+            return Color::new();
+        }
+
+        // Start slightly off the surface to avoid self intersection (shadow acne).
+        let rec = match world.hit(r, 0.001, f64::INFINITY) {
+            Some(rec) => rec,
+            None => return self.background.value(r),
+        };
+
+        let emitted = rec.material.emitted(rec.u, rec.v, &rec.p);
+        let (attenuation, scattered) = match rec.material.scatter(r, &rec) {
+            Some(scatter) => scatter,
+            None => return emitted,
+        };
+
+        let mut weight = attenuation;
+        if depth >= self.rr_depth {
+            let survival = attenuation.x().max(*attenuation.y()).max(*attenuation.z());
+            if survival <= 0f64 || random_double() >= survival {
+                return emitted;
+            }
+            if survival < 1f64 {
+                weight /= survival;
+            }
+        }
+
+        emitted + weight * self.trace(&scattered, world, depth + 1)
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use std::sync::Arc;
+
+    use super::PathTracer;
+    use crate::background::Background;
+    use crate::hittable_list::HittableList;
+    use crate::material::{DiffuseLight, Lambertian};
+    use crate::ray::Ray;
+    use crate::sphere::Sphere;
+    use crate::vec3::*;
+
+    #[test]
+    fn depth_limit_and_absorption() {
+        let black = Arc::new(Lambertian::from(Color::new()));
+        let mut world = HittableList::new();
+        world.add(Arc::new(Sphere::from(Point3::new(), 1f64, black)));
+
+        let up = Ray::from(Point3::from(0f64, 5f64, 0f64), Vec3::from(0f64, 1f64, 0f64));
+        let integrator = PathTracer::from(50, 0);
+        assert_eq!(
+            Color::from(0.5, 0.7, 1f64),
+            integrator.ray_color(&up, &world)
+        );
+        assert_eq!(Color::new(), PathTracer::from(0, 0).ray_color(&up, &world));
+
+        let down = Ray::from(
+            Point3::from(0f64, 5f64, 0f64),
+            Vec3::from(0f64, -1f64, 0f64),
+        );
+        assert_eq!(Color::new(), integrator.ray_color(&down, &world));
+    }
+
+    #[test]
+    fn lights_and_background() {
+        let light = Arc::new(DiffuseLight::from(Color::from(4f64, 2f64, 1f64)));
+        let mut world = HittableList::new();
+        world.add(Arc::new(Sphere::from(Point3::new(), 1f64, light)));
+        let integrator = PathTracer::from(50, 0)
+            .with_background(Background::Solid(Color::from(0.1, 0f64, 0f64)));
+
+        let down = Ray::from(
+            Point3::from(0f64, 5f64, 0f64),
+            Vec3::from(0f64, -1f64, 0f64),
+        );
+        assert_eq!(
+            Color::from(4f64, 2f64, 1f64),
+            integrator.ray_color(&down, &world)
+        );
+        let up = Ray::from(Point3::from(0f64, 5f64, 0f64), Vec3::from(0f64, 1f64, 0f64));
+        assert_eq!(
+            Color::from(0.1, 0f64, 0f64),
+            integrator.ray_color(&up, &world)
+        );
+    }
+}
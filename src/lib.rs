@@ -0,0 +1,34 @@
+pub mod aabb;
+pub mod aarect;
+pub mod background;
+pub mod bvh;
+pub mod camera;
+pub mod cli;
+pub mod color;
+pub mod cuboid;
+pub mod deflate;
+pub mod exr;
+pub mod hittable;
+pub mod hittable_list;
+pub mod image;
+pub mod instance;
+pub mod integrator;
+pub mod material;
+pub mod moving_sphere;
+pub mod noise;
+pub mod obj;
+pub mod pfm;
+pub mod png;
+pub mod ppm;
+pub mod quad;
+pub mod ray;
+pub mod render;
+pub mod rng;
+pub mod sampler;
+pub mod scene;
+pub mod sphere;
+pub mod texture;
+pub mod toml;
+pub mod transform;
+pub mod triangle;
+pub mod vec3;
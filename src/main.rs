@@ -1,64 +1,162 @@
-use crate::color::*;
-use crate::ray::*;
-use crate::vec3::*;
+use ray_tracing::bvh::*;
+use ray_tracing::cli::*;
+use ray_tracing::color::*;
+use ray_tracing::exr::*;
+use ray_tracing::image::*;
+use ray_tracing::integrator::*;
+use ray_tracing::ppm::*;
+use ray_tracing::render::*;
+use ray_tracing::rng;
+use ray_tracing::sampler::*;
+use ray_tracing::scene::*;
+use ray_tracing::vec3::*;
 
-mod color;
-mod ray;
-mod vec3;
+// Defaults for everything left out on the command line.
+const ASPECT_RATIO: f64 = 16f64 / 9f64;
+const IMAGE_WIDTH: u32 = 400;
+const SAMPLES_PER_PIXEL: u32 = 100;
+const MAX_DEPTH: u32 = 50;
+const RR_DEPTH: u32 = 3;
+const SEED: u64 = 0;
+const TILE_SIZE: u32 = 16;
+const SCENE: &str = "three-spheres";
 
 fn main() {
+    let options = match Options::parse(std::env::args().skip(1)) {
+        Ok(options) => options,
+        Err(err) => {
+            eprintln!("error: {err}\n\nRun with --help to see the available options.");
+            std::process::exit(2);
+        }
+    };
+    if options.help {
+        print!("{USAGE}");
+        return;
+    }
+
+    // World
+
+    let scene_name = options.scene.clone().unwrap_or(SCENE.to_string());
+    // Random built-in scenes are laid out from the seed given on the command line.
+    rng::seed(options.seed.unwrap_or(SEED));
+    let scene = match Scene::builtin(&scene_name) {
+        Some(scene) => scene,
+        None if scene_name.ends_with(".toml") => {
+            Scene::load(&scene_name).unwrap_or_else(|err| fail(&format!("{scene_name}: {err}")))
+        }
+        None => fail(&format!(
+            "unknown scene '{scene_name}', expected a .toml file or one of {}",
+            Scene::BUILTIN_NAMES.join(", ")
+        )),
+    };
+    // The command line takes precedence over the scene's own settings.
+    let options = options.or(scene.render);
+    let seed = options.seed.unwrap_or(SEED);
+
     // Image
-    const ASPECT_RATIO: f64 = 16f64 / 9f64;
-    const IMAGE_WIDTH: i32 = 400;
-    const IMAGE_HEIGHT: i32 = (IMAGE_WIDTH as f64 / ASPECT_RATIO) as i32;
 
-    // Camera
+    if options.width.is_some() && options.height.is_some() && options.aspect_ratio.is_some() {
+        fail("the image width, height and aspect ratio cannot all be given");
+    }
 
-    let viewport_height = 2f64;
-    let viewport_width = ASPECT_RATIO * viewport_height;
-    let focal_length = 1f64;
+    let (width, height) = match (options.width, options.height) {
+        (Some(width), Some(height)) => (width, height),
+        (width, Some(height)) => {
+            let aspect_ratio = options.aspect_ratio.unwrap_or(ASPECT_RATIO);
+            let width = width.unwrap_or((height as f64 * aspect_ratio) as u32);
+            (width, height)
+        }
+        (width, None) => {
+            let width = width.unwrap_or(IMAGE_WIDTH);
+            let aspect_ratio = options.aspect_ratio.unwrap_or(ASPECT_RATIO);
+            (width, (width as f64 / aspect_ratio) as u32)
+        }
+    };
+    if width < 2 || height < 2 {
+        fail(&format!(
+            "the image must be at least 2x2 pixels, not {width}x{height}"
+        ));
+    }
 
-    let origin = Point3::from(0f64, 0f64, 0f64);
-    let horizontal = Vec3::from(viewport_width, 0f64, 0f64);
-    let vertical = Vec3::from(0f64, viewport_height, 0f64);
-    let lower_left_corner =
-        origin - horizontal / 2f64 - vertical / 2f64 - Vec3::from(0f64, 0f64, focal_length);
+    // Camera
 
-    // Render
+    let mut camera_settings = scene.camera;
+    camera_settings.lookfrom = options.lookfrom.unwrap_or(camera_settings.lookfrom);
+    camera_settings.lookat = options.lookat.unwrap_or(camera_settings.lookat);
+    camera_settings.vup = options.vup.unwrap_or(camera_settings.vup);
+    camera_settings.vfov = options.vfov.unwrap_or(camera_settings.vfov);
+    camera_settings.aperture = options.aperture.unwrap_or(camera_settings.aperture);
+    camera_settings.focus_dist = options.focus_dist.or(camera_settings.focus_dist);
+    camera_settings.shutter = options.shutter.unwrap_or(camera_settings.shutter);
+    if (camera_settings.lookfrom - camera_settings.lookat).near_zero() {
+        fail("the camera cannot look at its own position");
+    }
+    let view = camera_settings.lookfrom - camera_settings.lookat;
+    if Vec3::cross(&camera_settings.vup, &view).near_zero() {
+        fail("'vup' cannot point along the view direction");
+    }
+    let camera = camera_settings.build(width as f64 / height as f64);
 
-    println!("P3\n{IMAGE_WIDTH} {IMAGE_HEIGHT}\n255");
+    // Render
 
-    for j in (0..IMAGE_HEIGHT).rev() {
-        eprintln!("\rScanlines remaining: {j}");
-        for i in 0..IMAGE_WIDTH {
-            let u = (i as f64) / (IMAGE_WIDTH as f64 - 1f64);
-            let v = (j as f64) / (IMAGE_HEIGHT as f64 - 1f64);
+    let sampler = options
+        .sampler
+        .unwrap_or(SamplerKind::Stratified)
+        .build(seed);
+    let integrator = PathTracer::from(
+        options.max_depth.unwrap_or(MAX_DEPTH),
+        options.rr_depth.unwrap_or(RR_DEPTH),
+    )
+    .with_background(scene.background);
+    let display = DisplayTransform::from(
+        options.tone_map.unwrap_or(ToneMap::Clamp),
+        options.transfer.unwrap_or(Transfer::Srgb),
+    );
+    let exr = ExrSettings {
+        pixel_type: options.exr_pixel_type.unwrap_or(ExrPixelType::Half),
+        compression: options.exr_compression.unwrap_or(ExrCompression::Zip),
+    };
 
-            let r: Ray = Ray::from(
-                origin,
-                lower_left_corner + u * horizontal + v * vertical - origin,
-            );
-            let pixel_color = ray_color(&r);
+    let settings = RenderSettings {
+        width,
+        height,
+        samples_per_pixel: options.samples_per_pixel.unwrap_or(SAMPLES_PER_PIXEL),
+        tile_size: options.tile_size.unwrap_or(TILE_SIZE),
+        threads: options.threads.unwrap_or(0),
+        seed,
+        // Depth and normals only fit in EXR files.
+        aovs: options.aovs.unwrap_or(true)
+            && options
+                .output
+                .as_ref()
+                .is_some_and(|path| ImageFormat::from_path(path) == Some(ImageFormat::Exr)),
+        progress: Some(|remaining| eprint!("\rTiles remaining: {remaining:5}")),
+    };
+    let world = BvhList::from(scene.world.objects());
+    if options.verbose {
+        eprintln!("BVH: {}", world.stats());
+    }
+    let image = render(&settings, &camera, &world, &integrator, sampler.as_ref());
+    eprintln!();
 
-            write_color(std::io::stdout(), pixel_color).unwrap();
+    // Write to the output file, in the format matching its extension, or as ASCII PPM to stdout.
+    match options.output {
+        Some(path) => {
+            if let Err(err) = image.save(&path, &display, &exr) {
+                fail(&format!("cannot write {path}: {err}"));
+            }
+        }
+        None => {
+            let out = std::io::BufWriter::new(std::io::stdout().lock());
+            if let Err(err) = write_ppm_ascii(out, &image, &display) {
+                fail(&format!("cannot write the image: {err}"));
+            }
         }
     }
-    eprintln!("\nDone");
+    eprintln!("Done");
 }
 
-fn hit_sphere(center: &Point3, radius: f64, r: &Ray) -> bool {
-    let oc: Vec3 = r.origin() - *center;
-    let a = Vec3::dot(&r.direction(), &r.direction());
-    let b = 2.0 * Vec3::dot(&oc, &r.direction());
-    let c = Vec3::dot(&oc, &oc) - radius * radius;
-    (b * b - 4.0 * a * c) > 0f64
-}
-
-fn ray_color(r: &Ray) -> Color {
-    if hit_sphere(&Point3::from(0f64, 0f64, -1f64), 0.5, r) {
-        return Color::from(1f64, 0f64, 0f64);
-    }
-    let unit_direction: Vec3 = r.direction().unit_vector();
-    let t = 0.5 * (unit_direction.y() + 1f64);
-    (1f64 - t) * Color::from(1f64, 1f64, 1f64) + t * Color::from(0.5, 0.7, 1f64)
+fn fail(message: &str) -> ! {
+    eprintln!("error: {message}");
+    std::process::exit(1);
 }
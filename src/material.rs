@@ -0,0 +1,207 @@
+use std::sync::Arc;
+
+use crate::hittable::*;
+use crate::ray::*;
+use crate::rng::*;
+use crate::texture::*;
+use crate::vec3::*;
+
+pub trait Material: Send + Sync {
+    /// Returns the attenuation and the scattered ray, or `None` if the incoming ray is absorbed.
+    fn scatter(&self, r_in: &Ray, rec: &HitRecord) -> Option<(Color, Ray)>;
+
+    /// Radiance given off at the surface point `p` with coordinates `(u, v)`. Only lights emit.
+    fn emitted(&self, _u: f64, _v: f64, _p: &Point3) -> Color {
+        Color::new()
+    }
+}
+
+/// Ideal diffuse reflector, with an albedo that may vary over the surface.
+pub struct Lambertian {
+    albedo: Arc<dyn Texture>,
+}
+
+impl Lambertian {
+    pub fn from(albedo: Color) -> Lambertian {
+        Lambertian::from_texture(Arc::new(SolidColor::from(albedo)))
+    }
+
+    pub fn from_texture(albedo: Arc<dyn Texture>) -> Lambertian {
+        Lambertian { albedo }
+    }
+}
+
+impl Material for Lambertian {
+    fn scatter(&self, r_in: &Ray, rec: &HitRecord) -> Option<(Color, Ray)> {
+        let mut scatter_direction = rec.normal + Vec3::random_unit_vector();
+
+        // Catch degenerate scatter direction
+        if scatter_direction.near_zero() {
+            scatter_direction = rec.normal;
+        }
+
+        Some((
+            self.albedo.value(rec.u, rec.v, &rec.p),
+            Ray::from_time(rec.p, scatter_direction, r_in.time()),
+        ))
+    }
+}
+
+/// Mirror reflector; `fuzz` in `[0, 1]` randomly perturbs the reflected direction.
+pub struct Metal {
+    albedo: Color,
+    fuzz: f64,
+}
+
+impl Metal {
+    pub fn from(albedo: Color, fuzz: f64) -> Metal {
+        Metal {
+            albedo,
+            fuzz: fuzz.min(1f64),
+        }
+    }
+}
+
+impl Material for Metal {
+    fn scatter(&self, r_in: &Ray, rec: &HitRecord) -> Option<(Color, Ray)> {
+        let reflected = Vec3::reflect(&r_in.direction().unit_vector(), &rec.normal);
+        let scattered = Ray::from_time(
+            rec.p,
+            reflected + self.fuzz * Vec3::random_in_unit_sphere(),
+            r_in.time(),
+        );
+
+        // Fuzzed rays that end up below the surface are absorbed.
+        if Vec3::dot(&scattered.direction(), &rec.normal) > 0f64 {
+            Some((self.albedo, scattered))
+        } else {
+            None
+        }
+    }
+}
+
+/// Clear refractive material such as glass or water, described by its index of refraction.
+pub struct Dielectric {
+    ir: f64,
+}
+
+impl Dielectric {
+    pub fn from(index_of_refraction: f64) -> Dielectric {
+        Dielectric {
+            ir: index_of_refraction,
+        }
+    }
+
+    /// Schlick's approximation of the Fresnel reflectance.
+    pub fn reflectance(cosine: f64, ref_idx: f64) -> f64 {
+        let r0 = (1f64 - ref_idx) / (1f64 + ref_idx);
+        let r0 = r0 * r0;
+        r0 + (1f64 - r0) * (1f64 - cosine).powi(5)
+    }
+}
+
+impl Material for Dielectric {
+    fn scatter(&self, r_in: &Ray, rec: &HitRecord) -> Option<(Color, Ray)> {
+        let refraction_ratio = if rec.front_face {
+            1f64 / self.ir
+        } else {
+            self.ir
+        };
+
+        let unit_direction = r_in.direction().unit_vector();
+        let cos_theta = Vec3::dot(&-unit_direction, &rec.normal).min(1f64);
+        let sin_theta = (1f64 - cos_theta * cos_theta).sqrt();
+
+        let cannot_refract = refraction_ratio * sin_theta > 1f64;
+        let direction = if cannot_refract
+            || Dielectric::reflectance(cos_theta, refraction_ratio) > random_double()
+        {
+            Vec3::reflect(&unit_direction, &rec.normal)
+        } else {
+            Vec3::refract(&unit_direction, &rec.normal, refraction_ratio)
+        };
+
+        Some((
+            Color::from(1f64, 1f64, 1f64),
+            Ray::from_time(rec.p, direction, r_in.time()),
+        ))
+    }
+}
+
+/// Area light: emits the color of `emit` from both sides of the surface and reflects nothing.
+pub struct DiffuseLight {
+    emit: Arc<dyn Texture>,
+}
+
+impl DiffuseLight {
+    pub fn from(color: Color) -> DiffuseLight {
+        DiffuseLight::from_texture(Arc::new(SolidColor::from(color)))
+    }
+
+    pub fn from_texture(emit: Arc<dyn Texture>) -> DiffuseLight {
+        DiffuseLight { emit }
+    }
+}
+
+impl Material for DiffuseLight {
+    fn scatter(&self, _r_in: &Ray, _rec: &HitRecord) -> Option<(Color, Ray)> {
+        None
+    }
+
+    fn emitted(&self, u: f64, v: f64, p: &Point3) -> Color {
+        self.emit.value(u, v, p)
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    fn record<'a>(r: &Ray, material: &'a dyn Material) -> HitRecord<'a> {
+        HitRecord::from(r, 1f64, Vec3::from(0f64, 1f64, 0f64), 0f64, 0f64, material)
+    }
+
+    #[test]
+    fn lambertian_scatters_above_surface() {
+        let material = Lambertian::from(Color::from(0.5, 0.5, 0.5));
+        let r = Ray::from(
+            Point3::from(0f64, 1f64, 0f64),
+            Vec3::from(0f64, -1f64, 0f64),
+        );
+        let rec = record(&r, &material);
+        for _ in 0..32 {
+            let (attenuation, scattered) = material.scatter(&r, &rec).unwrap();
+            assert_eq!(Color::from(0.5, 0.5, 0.5), attenuation);
+            assert!(Vec3::dot(&scattered.direction(), &rec.normal) >= 0f64);
+        }
+    }
+
+    #[test]
+    fn metal_mirrors() {
+        let material = Metal::from(Color::from(0.8, 0.6, 0.2), 0f64);
+        let r = Ray::from(
+            Point3::from(-1f64, 1f64, 0f64),
+            Vec3::from(1f64, -1f64, 0f64),
+        );
+        let rec = record(&r, &material);
+        let (_, scattered) = material.scatter(&r, &rec).unwrap();
+        assert_eq!(rec.p, scattered.origin());
+        let expected = Vec3::from(1f64, 1f64, 0f64).unit_vector();
+        assert!((scattered.direction() - expected).length() < 1e-12);
+    }
+
+    #[test]
+    fn dielectric_reflectance() {
+        assert!((Dielectric::reflectance(1f64, 1.5) - 0.04).abs() < 1e-12);
+        assert_eq!(1f64, Dielectric::reflectance(0f64, 1.5));
+
+        // Leaving glass at a grazing angle is always a total internal reflection.
+        let material = Dielectric::from(1.5);
+        let r = Ray::from(Point3::new(), Vec3::from(1f64, -0.2, 0f64));
+        let mut rec = record(&r, &material);
+        rec.front_face = false;
+        let (attenuation, scattered) = material.scatter(&r, &rec).unwrap();
+        assert_eq!(Color::from(1f64, 1f64, 1f64), attenuation);
+        assert!(*scattered.direction().y() > 0f64);
+    }
+}
@@ -0,0 +1,108 @@
+use std::sync::Arc;
+
+use crate::aabb::*;
+use crate::hittable::*;
+use crate::material::*;
+use crate::ray::*;
+use crate::sphere::*;
+use crate::vec3::*;
+
+/// A sphere moving in a straight line from `center0` at `time0` to `center1` at `time1`. Outside
+/// that interval it rests at the nearest end, as animated transforms do.
+pub struct MovingSphere {
+    center0: Point3,
+    center1: Point3,
+    time0: f64,
+    time1: f64,
+    radius: f64,
+    material: Arc<dyn Material>,
+}
+
+impl MovingSphere {
+    pub fn from(
+        center0: Point3,
+        center1: Point3,
+        time0: f64,
+        time1: f64,
+        radius: f64,
+        material: Arc<dyn Material>,
+    ) -> MovingSphere {
+        MovingSphere {
+            center0,
+            center1,
+            time0,
+            time1,
+            radius,
+            material,
+        }
+    }
+
+    pub fn center(&self, time: f64) -> Point3 {
+        let s = if self.time1 > self.time0 {
+            ((time - self.time0) / (self.time1 - self.time0)).clamp(0f64, 1f64)
+        } else if time < self.time0 {
+            0f64
+        } else {
+            1f64
+        };
+        self.center0 + s * (self.center1 - self.center0)
+    }
+}
+
+impl Hittable for MovingSphere {
+    fn hit(&self, r: &Ray, t_min: f64, t_max: f64) -> Option<HitRecord<'_>> {
+        hit_sphere(
+            self.center(r.time()),
+            self.radius,
+            self.material.as_ref(),
+            r,
+            t_min,
+            t_max,
+        )
+    }
+
+    /// The box swept over `time0..time1`, which holds the sphere at any time.
+    fn bounding_box(&self) -> Option<Aabb> {
+        let r = Vec3::from(self.radius, self.radius, self.radius);
+        Some(Aabb::surrounding_box(
+            &Aabb::from(self.center0 - r, self.center0 + r),
+            &Aabb::from(self.center1 - r, self.center1 + r),
+        ))
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn moves_with_ray_time() {
+        let material = Arc::new(Lambertian::from(Color::from(0.5, 0.5, 0.5)));
+        let sphere = MovingSphere::from(
+            Point3::from(0f64, 0f64, -5f64),
+            Point3::from(4f64, 0f64, -5f64),
+            0f64,
+            1f64,
+            1f64,
+            material,
+        );
+        assert_eq!(Point3::from(2f64, 0f64, -5f64), sphere.center(0.5));
+
+        let direction = Vec3::from(0f64, 0f64, -1f64);
+        let at_start = Ray::from_time(Point3::new(), direction, 0f64);
+        assert_eq!(4f64, sphere.hit(&at_start, 0.001, f64::INFINITY).unwrap().t);
+        let at_end = Ray::from_time(Point3::new(), direction, 1f64);
+        assert!(sphere.hit(&at_end, 0.001, f64::INFINITY).is_none());
+        let on_path = Ray::from_time(Point3::from(4f64, 0f64, 0f64), direction, 1f64);
+        let rec = sphere.hit(&on_path, 0.001, f64::INFINITY).unwrap();
+        assert_eq!(Vec3::from(0f64, 0f64, 1f64), rec.normal);
+
+        // Past its interval the sphere stays at the end, inside its bounding box.
+        assert_eq!(Point3::from(4f64, 0f64, -5f64), sphere.center(2f64));
+        assert_eq!(Point3::from(0f64, 0f64, -5f64), sphere.center(-1f64));
+
+        let bbox = sphere.bounding_box().unwrap();
+        assert_eq!(Point3::from(-1f64, -1f64, -6f64), bbox.min());
+        assert_eq!(Point3::from(5f64, 1f64, -4f64), bbox.max());
+    }
+}
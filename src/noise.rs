@@ -0,0 +1,440 @@
+use crate::rng::*;
+use crate::vec3::*;
+
+/// A scalar field that varies smoothly in space, for procedural textures, bumps and volumes.
+pub trait Noise: Send + Sync {
+    fn noise(&self, p: &Point3) -> f64;
+
+    /// Bounds on the values of [`Noise::noise`], so textures can map them to colors.
+    fn range(&self) -> (f64, f64) {
+        (-1f64, 1f64)
+    }
+}
+
+const POINT_COUNT: usize = 256;
+
+/// Ken Perlin's lattice noise. [`Perlin::noise`] is the gradient noise, smooth in space and
+/// between -1 and 1; the value noise variants are the steps leading up to it.
+pub struct Perlin {
+    gradients: Vec<Vec3>,
+    values: Vec<f64>,
+    perm_x: Vec<usize>,
+    perm_y: Vec<usize>,
+    perm_z: Vec<usize>,
+}
+
+impl Perlin {
+    /// The same `seed` always gives the same noise.
+    pub fn from(seed: u64) -> Perlin {
+        let mut rng = Rng::from(seed);
+        let gradients = (0..POINT_COUNT)
+            .map(|_| loop {
+                let v = Vec3::from(
+                    2f64 * rng.next_double() - 1f64,
+                    2f64 * rng.next_double() - 1f64,
+                    2f64 * rng.next_double() - 1f64,
+                );
+                if v.length_squared() <= 1f64 && !v.near_zero() {
+                    break v.unit_vector();
+                }
+            })
+            .collect();
+        let perm_x = permutation(&mut rng);
+        let perm_y = permutation(&mut rng);
+        let perm_z = permutation(&mut rng);
+        Perlin {
+            gradients,
+            values: (0..POINT_COUNT).map(|_| rng.next_double()).collect(),
+            perm_x,
+            perm_y,
+            perm_z,
+        }
+    }
+
+    /// Index of the random gradient or value at lattice point `(i, j, k)`.
+    fn hash(&self, i: i64, j: i64, k: i64) -> usize {
+        self.perm_x[(i & 255) as usize]
+            ^ self.perm_y[(j & 255) as usize]
+            ^ self.perm_z[(k & 255) as usize]
+    }
+
+    /// A random value in `[0, 1)` per unit cube, without any interpolation.
+    pub fn blocky(&self, p: &Point3) -> f64 {
+        let (i, j, k) = (p.x().floor(), p.y().floor(), p.z().floor());
+        self.values[self.hash(i as i64, j as i64, k as i64)]
+    }
+
+    /// Random values at the lattice points, interpolated trilinearly. With `smooth`, the weights
+    /// go through a Hermite cubic, which hides the grid that plain trilinear interpolation shows.
+    pub fn value_noise(&self, p: &Point3, smooth: bool) -> f64 {
+        let (fx, fy, fz) = (p.x().floor(), p.y().floor(), p.z().floor());
+        let (mut u, mut v, mut w) = (p.x() - fx, p.y() - fy, p.z() - fz);
+        if smooth {
+            (u, v, w) = (hermite(u), hermite(v), hermite(w));
+        }
+        let (i, j, k) = (fx as i64, fy as i64, fz as i64);
+
+        let mut accum = 0f64;
+        for di in 0..2 {
+            for dj in 0..2 {
+                for dk in 0..2 {
+                    let (a, b, c) = (di as f64, dj as f64, dk as f64);
+                    accum += (a * u + (1f64 - a) * (1f64 - u))
+                        * (b * v + (1f64 - b) * (1f64 - v))
+                        * (c * w + (1f64 - c) * (1f64 - w))
+                        * self.values[self.hash(i + di, j + dj, k + dk)];
+                }
+            }
+        }
+        accum
+    }
+}
+
+impl Noise for Perlin {
+    /// Interpolates the random gradients at the corners of the unit cell around `p`, with
+    /// Hermite smoothing.
+    fn noise(&self, p: &Point3) -> f64 {
+        let (fx, fy, fz) = (p.x().floor(), p.y().floor(), p.z().floor());
+        let (u, v, w) = (p.x() - fx, p.y() - fy, p.z() - fz);
+        let (i, j, k) = (fx as i64, fy as i64, fz as i64);
+
+        let mut accum = 0f64;
+        let (uu, vv, ww) = (hermite(u), hermite(v), hermite(w));
+        for di in 0..2 {
+            for dj in 0..2 {
+                for dk in 0..2 {
+                    let gradient = self.gradients[self.hash(i + di, j + dj, k + dk)];
+                    let (a, b, c) = (di as f64, dj as f64, dk as f64);
+                    let weight = Vec3::from(u - a, v - b, w - c);
+                    accum += (a * uu + (1f64 - a) * (1f64 - uu))
+                        * (b * vv + (1f64 - b) * (1f64 - vv))
+                        * (c * ww + (1f64 - c) * (1f64 - ww))
+                        * Vec3::dot(&gradient, &weight);
+                }
+            }
+        }
+        accum
+    }
+}
+
+fn hermite(t: f64) -> f64 {
+    t * t * (3f64 - 2f64 * t)
+}
+
+/// A random shuffle of `0..POINT_COUNT`.
+fn permutation(rng: &mut Rng) -> Vec<usize> {
+    let mut p: Vec<usize> = (0..POINT_COUNT).collect();
+    for i in (1..POINT_COUNT).rev() {
+        let target = (rng.next_double() * (i + 1) as f64) as usize;
+        p.swap(i, target);
+    }
+    p
+}
+
+/// The gradients of simplex noise: the midpoints of the edges of a cube.
+const SIMPLEX_GRADIENTS: [[f64; 3]; 12] = [
+    [1f64, 1f64, 0f64],
+    [-1f64, 1f64, 0f64],
+    [1f64, -1f64, 0f64],
+    [-1f64, -1f64, 0f64],
+    [1f64, 0f64, 1f64],
+    [-1f64, 0f64, 1f64],
+    [1f64, 0f64, -1f64],
+    [-1f64, 0f64, -1f64],
+    [0f64, 1f64, 1f64],
+    [0f64, -1f64, 1f64],
+    [0f64, 1f64, -1f64],
+    [0f64, -1f64, -1f64],
+];
+
+/// Ken Perlin's simplex noise, between -1 and 1. It sums the contributions of the four corners
+/// of the tetrahedron around `p` rather than the eight of a cube, and has fewer directional
+/// artifacts than [`Perlin`] noise.
+pub struct Simplex {
+    perm: Vec<usize>,
+}
+
+impl Simplex {
+    /// The same `seed` always gives the same noise.
+    pub fn from(seed: u64) -> Simplex {
+        Simplex {
+            perm: permutation(&mut Rng::from(seed)),
+        }
+    }
+
+    fn gradient(&self, i: i64, j: i64, k: i64) -> Vec3 {
+        let hash = |n: i64, offset: usize| self.perm[(n as usize).wrapping_add(offset) & 255];
+        let index = hash(i, hash(j, hash(k, 0)));
+        let [x, y, z] = SIMPLEX_GRADIENTS[index % 12];
+        Vec3::from(x, y, z)
+    }
+}
+
+impl Noise for Simplex {
+    fn noise(&self, p: &Point3) -> f64 {
+        const SKEW: f64 = 1f64 / 3f64;
+        const UNSKEW: f64 = 1f64 / 6f64;
+
+        // The simplex cell holding p, found in the skewed grid where the cells are cubes.
+        let s = (p.x() + p.y() + p.z()) * SKEW;
+        let (i, j, k) = (
+            (p.x() + s).floor(),
+            (p.y() + s).floor(),
+            (p.z() + s).floor(),
+        );
+        let t = (i + j + k) * UNSKEW;
+        let d0 = *p - Vec3::from(i - t, j - t, k - t);
+
+        // Which of the six tetrahedra of the cube p is in decides the two middle corners.
+        let (x, y, z) = (*d0.x(), *d0.y(), *d0.z());
+        let (o1, o2) = if x >= y {
+            if y >= z {
+                ([1, 0, 0], [1, 1, 0])
+            } else if x >= z {
+                ([1, 0, 0], [1, 0, 1])
+            } else {
+                ([0, 0, 1], [1, 0, 1])
+            }
+        } else if y < z {
+            ([0, 0, 1], [0, 1, 1])
+        } else if x < z {
+            ([0, 1, 0], [0, 1, 1])
+        } else {
+            ([0, 1, 0], [1, 1, 0])
+        };
+
+        let (i, j, k) = (i as i64, j as i64, k as i64);
+        let offset = |o: [i64; 3], n: f64| {
+            Vec3::from(o[0] as f64, o[1] as f64, o[2] as f64) - Vec3::from(n, n, n) * UNSKEW
+        };
+        let corners = [
+            ([0, 0, 0], d0),
+            (o1, d0 - offset(o1, 1f64)),
+            (o2, d0 - offset(o2, 2f64)),
+            ([1, 1, 1], d0 - offset([1, 1, 1], 3f64)),
+        ];
+
+        let mut accum = 0f64;
+        for (o, d) in corners {
+            let falloff = 0.6 - d.length_squared();
+            if falloff > 0f64 {
+                let gradient = self.gradient(i + o[0], j + o[1], k + o[2]);
+                accum += falloff.powi(4) * Vec3::dot(&gradient, &d);
+            }
+        }
+        // Brings the extremes close to -1 and 1.
+        32f64 * accum
+    }
+}
+
+/// Steven Worley's cellular noise: the distance from `p` to the nearest of a set of random
+/// feature points, one per unit cube.
+pub struct Worley {
+    seed: u64,
+}
+
+impl Worley {
+    /// The same `seed` always gives the same noise.
+    pub fn from(seed: u64) -> Worley {
+        Worley { seed }
+    }
+
+    /// The random feature point in the unit cube at `(i, j, k)`.
+    fn feature_point(&self, i: i64, j: i64, k: i64) -> Point3 {
+        let hash = (i as u64).wrapping_mul(0x8DA6_B343)
+            ^ (j as u64).wrapping_mul(0xD816_3841)
+            ^ (k as u64).wrapping_mul(0xCB1A_B31F)
+            ^ self.seed.wrapping_mul(0x9E37_79B9_7F4A_7C15);
+        let mut rng = Rng::from(hash);
+        Point3::from(
+            i as f64 + rng.next_double(),
+            j as f64 + rng.next_double(),
+            k as f64 + rng.next_double(),
+        )
+    }
+
+    /// Distances to the nearest and the second nearest feature points. Their difference outlines
+    /// the cells.
+    pub fn distances(&self, p: &Point3) -> (f64, f64) {
+        let (i, j, k) = (
+            p.x().floor() as i64,
+            p.y().floor() as i64,
+            p.z().floor() as i64,
+        );
+        let (mut f1, mut f2) = (f64::INFINITY, f64::INFINITY);
+        // The points of this cube and of the one across its nearest face are both within sqrt(3),
+        // while cubes three or more steps away are at least 2 away, so the two nearest points lie
+        // within two cubes. Searching one step out would occasionally miss the second nearest.
+        for di in -2..=2 {
+            for dj in -2..=2 {
+                for dk in -2..=2 {
+                    let d = (self.feature_point(i + di, j + dj, k + dk) - *p).length_squared();
+                    if d < f1 {
+                        f2 = f1;
+                        f1 = d;
+                    } else if d < f2 {
+                        f2 = d;
+                    }
+                }
+            }
+        }
+        (f1.sqrt(), f2.sqrt())
+    }
+}
+
+impl Noise for Worley {
+    fn noise(&self, p: &Point3) -> f64 {
+        self.distances(p).0
+    }
+
+    /// The feature point of the cube around `p` is never further than its diagonal.
+    fn range(&self) -> (f64, f64) {
+        (0f64, 3f64.sqrt())
+    }
+}
+
+/// Fractal Brownian motion: `octaves` layers of `noise`, each `lacunarity` times the frequency
+/// and `gain` times the amplitude of the previous one. The sum is divided by the total amplitude
+/// so it keeps the range of the noise.
+pub fn fbm<N: Noise + ?Sized>(
+    noise: &N,
+    p: &Point3,
+    octaves: u32,
+    lacunarity: f64,
+    gain: f64,
+) -> f64 {
+    let mut accum = 0f64;
+    let mut total = 0f64;
+    let mut amplitude = 1f64;
+    let mut p = *p;
+    for _ in 0..octaves {
+        accum += amplitude * noise.noise(&p);
+        total += amplitude;
+        amplitude *= gain;
+        p = lacunarity * p;
+    }
+    if total > 0f64 {
+        accum / total
+    } else {
+        0f64
+    }
+}
+
+/// Sum of `depth` octaves of `noise`, each at twice the frequency and half the weight of the
+/// previous one, taken in absolute value.
+pub fn turbulence<N: Noise + ?Sized>(noise: &N, p: &Point3, depth: u32) -> f64 {
+    let mut accum = 0f64;
+    let mut p = *p;
+    let mut weight = 1f64;
+    for _ in 0..depth {
+        accum += weight * noise.noise(&p);
+        weight *= 0.5;
+        p = 2f64 * p;
+    }
+    accum.abs()
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    /// Samples `noise` along a line, checking it is deterministic and within `range`.
+    fn sample<N: Noise>(noise: &N, same: &N, range: std::ops::RangeInclusive<f64>) -> Vec<f64> {
+        (0..200)
+            .map(|i| {
+                let p = Point3::from(i as f64 * 0.37, i as f64 * 0.11, -(i as f64) * 0.23);
+                let value = noise.noise(&p);
+                assert!(range.contains(&value), "{value}");
+                assert_eq!(value, same.noise(&p));
+                value
+            })
+            .collect()
+    }
+
+    fn is_continuous<N: Noise>(noise: &N) -> bool {
+        let a = noise.noise(&Point3::from(0.999_999, 0.5, 0.25));
+        let b = noise.noise(&Point3::from(1.000_001, 0.5, 0.25));
+        (a - b).abs() < 1e-4
+    }
+
+    #[test]
+    fn perlin_noise() {
+        let noise = Perlin::from(1);
+        let values = sample(&noise, &Perlin::from(1), -1f64..=1f64);
+        assert!(values.iter().any(|&v| v > 0.1) && values.iter().any(|&v| v < -0.1));
+        assert_ne!(
+            noise.noise(&Point3::from(0.5, 0.5, 0.5)),
+            Perlin::from(2).noise(&Point3::from(0.5, 0.5, 0.5))
+        );
+
+        // Zero on the lattice, and continuous across it.
+        assert_eq!(0f64, noise.noise(&Point3::from(3f64, -2f64, 5f64)));
+        assert!(is_continuous(&noise));
+
+        // Value noise matches the random values at the lattice points, where the blocky noise
+        // jumps.
+        let corner = Point3::from(2f64, 1f64, -3f64);
+        assert_eq!(noise.blocky(&corner), noise.value_noise(&corner, true));
+        assert_eq!(noise.blocky(&corner), noise.value_noise(&corner, false));
+        let p = Point3::from(2.3, 1.7, -2.6);
+        assert!((0f64..1f64).contains(&noise.value_noise(&p, true)));
+    }
+
+    #[test]
+    fn simplex_noise() {
+        let noise = Simplex::from(1);
+        let values = sample(&noise, &Simplex::from(1), -1f64..=1f64);
+        assert!(values.iter().any(|&v| v > 0.2) && values.iter().any(|&v| v < -0.2));
+        assert!(is_continuous(&noise));
+        assert_eq!(0f64, noise.noise(&Point3::new()));
+    }
+
+    #[test]
+    fn worley_noise() {
+        let noise = Worley::from(1);
+        sample(&noise, &Worley::from(1), 0f64..=3f64.sqrt());
+        assert!(is_continuous(&noise));
+        let p = Point3::from(0.3, 0.6, 0.9);
+        let (f1, f2) = noise.distances(&p);
+        assert!(f1 <= f2);
+        assert_eq!(0f64, noise.noise(&noise.feature_point(4, -2, 7)));
+
+        // The same as searching further out, including at a point whose second nearest feature
+        // point is two cubes away.
+        let mut rng = Rng::from(3);
+        let far = Point3::from(6.678230166447163, 5.15378377560627, 9.034523592606691);
+        for n in 0..200 {
+            let p = match n {
+                0 => far,
+                _ => Point3::from(
+                    10f64 * rng.next_double(),
+                    10f64 * rng.next_double(),
+                    10f64 * rng.next_double(),
+                ),
+            };
+            let mut d: Vec<f64> = (-3..=3)
+                .flat_map(|i| (-3..=3).flat_map(move |j| (-3..=3).map(move |k| (i, j, k))))
+                .map(|(i, j, k)| {
+                    let cell = |x: &f64| x.floor() as i64;
+                    let q = noise.feature_point(cell(p.x()) + i, cell(p.y()) + j, cell(p.z()) + k);
+                    (q - p).length()
+                })
+                .collect();
+            d.sort_by(f64::total_cmp);
+            assert_eq!((d[0], d[1]), noise.distances(&p));
+        }
+    }
+
+    #[test]
+    fn fractal_sums() {
+        let noise = Perlin::from(1);
+        let p = Point3::from(0.3, 0.6, 0.9);
+        assert_eq!(noise.noise(&p), fbm(&noise, &p, 1, 2f64, 0.5));
+        let expected = (noise.noise(&p) + 0.5 * noise.noise(&(2f64 * p))) / 1.5;
+        assert!((expected - fbm(&noise, &p, 2, 2f64, 0.5)).abs() < 1e-12);
+        assert_eq!(0f64, fbm(&noise, &p, 0, 2f64, 0.5));
+
+        let turb = turbulence(&noise as &dyn Noise, &p, 7);
+        assert!((0f64..=2f64).contains(&turb));
+    }
+}
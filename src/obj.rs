@@ -0,0 +1,456 @@
+//! Wavefront OBJ and MTL import.
+//!
+//! Faces are triangulated as fans, and every distinct position/uv/normal combination becomes one
+//! shared vertex, so the result is an indexed triangle mesh per material.
+
+use std::collections::HashMap;
+use std::io::BufRead;
+use std::path::{Path, PathBuf};
+use std::sync::Arc;
+
+use crate::image::*;
+use crate::material::*;
+use crate::texture::*;
+use crate::vec3::*;
+
+/// Triangles sharing one material, with indexed vertices.
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct ObjMesh {
+    /// Object name from the last `o` statement, if any.
+    pub name: String,
+    /// Material name from `usemtl`, if any.
+    pub material: Option<String>,
+    pub positions: Vec<Point3>,
+    /// One per position, or empty if the faces have no normals.
+    pub normals: Vec<Vec3>,
+    /// One per position, or empty if the faces have no texture coordinates.
+    pub uvs: Vec<(f64, f64)>,
+    pub triangles: Vec<[u32; 3]>,
+}
+
+/// The subset of an MTL material the renderer understands.
+#[derive(Debug, Clone, PartialEq)]
+pub struct ObjMaterial {
+    pub name: String,
+    /// `Kd`
+    pub diffuse: Color,
+    /// `Ks`
+    pub specular: Color,
+    /// `Ke`
+    pub emission: Color,
+    /// `Ns`, the Phong exponent.
+    pub shininess: f64,
+    /// `Ni`
+    pub ior: f64,
+    /// `d`, or one minus `Tr`.
+    pub dissolve: f64,
+    /// `illum`
+    pub illum: u32,
+    /// `map_Kd`. [`load_obj`] resolves it against the directory of the MTL file, [`read_mtl`]
+    /// leaves it as written.
+    pub diffuse_map: Option<PathBuf>,
+}
+
+impl ObjMaterial {
+    pub fn new(name: &str) -> ObjMaterial {
+        ObjMaterial {
+            name: name.to_string(),
+            diffuse: Color::from(0.8, 0.8, 0.8),
+            specular: Color::new(),
+            emission: Color::new(),
+            shininess: 0f64,
+            ior: 1f64,
+            dissolve: 1f64,
+            illum: 2,
+            diffuse_map: None,
+        }
+    }
+
+    /// Picks the closest renderer material: materials with an emission `Ke` become lights,
+    /// transparent materials (`d` below 1, or the refraction `illum` models) become glass with
+    /// index `Ni`, materials whose specular color outweighs the diffuse one become metal with a
+    /// fuzz derived from `Ns`, and everything else is Lambertian, textured with the `map_Kd`
+    /// image in place of `Kd` if there is one.
+    ///
+    /// Fails if the `map_Kd` image cannot be read.
+    pub fn to_material(&self) -> std::io::Result<Arc<dyn Material>> {
+        let max = |c: Color| c.x().max(*c.y()).max(*c.z());
+        Ok(if max(self.emission) > 0f64 {
+            Arc::new(DiffuseLight::from(self.emission))
+        } else if self.dissolve < 1f64 || matches!(self.illum, 4 | 6 | 7 | 9) {
+            let ior = if self.ior > 1f64 { self.ior } else { 1.5 };
+            Arc::new(Dielectric::from(ior))
+        } else if max(self.specular) > max(self.diffuse) {
+            // Roughness matching a Phong lobe of exponent Ns.
+            let fuzz = (2f64 / (self.shininess + 2f64)).sqrt();
+            Arc::new(Metal::from(self.specular, fuzz))
+        } else if let Some(map) = &self.diffuse_map {
+            let texture = ImageTexture::load(map).map_err(|err| {
+                std::io::Error::new(err.kind(), format!("cannot read {}: {err}", map.display()))
+            })?;
+            Arc::new(Lambertian::from_texture(Arc::new(texture)))
+        } else {
+            Arc::new(Lambertian::from(self.diffuse))
+        })
+    }
+}
+
+/// The contents of an OBJ file.
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct Obj {
+    pub meshes: Vec<ObjMesh>,
+    /// Files named by `mtllib`, relative to the OBJ file.
+    pub mtllibs: Vec<String>,
+    pub materials: Vec<ObjMaterial>,
+}
+
+impl Obj {
+    pub fn material(&self, name: &str) -> Option<&ObjMaterial> {
+        self.materials.iter().find(|m| m.name == name)
+    }
+}
+
+/// Reads an OBJ file and the MTL libraries it references.
+pub fn load_obj<P: AsRef<Path>>(path: P) -> std::io::Result<Obj> {
+    let path = path.as_ref();
+    let file = std::fs::File::open(path)?;
+    let mut obj = read_obj(std::io::BufReader::new(file))?;
+    let dir = path.parent().unwrap_or(Path::new(""));
+    for mtllib in &obj.mtllibs {
+        let mtl_path = dir.join(mtllib);
+        let file = std::fs::File::open(&mtl_path).map_err(|err| {
+            std::io::Error::new(err.kind(), format!("cannot read {mtllib}: {err}"))
+        })?;
+        let mtl_dir = mtl_path.parent().unwrap_or(Path::new(""));
+        for mut material in read_mtl(std::io::BufReader::new(file))? {
+            material.diffuse_map = material.diffuse_map.map(|map| mtl_dir.join(map));
+            obj.materials.push(material);
+        }
+    }
+    Ok(obj)
+}
+
+/// Builds the meshes as faces come in, starting a new one whenever the material changes.
+#[derive(Default)]
+struct MeshBuilder {
+    mesh: ObjMesh,
+    /// Maps (position, uv, normal) indices to the mesh vertex.
+    vertices: HashMap<(usize, Option<usize>, Option<usize>), u32>,
+    has_uvs: bool,
+    has_normals: bool,
+}
+
+impl MeshBuilder {
+    fn finish(self) -> Option<ObjMesh> {
+        let mut mesh = self.mesh;
+        if mesh.triangles.is_empty() {
+            return None;
+        }
+        if !self.has_uvs {
+            mesh.uvs.clear();
+        }
+        if !self.has_normals {
+            mesh.normals.clear();
+        }
+        Some(mesh)
+    }
+}
+
+/// Reads OBJ statements. Materials named by `mtllib` are not loaded; see [`load_obj`].
+pub fn read_obj<R: BufRead>(reader: R) -> std::io::Result<Obj> {
+    let mut positions = Vec::new();
+    let mut uvs = Vec::new();
+    let mut normals = Vec::new();
+    let mut obj = Obj::default();
+    let mut builder = MeshBuilder {
+        has_uvs: true,
+        has_normals: true,
+        ..MeshBuilder::default()
+    };
+
+    for (number, line) in reader.lines().enumerate() {
+        let line = line?;
+        let error = |message: &str| invalid_data(format!("line {}: {message}", number + 1));
+        let line = line.split('#').next().unwrap_or("");
+        let mut words = line.split_whitespace();
+        let Some(keyword) = words.next() else {
+            continue;
+        };
+        let numbers = |words: std::str::SplitWhitespace| -> std::io::Result<Vec<f64>> {
+            words
+                .map(|w| {
+                    w.parse()
+                        .map_err(|_| error(&format!("invalid number '{w}'")))
+                })
+                .collect()
+        };
+
+        match keyword {
+            "v" => match numbers(words)?[..] {
+                [x, y, z, ..] => positions.push(Point3::from(x, y, z)),
+                _ => return Err(error("a vertex needs three coordinates")),
+            },
+            "vt" => match numbers(words)?[..] {
+                [u] => uvs.push((u, 0f64)),
+                [u, v, ..] => uvs.push((u, v)),
+                _ => return Err(error("a texture coordinate needs at least one value")),
+            },
+            "vn" => match numbers(words)?[..] {
+                [x, y, z] => normals.push(Vec3::from(x, y, z)),
+                _ => return Err(error("a normal needs three coordinates")),
+            },
+            "f" => {
+                let mut face = Vec::new();
+                for word in words {
+                    let mut parts = word.split('/');
+                    let mut index = |count: usize| -> std::io::Result<Option<usize>> {
+                        match parts.next() {
+                            None | Some("") => Ok(None),
+                            Some(part) => resolve_index(part, count)
+                                .map(Some)
+                                .ok_or_else(|| error(&format!("invalid index in '{word}'"))),
+                        }
+                    };
+                    let p = index(positions.len())?
+                        .ok_or_else(|| error(&format!("missing vertex index in '{word}'")))?;
+                    let t = index(uvs.len())?;
+                    let n = index(normals.len())?;
+                    builder.has_uvs &= t.is_some();
+                    builder.has_normals &= n.is_some();
+
+                    let next = builder.mesh.positions.len() as u32;
+                    let vertex = *builder.vertices.entry((p, t, n)).or_insert(next);
+                    if vertex == next {
+                        builder.mesh.positions.push(positions[p]);
+                        builder.mesh.uvs.push(t.map_or((0f64, 0f64), |t| uvs[t]));
+                        builder
+                            .mesh
+                            .normals
+                            .push(n.map_or(Vec3::new(), |n| normals[n]));
+                    }
+                    face.push(vertex);
+                }
+                if face.len() < 3 {
+                    return Err(error("a face needs at least three vertices"));
+                }
+                for i in 1..face.len() - 1 {
+                    builder.mesh.triangles.push([face[0], face[i], face[i + 1]]);
+                }
+            }
+            "usemtl" | "o" => {
+                let name = words.collect::<Vec<_>>().join(" ");
+                let mut mesh = ObjMesh {
+                    name: builder.mesh.name.clone(),
+                    material: builder.mesh.material.clone(),
+                    ..ObjMesh::default()
+                };
+                if keyword == "o" {
+                    mesh.name = name;
+                } else {
+                    mesh.material = Some(name);
+                }
+                let previous = std::mem::replace(
+                    &mut builder,
+                    MeshBuilder {
+                        mesh,
+                        has_uvs: true,
+                        has_normals: true,
+                        ..MeshBuilder::default()
+                    },
+                );
+                obj.meshes.extend(previous.finish());
+            }
+            "mtllib" => obj.mtllibs.extend(words.map(String::from)),
+            // Groups, smoothing groups, lines and points do not affect rendering.
+            _ => {}
+        }
+    }
+    obj.meshes.extend(builder.finish());
+    Ok(obj)
+}
+
+/// Turns a 1-based, or negative relative, OBJ index into a 0-based one.
+fn resolve_index(part: &str, count: usize) -> Option<usize> {
+    let index: i64 = part.parse().ok()?;
+    let index = if index < 0 {
+        count as i64 + index
+    } else {
+        index - 1
+    };
+    (0..count as i64).contains(&index).then_some(index as usize)
+}
+
+/// Reads the materials of an MTL file.
+pub fn read_mtl<R: BufRead>(reader: R) -> std::io::Result<Vec<ObjMaterial>> {
+    let mut materials: Vec<ObjMaterial> = Vec::new();
+
+    for (number, line) in reader.lines().enumerate() {
+        let line = line?;
+        let error = |message: &str| invalid_data(format!("line {}: {message}", number + 1));
+        let line = line.split('#').next().unwrap_or("");
+        let mut words = line.split_whitespace();
+        let Some(keyword) = words.next() else {
+            continue;
+        };
+        if keyword == "newmtl" {
+            materials.push(ObjMaterial::new(&words.collect::<Vec<_>>().join(" ")));
+            continue;
+        }
+        let Some(material) = materials.last_mut() else {
+            return Err(error(&format!("'{keyword}' before any 'newmtl'")));
+        };
+        let rest: Vec<&str> = words.collect();
+        let number = || -> std::io::Result<f64> {
+            match rest[..] {
+                [value] => value
+                    .parse()
+                    .map_err(|_| error(&format!("invalid number '{value}'"))),
+                _ => Err(error(&format!("'{keyword}' needs one number"))),
+            }
+        };
+        let color = || -> std::io::Result<Color> {
+            let values: Vec<f64> = rest.iter().filter_map(|w| w.parse().ok()).collect();
+            match values[..] {
+                // A single value is a grey.
+                [v] if rest.len() == 1 => Ok(Color::from(v, v, v)),
+                [r, g, b] if rest.len() == 3 => Ok(Color::from(r, g, b)),
+                _ => Err(error(&format!("'{keyword}' needs an RGB color"))),
+            }
+        };
+
+        match keyword {
+            "Kd" => material.diffuse = color()?,
+            "Ks" => material.specular = color()?,
+            "Ke" => material.emission = color()?,
+            "Ns" => material.shininess = number()?,
+            "Ni" => material.ior = number()?,
+            "d" => material.dissolve = number()?,
+            "Tr" => material.dissolve = 1f64 - number()?,
+            "illum" => material.illum = number()? as u32,
+            // The file name is last, after any options.
+            "map_Kd" => material.diffuse_map = rest.last().map(PathBuf::from),
+            _ => {}
+        }
+    }
+    Ok(materials)
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::hittable::*;
+    use crate::ray::*;
+    use crate::triangle::*;
+
+    #[test]
+    fn reads_obj() {
+        let text = "\
+mtllib scene.mtl
+v 0 0 0
+v 1 0 0
+v 1 1 0
+v 0 1 0
+vt 0 0
+vt 1 0
+vt 1 1
+vt 0 1
+vn 0 0 1
+usemtl red
+f 1/1/1 2/2/1 3/3/1 4/4/1
+o floor
+usemtl white
+f -4 -3 -2   # relative indices
+f 1 3 4
+";
+        let obj = read_obj(text.as_bytes()).unwrap();
+        assert_eq!(vec!["scene.mtl".to_string()], obj.mtllibs);
+        assert_eq!(2, obj.meshes.len());
+
+        let quad = &obj.meshes[0];
+        assert_eq!(Some("red".to_string()), quad.material);
+        assert_eq!(vec![[0, 1, 2], [0, 2, 3]], quad.triangles);
+        assert_eq!(4, quad.positions.len());
+        assert_eq!((1f64, 1f64), quad.uvs[2]);
+        assert_eq!(Vec3::from(0f64, 0f64, 1f64), quad.normals[3]);
+
+        let floor = &obj.meshes[1];
+        assert_eq!("floor", floor.name);
+        assert_eq!(Some("white".to_string()), floor.material);
+        assert_eq!(vec![[0, 1, 2], [0, 2, 3]], floor.triangles);
+        assert_eq!(Point3::from(0f64, 1f64, 0f64), floor.positions[3]);
+        assert!(floor.normals.is_empty() && floor.uvs.is_empty());
+
+        let err = read_obj("v 0 0 0\nf 1 2 3\n".as_bytes()).unwrap_err();
+        assert_eq!("line 2: invalid index in '2'", err.to_string());
+    }
+
+    #[test]
+    fn reads_mtl() {
+        let text = "\
+newmtl light
+Kd 0.65 0.65 0.65
+Ke 15 15 15
+newmtl glass
+Ni 1.5
+d 0.1
+newmtl gold
+Kd 0.1 0.1 0.1
+Ks 1 0.8 0.3
+Ns 98
+map_Kd -s 1 1 1 textures/gold.png
+";
+        let materials = read_mtl(text.as_bytes()).unwrap();
+        assert_eq!(3, materials.len());
+        assert_eq!(Color::from(15f64, 15f64, 15f64), materials[0].emission);
+        assert_eq!(
+            Color::from(15f64, 15f64, 15f64),
+            materials[0]
+                .to_material()
+                .unwrap()
+                .emitted(0f64, 0f64, &Point3::new())
+        );
+        assert_eq!(Color::from(0.65, 0.65, 0.65), materials[0].diffuse);
+        assert_eq!(1.5, materials[1].ior);
+        assert_eq!(0.1, materials[1].dissolve);
+        assert_eq!(98f64, materials[2].shininess);
+        assert_eq!(
+            Some(PathBuf::from("textures/gold.png")),
+            materials[2].diffuse_map
+        );
+        assert!(read_mtl("Kd 1 1 1\n".as_bytes()).is_err());
+    }
+
+    #[test]
+    fn loads_diffuse_maps() {
+        let dir = std::env::temp_dir().join("ray_tracing_obj_test");
+        std::fs::create_dir_all(dir.join("materials")).unwrap();
+        std::fs::write(
+            dir.join("mesh.obj"),
+            "mtllib materials/mesh.mtl\nv 0 0 0\nv 1 0 0\nv 0 1 0\nusemtl wood\nf 1 2 3\n",
+        )
+        .unwrap();
+        std::fs::write(
+            dir.join("materials/mesh.mtl"),
+            "newmtl wood\nmap_Kd wood.ppm\nnewmtl missing\nmap_Kd missing.ppm\n",
+        )
+        .unwrap();
+        // A single red pixel, decoded from sRGB.
+        std::fs::write(dir.join("materials/wood.ppm"), b"P3\n1 1\n255\n255 0 0\n").unwrap();
+        let obj = load_obj(dir.join("mesh.obj")).unwrap();
+        let wood = obj.material("wood").unwrap();
+        assert_eq!(
+            Some(dir.join("materials").join("wood.ppm")),
+            wood.diffuse_map
+        );
+        assert!(obj.material("missing").unwrap().to_material().is_err());
+
+        let mesh = TriangleMesh::from_obj(&obj.meshes[0], wood.to_material().unwrap());
+        let r = Ray::from(
+            Point3::from(0.25, 0.25, 1f64),
+            Vec3::from(0f64, 0f64, -1f64),
+        );
+        let rec = mesh.hit(&r, 0.001, f64::INFINITY).unwrap();
+        let (attenuation, _) = rec.material.scatter(&r, &rec).unwrap();
+        assert_eq!(Color::from(1f64, 0f64, 0f64), attenuation);
+    }
+}
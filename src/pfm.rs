@@ -0,0 +1,138 @@
+use std::io::{Read, Write};
+
+use crate::image::*;
+use crate::vec3::*;
+
+/// Writes `image` as a color PFM: unclamped linear 32 bit floats, little endian, with the rows
+/// stored from the bottom up.
+pub fn write_pfm<W: Write>(mut out: W, image: &Image) -> std::io::Result<()> {
+    // A negative scale marks little endian data.
+    write!(out, "PF\n{} {}\n-1.0\n", image.width(), image.height())?;
+    let mut row_bytes = Vec::with_capacity(image.width() as usize * 12);
+    for row in image.rows().rev() {
+        row_bytes.clear();
+        for pixel_color in row {
+            for c in [pixel_color.x(), pixel_color.y(), pixel_color.z()] {
+                row_bytes.extend((*c as f32).to_le_bytes());
+            }
+        }
+        out.write_all(&row_bytes)?;
+    }
+    Ok(())
+}
+
+/// Reads a color (`PF`) or grayscale (`Pf`) PFM of either endianness.
+pub fn read_pfm<R: Read>(mut input: R) -> std::io::Result<Image> {
+    let mut data = Vec::new();
+    input.read_to_end(&mut data)?;
+
+    // The header is three whitespace separated fields after the magic, then one whitespace byte.
+    let mut fields = Vec::new();
+    let mut pos = 0;
+    while fields.len() < 4 {
+        while data.get(pos).is_some_and(|b| b.is_ascii_whitespace()) {
+            pos += 1;
+        }
+        let start = pos;
+        while data.get(pos).is_some_and(|b| !b.is_ascii_whitespace()) {
+            pos += 1;
+        }
+        if start == pos {
+            return Err(invalid_data("truncated PFM header"));
+        }
+        fields.push(String::from_utf8_lossy(&data[start..pos]).into_owned());
+    }
+    pos += 1;
+
+    let channels = match fields[0].as_str() {
+        "PF" => 3,
+        "Pf" => 1,
+        _ => return Err(invalid_data("not a PFM file")),
+    };
+    let parse = |field: &str| {
+        field
+            .parse::<u32>()
+            .map_err(|_| invalid_data(format!("invalid PFM size {field:?}")))
+    };
+    let (width, height) = (parse(&fields[1])?, parse(&fields[2])?);
+    if width == 0 || height == 0 {
+        return Err(invalid_data(format!("invalid PFM size {width}x{height}")));
+    }
+    let scale: f32 = fields[3]
+        .parse()
+        .map_err(|_| invalid_data(format!("invalid PFM scale {:?}", fields[3])))?;
+    let little_endian = scale < 0f32;
+
+    let end = (width as usize)
+        .checked_mul(height as usize)
+        .and_then(|count| count.checked_mul(4 * channels))
+        .and_then(|len| len.checked_add(pos))
+        .ok_or_else(|| invalid_data(format!("PFM size {width}x{height} is too large")))?;
+    let samples = data
+        .get(pos..end)
+        .ok_or_else(|| invalid_data("truncated PFM pixel data"))?;
+    let values: Vec<f64> = samples
+        .chunks(4)
+        .map(|b| {
+            let bytes = [b[0], b[1], b[2], b[3]];
+            if little_endian {
+                f32::from_le_bytes(bytes) as f64
+            } else {
+                f32::from_be_bytes(bytes) as f64
+            }
+        })
+        .collect();
+
+    let mut image = Image::new(width, height);
+    for (row, values) in values.chunks(width as usize * channels).enumerate() {
+        // Rows are stored from the bottom up.
+        let y = height - 1 - row as u32;
+        for (x, v) in values.chunks(channels).enumerate() {
+            let color = match v {
+                [r, g, b] => Color::from(*r, *g, *b),
+                _ => Color::from(v[0], v[0], v[0]),
+            };
+            image.set_pixel(x as u32, y, color);
+        }
+    }
+    Ok(image)
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn bottom_up_little_endian() {
+        let mut image = Image::new(1, 2);
+        image.set_pixel(0, 0, Color::from(2.5, 0f64, 0f64));
+        image.set_pixel(0, 1, Color::from(0f64, 0f64, -1f64));
+
+        let mut out = Vec::new();
+        write_pfm(&mut out, &image).unwrap();
+        let header = b"PF\n1 2\n-1.0\n";
+        assert_eq!(header, &out[..header.len()]);
+
+        let floats: Vec<f32> = out[header.len()..]
+            .chunks(4)
+            .map(|b| f32::from_le_bytes([b[0], b[1], b[2], b[3]]))
+            .collect();
+        assert_eq!(vec![0f32, 0f32, -1f32, 2.5, 0f32, 0f32], floats);
+        assert_eq!(image, read_pfm(&out[..]).unwrap());
+    }
+
+    #[test]
+    fn reads_big_endian_grayscale() {
+        let mut file = b"Pf\n2 1\n1.0\n".to_vec();
+        file.extend(0.5f32.to_be_bytes());
+        file.extend(8f32.to_be_bytes());
+        let image = read_pfm(&file[..]).unwrap();
+        assert_eq!(Color::from(0.5, 0.5, 0.5), image.get_pixel(0, 0));
+        assert_eq!(Color::from(8f64, 8f64, 8f64), image.get_pixel(1, 0));
+
+        assert!(read_pfm(&file[..file.len() - 1]).is_err());
+        assert!(read_pfm(&b"P6\n1 1\n1.0\n"[..]).is_err());
+        assert!(read_pfm(&b"PF\n0 1\n-1.0\n"[..]).is_err());
+        assert!(read_pfm(&b"PF\n4294967295 4294967295\n-1.0\n"[..]).is_err());
+    }
+}
@@ -0,0 +1,402 @@
+use std::io::{Read, Write};
+
+use crate::color::*;
+use crate::deflate::*;
+use crate::image::*;
+use crate::vec3::*;
+
+const SIGNATURE: [u8; 8] = [0x89, b'P', b'N', b'G', b'\r', b'\n', 0x1A, b'\n'];
+
+fn write_chunk<W: Write>(out: &mut W, kind: &[u8; 4], data: &[u8]) -> std::io::Result<()> {
+    out.write_all(&(data.len() as u32).to_be_bytes())?;
+    let mut crc_input = Vec::with_capacity(4 + data.len());
+    crc_input.extend_from_slice(kind);
+    crc_input.extend_from_slice(data);
+    out.write_all(&crc_input)?;
+    out.write_all(&crc32(&crc_input).to_be_bytes())
+}
+
+fn paeth(a: u8, b: u8, c: u8) -> u8 {
+    let p = a as i16 + b as i16 - c as i16;
+    let (pa, pb, pc) = (
+        (p - a as i16).abs(),
+        (p - b as i16).abs(),
+        (p - c as i16).abs(),
+    );
+    if pa <= pb && pa <= pc {
+        a
+    } else if pb <= pc {
+        b
+    } else {
+        c
+    }
+}
+
+/// Applies the five PNG filters to `row` and appends the one with the smallest sum of absolute
+/// values, the heuristic recommended by the PNG specification.
+fn filter_row(row: &[u8], prior: &[u8], bpp: usize, out: &mut Vec<u8>) {
+    let mut best: Option<(u64, u8, Vec<u8>)> = None;
+    for filter in 0..5u8 {
+        let filtered: Vec<u8> = (0..row.len())
+            .map(|i| {
+                let a = if i >= bpp { row[i - bpp] } else { 0 };
+                let b = prior[i];
+                let c = if i >= bpp { prior[i - bpp] } else { 0 };
+                let predictor = match filter {
+                    0 => 0,
+                    1 => a,
+                    2 => b,
+                    3 => ((a as u16 + b as u16) / 2) as u8,
+                    _ => paeth(a, b, c),
+                };
+                row[i].wrapping_sub(predictor)
+            })
+            .collect();
+        let cost = filtered
+            .iter()
+            .map(|&x| (x as i8).unsigned_abs() as u64)
+            .sum();
+        if best
+            .as_ref()
+            .is_none_or(|(best_cost, _, _)| cost < *best_cost)
+        {
+            best = Some((cost, filter, filtered));
+        }
+    }
+    let (_, filter, filtered) = best.unwrap();
+    out.push(filter);
+    out.extend(filtered);
+}
+
+/// Writes `image` as an 8 bit RGB PNG after mapping it through `display`.
+pub fn write_png<W: Write>(
+    mut out: W,
+    image: &Image,
+    display: &DisplayTransform,
+) -> std::io::Result<()> {
+    out.write_all(&SIGNATURE)?;
+
+    let mut header = Vec::with_capacity(13);
+    header.extend(image.width().to_be_bytes());
+    header.extend(image.height().to_be_bytes());
+    // 8 bit depth, truecolor, deflate compression, adaptive filtering, no interlace.
+    header.extend([8, 2, 0, 0, 0]);
+    write_chunk(&mut out, b"IHDR", &header)?;
+
+    let stride = image.width() as usize * 3;
+    let mut raw = Vec::with_capacity((stride + 1) * image.height() as usize);
+    let mut prior = vec![0u8; stride];
+    let mut row = Vec::with_capacity(stride);
+    for pixels in image.rows() {
+        row.clear();
+        for &pixel_color in pixels {
+            row.extend(display.to_rgb8(pixel_color));
+        }
+        filter_row(&row, &prior, 3, &mut raw);
+        std::mem::swap(&mut prior, &mut row);
+    }
+    write_chunk(&mut out, b"IDAT", &zlib_compress(&raw))?;
+    write_chunk(&mut out, b"IEND", &[])
+}
+
+/// Origin and spacing of the pixels of each of the seven Adam7 interlacing passes.
+const ADAM7: [(u32, u32, u32, u32); 7] = [
+    (0, 0, 8, 8),
+    (4, 0, 8, 8),
+    (0, 4, 4, 8),
+    (2, 0, 4, 4),
+    (0, 2, 2, 4),
+    (1, 0, 2, 2),
+    (0, 1, 1, 2),
+];
+
+/// Undoes the filters of a `width` by `height` (sub)image starting at `data[*pos]`, and returns
+/// its rows.
+fn unfilter(
+    data: &[u8],
+    pos: &mut usize,
+    height: u32,
+    row_bytes: usize,
+    bpp: usize,
+) -> std::io::Result<Vec<Vec<u8>>> {
+    let mut rows: Vec<Vec<u8>> = Vec::with_capacity(height as usize);
+    let mut prior = vec![0u8; row_bytes];
+    for _ in 0..height {
+        let filter = *data
+            .get(*pos)
+            .ok_or_else(|| invalid_data("truncated PNG image data"))?;
+        let mut row = data
+            .get(*pos + 1..*pos + 1 + row_bytes)
+            .ok_or_else(|| invalid_data("truncated PNG image data"))?
+            .to_vec();
+        *pos += 1 + row_bytes;
+
+        for i in 0..row_bytes {
+            let a = if i >= bpp { row[i - bpp] } else { 0 };
+            let b = prior[i];
+            let c = if i >= bpp { prior[i - bpp] } else { 0 };
+            let predictor = match filter {
+                0 => 0,
+                1 => a,
+                2 => b,
+                3 => ((a as u16 + b as u16) / 2) as u8,
+                4 => paeth(a, b, c),
+                _ => return Err(invalid_data(format!("invalid PNG filter type {filter}"))),
+            };
+            row[i] = row[i].wrapping_add(predictor);
+        }
+        prior.clone_from(&row);
+        rows.push(row);
+    }
+    Ok(rows)
+}
+
+/// Returns sample `index` of a row of `depth` bit samples, packed most significant bit first.
+fn sample(row: &[u8], index: usize, depth: u8) -> u32 {
+    match depth {
+        16 => u16::from_be_bytes([row[2 * index], row[2 * index + 1]]) as u32,
+        8 => row[index] as u32,
+        _ => {
+            let per_byte = 8 / depth as usize;
+            let shift = 8 - depth as usize * (index % per_byte + 1);
+            (row[index / per_byte] >> shift) as u32 & ((1 << depth) - 1)
+        }
+    }
+}
+
+/// Reads a PNG of any color type and bit depth, interlaced or not, decoding its colors to linear
+/// with `transfer`. Alpha, if present, is stored linearly in an extra `A` channel.
+pub fn read_png<R: Read>(mut input: R, transfer: Transfer) -> std::io::Result<Image> {
+    let mut data = Vec::new();
+    input.read_to_end(&mut data)?;
+    if !data.starts_with(&SIGNATURE) {
+        return Err(invalid_data("not a PNG file"));
+    }
+
+    let mut header = None;
+    let mut palette = Vec::new();
+    let mut idat = Vec::new();
+    let mut pos = SIGNATURE.len();
+    loop {
+        let length = data
+            .get(pos..pos + 4)
+            .map(|b| u32::from_be_bytes([b[0], b[1], b[2], b[3]]) as usize)
+            .ok_or_else(|| invalid_data("truncated PNG chunk"))?;
+        let chunk = data
+            .get(pos + 4..pos + 8 + length + 4)
+            .ok_or_else(|| invalid_data("truncated PNG chunk"))?;
+        let (kind, rest) = chunk.split_at(4);
+        let (body, crc) = rest.split_at(length);
+        if crc32(&chunk[..4 + length]).to_be_bytes() != crc {
+            return Err(invalid_data(format!(
+                "PNG chunk {} has a bad CRC",
+                String::from_utf8_lossy(kind)
+            )));
+        }
+        pos += 12 + length;
+
+        match kind {
+            b"IHDR" => header = Some(body.to_vec()),
+            b"PLTE" => palette = body.to_vec(),
+            b"IDAT" => idat.extend_from_slice(body),
+            b"IEND" => break,
+            // Ancillary chunks have a lowercase first letter and can be skipped.
+            _ if kind[0].is_ascii_lowercase() => {}
+            _ => {
+                return Err(invalid_data(format!(
+                    "unsupported critical PNG chunk {}",
+                    String::from_utf8_lossy(kind)
+                )))
+            }
+        }
+    }
+
+    let header = header
+        .filter(|h| h.len() == 13)
+        .ok_or_else(|| invalid_data("missing PNG header"))?;
+    let width = u32::from_be_bytes([header[0], header[1], header[2], header[3]]);
+    let height = u32::from_be_bytes([header[4], header[5], header[6], header[7]]);
+    let (depth, color_type, interlace) = (header[8], header[9], header[12]);
+
+    let channels = match (color_type, depth) {
+        (0, 1 | 2 | 4 | 8 | 16) => 1,
+        (2, 8 | 16) => 3,
+        (3, 1 | 2 | 4 | 8) => 1,
+        (4, 8 | 16) => 2,
+        (6, 8 | 16) => 4,
+        _ => {
+            return Err(invalid_data(format!(
+                "invalid PNG color type {color_type} with bit depth {depth}"
+            )))
+        }
+    };
+    if color_type == 3 && palette.is_empty() {
+        return Err(invalid_data("missing PNG palette"));
+    }
+    if interlace > 1 {
+        return Err(invalid_data(format!(
+            "invalid PNG interlace method {interlace}"
+        )));
+    }
+    // The specification limits both sides to 2^31 - 1.
+    if width == 0 || height == 0 || width > i32::MAX as u32 || height > i32::MAX as u32 {
+        return Err(invalid_data(format!("invalid PNG size {width}x{height}")));
+    }
+
+    let bits_per_pixel = channels * depth as usize;
+    let bpp = bits_per_pixel.div_ceil(8);
+    let max_value = ((1u32 << depth) - 1) as f64;
+    let passes = if interlace == 1 {
+        ADAM7.to_vec()
+    } else {
+        vec![(0, 0, 1, 1)]
+    };
+    // The width, height and bytes per row of a pass.
+    let pass_size = |(x0, y0, dx, dy): (u32, u32, u32, u32)| {
+        let pass_width = width.saturating_sub(x0).div_ceil(dx);
+        let pass_height = height.saturating_sub(y0).div_ceil(dy);
+        let row_bytes = (pass_width as usize * bits_per_pixel).div_ceil(8);
+        (pass_width, pass_height, row_bytes)
+    };
+
+    // The header alone could ask for a huge image, so check it against the data first.
+    let raw = zlib_decompress(&idat)?;
+    let expected_len = passes.iter().try_fold(0usize, |total, &pass| {
+        let (pass_width, pass_height, row_bytes) = pass_size(pass);
+        if pass_width == 0 {
+            return Some(total);
+        }
+        // Each row starts with its filter type.
+        (row_bytes + 1)
+            .checked_mul(pass_height as usize)?
+            .checked_add(total)
+    });
+    if expected_len != Some(raw.len()) {
+        return Err(invalid_data(format!(
+            "PNG image data does not match its size of {width}x{height}"
+        )));
+    }
+
+    let mut image = Image::new(width, height);
+    let alpha = if channels == 2 || channels == 4 {
+        Some(image.add_channel("A"))
+    } else {
+        None
+    };
+
+    let mut pos = 0;
+    for (x0, y0, dx, dy) in passes {
+        let (pass_width, pass_height, row_bytes) = pass_size((x0, y0, dx, dy));
+        if pass_width == 0 || pass_height == 0 {
+            continue;
+        }
+
+        let rows = unfilter(&raw, &mut pos, pass_height, row_bytes, bpp)?;
+        for (py, row) in rows.iter().enumerate() {
+            let y = y0 + py as u32 * dy;
+            for px in 0..pass_width as usize {
+                let x = x0 + px as u32 * dx;
+                let value = |c: usize| sample(row, px * channels + c, depth) as f64 / max_value;
+                let decode = |c: usize| transfer.decode(value(c));
+
+                let color = match color_type {
+                    3 => {
+                        let index = sample(row, px, depth) as usize;
+                        let rgb = palette
+                            .get(3 * index..3 * index + 3)
+                            .ok_or_else(|| invalid_data("PNG palette index out of range"))?;
+                        let decode = |v: u8| transfer.decode(v as f64 / 255f64);
+                        Color::from(decode(rgb[0]), decode(rgb[1]), decode(rgb[2]))
+                    }
+                    0 | 4 => Color::from(decode(0), decode(0), decode(0)),
+                    _ => Color::from(decode(0), decode(1), decode(2)),
+                };
+                image.set_pixel(x, y, color);
+                if let Some(alpha) = alpha {
+                    image.set_channel_value(alpha, x, y, value(channels - 1) as f32);
+                }
+            }
+        }
+    }
+    Ok(image)
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    /// Wraps already filtered scanlines into a PNG file.
+    fn png_file(header: [u8; 13], palette: &[u8], scanlines: &[u8]) -> Vec<u8> {
+        let mut file = Vec::new();
+        file.extend(SIGNATURE);
+        write_chunk(&mut file, b"IHDR", &header).unwrap();
+        if !palette.is_empty() {
+            write_chunk(&mut file, b"PLTE", palette).unwrap();
+        }
+        write_chunk(&mut file, b"tEXt", b"Comment\0ignored").unwrap();
+        write_chunk(&mut file, b"IDAT", &zlib_compress(scanlines)).unwrap();
+        write_chunk(&mut file, b"IEND", &[]).unwrap();
+        file
+    }
+
+    #[test]
+    fn round_trip() {
+        let mut image = Image::new(5, 4);
+        for y in 0..4 {
+            for x in 0..5 {
+                let v = (x * 4 + y) as f64 / 255f64;
+                image.set_pixel(x, y, Color::from(v, 1f64 - v, (x * y) as f64 / 255f64));
+            }
+        }
+
+        let mut file = Vec::new();
+        let display = DisplayTransform::from(ToneMap::Clamp, Transfer::Linear);
+        write_png(&mut file, &image, &display).unwrap();
+        let loaded = read_png(&file[..], Transfer::Linear).unwrap();
+        for (a, b) in image.pixels().iter().zip(loaded.pixels()) {
+            assert!((*a - *b).length() < 1e-9);
+        }
+
+        let last = file.len() - 5;
+        file[last] ^= 1;
+        assert!(read_png(&file[..], Transfer::Linear).is_err());
+    }
+
+    #[test]
+    fn palette_gray_alpha_and_interlacing() {
+        // 3x1, 2 bit palette indices 2, 0, 1.
+        let mut header = [0, 0, 0, 3, 0, 0, 0, 1, 2, 3, 0, 0, 0];
+        let palette = [255, 0, 0, 0, 255, 0, 0, 0, 255];
+        let file = png_file(header, &palette, &[0, 0b10_00_01_00]);
+        let image = read_png(&file[..], Transfer::Linear).unwrap();
+        assert_eq!(Color::from(0f64, 0f64, 1f64), image.get_pixel(0, 0));
+        assert_eq!(Color::from(1f64, 0f64, 0f64), image.get_pixel(1, 0));
+        assert_eq!(Color::from(0f64, 1f64, 0f64), image.get_pixel(2, 0));
+
+        // 1x2, 16 bit gray with alpha, the second row using the Up filter.
+        header = [0, 0, 0, 1, 0, 0, 0, 2, 16, 4, 0, 0, 0];
+        let rows = [0, 0xFF, 0xFF, 0x80, 0x00, 2, 0x01, 0x00, 0x80, 0x00];
+        let image = read_png(&png_file(header, &[], &rows)[..], Transfer::Linear).unwrap();
+        assert_eq!(Color::from(1f64, 1f64, 1f64), image.get_pixel(0, 0));
+        assert_eq!(255f64 / 65535f64, *image.get_pixel(0, 1).x());
+        assert_eq!(32768f32 / 65535f32, image.channel("A").unwrap().data[0]);
+        assert_eq!(0f32, image.channel("A").unwrap().data[1]);
+
+        // 3x3, 8 bit gray, Adam7 interlaced: passes 2 and 3 are empty, 6 has two rows.
+        header = [0, 0, 0, 3, 0, 0, 0, 3, 8, 0, 0, 0, 1];
+        let rows = [0, 10, 0, 20, 0, 30, 40, 0, 50, 0, 60, 0, 70, 80, 90];
+        let image = read_png(&png_file(header, &[], &rows)[..], Transfer::Linear).unwrap();
+        let gray: Vec<u8> = image
+            .pixels()
+            .iter()
+            .map(|c| (*c.x() * 255f64).round() as u8)
+            .collect();
+        assert_eq!(vec![10, 50, 20, 70, 80, 90, 30, 60, 40], gray);
+
+        // A huge or empty header is rejected before allocating the image.
+        header = [0, 0, 0xFF, 0xFF, 0, 0, 0xFF, 0xFF, 8, 0, 0, 0, 0];
+        assert!(read_png(&png_file(header, &[], &rows)[..], Transfer::Linear).is_err());
+        header = [0, 0, 0, 0, 0, 0, 0, 1, 8, 0, 0, 0, 0];
+        assert!(read_png(&png_file(header, &[], &[])[..], Transfer::Linear).is_err());
+    }
+}
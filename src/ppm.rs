@@ -0,0 +1,197 @@
+use std::io::{Read, Write};
+
+use crate::color::*;
+use crate::image::*;
+use crate::vec3::*;
+
+/// Writes `image` as an ASCII (P3) PPM after mapping it through `display`.
+pub fn write_ppm_ascii<W: Write>(
+    mut out: W,
+    image: &Image,
+    display: &DisplayTransform,
+) -> std::io::Result<()> {
+    writeln!(out, "P3\n{} {}\n255", image.width(), image.height())?;
+    for &pixel_color in image.pixels() {
+        write_color(&mut out, pixel_color, display)?;
+    }
+    Ok(())
+}
+
+/// Writes `image` as a binary (P6) PPM after mapping it through `display`.
+pub fn write_ppm_binary<W: Write>(
+    mut out: W,
+    image: &Image,
+    display: &DisplayTransform,
+) -> std::io::Result<()> {
+    write!(out, "P6\n{} {}\n255\n", image.width(), image.height())?;
+    let bytes: Vec<u8> = image
+        .pixels()
+        .iter()
+        .flat_map(|&pixel_color| display.to_rgb8(pixel_color))
+        .collect();
+    out.write_all(&bytes)
+}
+
+/// Splits a netpbm header into whitespace separated tokens, skipping `#` comments.
+struct HeaderTokens<'a> {
+    data: &'a [u8],
+    pos: usize,
+}
+
+impl<'a> HeaderTokens<'a> {
+    fn next_token(&mut self) -> std::io::Result<&'a [u8]> {
+        loop {
+            match self.data.get(self.pos) {
+                Some(b'#') => {
+                    while self.data.get(self.pos).is_some_and(|&b| b != b'\n') {
+                        self.pos += 1;
+                    }
+                }
+                Some(b) if b.is_ascii_whitespace() => self.pos += 1,
+                Some(_) => break,
+                None => return Err(invalid_data("unexpected end of PPM file")),
+            }
+        }
+        let start = self.pos;
+        while self
+            .data
+            .get(self.pos)
+            .is_some_and(|b| !b.is_ascii_whitespace())
+        {
+            self.pos += 1;
+        }
+        Ok(&self.data[start..self.pos])
+    }
+
+    fn next_number(&mut self) -> std::io::Result<u32> {
+        let token = self.next_token()?;
+        std::str::from_utf8(token)
+            .ok()
+            .and_then(|token| token.parse().ok())
+            .ok_or_else(|| {
+                invalid_data(format!(
+                    "expected a number in PPM file, found {:?}",
+                    String::from_utf8_lossy(token)
+                ))
+            })
+    }
+}
+
+/// Reads an ASCII (P3) or binary (P6) PPM, decoding its values to linear with `transfer`.
+pub fn read_ppm<R: Read>(mut input: R, transfer: Transfer) -> std::io::Result<Image> {
+    let mut data = Vec::new();
+    input.read_to_end(&mut data)?;
+
+    let mut tokens = HeaderTokens {
+        data: &data,
+        pos: 0,
+    };
+    let binary = match tokens.next_token()? {
+        b"P3" => false,
+        b"P6" => true,
+        _ => return Err(invalid_data("not a P3 or P6 PPM file")),
+    };
+    let width = tokens.next_number()?;
+    let height = tokens.next_number()?;
+    if width == 0 || height == 0 {
+        return Err(invalid_data(format!("invalid PPM size {width}x{height}")));
+    }
+    let max_value = tokens.next_number()?;
+    if max_value == 0 || max_value > 65535 {
+        return Err(invalid_data(format!(
+            "invalid PPM maximum value {max_value}"
+        )));
+    }
+
+    // Check the size against the data before allocating, as the header alone could be huge.
+    let size = if max_value < 256 { 1 } else { 2 };
+    let count = (width as usize)
+        .checked_mul(height as usize)
+        .and_then(|pixels| pixels.checked_mul(3))
+        .filter(|&count| {
+            if binary {
+                count.checked_mul(size).is_some_and(|len| len <= data.len())
+            } else {
+                // Every ASCII sample takes at least a digit and a separator.
+                count <= data.len() / 2
+            }
+        })
+        .ok_or_else(|| invalid_data("truncated PPM pixel data"))?;
+    let mut values = Vec::with_capacity(count);
+    if binary {
+        // A single whitespace byte separates the header from the samples.
+        let start = tokens.pos + 1;
+        let samples = data
+            .get(start..start + count * size)
+            .ok_or_else(|| invalid_data("truncated PPM pixel data"))?;
+        if size == 1 {
+            values.extend(samples.iter().map(|&v| v as u32));
+        } else {
+            values.extend(
+                samples
+                    .chunks(2)
+                    .map(|v| u16::from_be_bytes([v[0], v[1]]) as u32),
+            );
+        }
+    } else {
+        for _ in 0..count {
+            values.push(tokens.next_number()?);
+        }
+    }
+
+    let decode = |v: u32| transfer.decode(v.min(max_value) as f64 / max_value as f64);
+    let pixels = values
+        .chunks(3)
+        .map(|rgb| Color::from(decode(rgb[0]), decode(rgb[1]), decode(rgb[2])))
+        .collect();
+    Ok(Image::from(width, height, pixels))
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn ascii_ppm() {
+        let mut image = Image::new(2, 1);
+        image.set_pixel(1, 0, Color::from(1f64, 0.5, 0f64));
+        assert_eq!(Color::from(1f64, 0.5, 0f64), image.get_pixel(1, 0));
+
+        let mut out = Vec::new();
+        let display = DisplayTransform::from(ToneMap::Clamp, Transfer::Linear);
+        write_ppm_ascii(&mut out, &image, &display).unwrap();
+        assert_eq!(
+            "P3\n2 1\n255\n0 0 0\n255 128 0\n",
+            String::from_utf8(out).unwrap()
+        );
+    }
+
+    #[test]
+    fn reads_p3_and_p6() {
+        let p3 = b"P3\n# made by hand\n2 1 # size\n15\n0 15 3\n15 0 0\n";
+        let image = read_ppm(&p3[..], Transfer::Linear).unwrap();
+        assert_eq!(Color::from(0f64, 1f64, 0.2), image.get_pixel(0, 0));
+        assert_eq!(Color::from(1f64, 0f64, 0f64), image.get_pixel(1, 0));
+
+        let mut out = Vec::new();
+        let display = DisplayTransform::from(ToneMap::Clamp, Transfer::Linear);
+        write_ppm_binary(&mut out, &image, &display).unwrap();
+        let p6 = read_ppm(&out[..], Transfer::Linear).unwrap();
+        assert_eq!(Color::from(0f64, 1f64, 0.2), p6.get_pixel(0, 0));
+        assert_eq!(image.pixels()[1], p6.pixels()[1]);
+
+        let p6_16bit = b"P6 1 1 65535\n\xFF\xFF\x80\x00\x00\x00";
+        let image = read_ppm(&p6_16bit[..], Transfer::Linear).unwrap();
+        assert_eq!(
+            Color::from(1f64, 32768f64 / 65535f64, 0f64),
+            image.get_pixel(0, 0)
+        );
+
+        assert!(read_ppm(&b"P6 2 2 255\n\0\0\0"[..], Transfer::Linear).is_err());
+        assert!(read_ppm(&b"P3 1 1 255 0 x 0"[..], Transfer::Linear).is_err());
+        assert!(read_ppm(&b"P5 1 1 255 0"[..], Transfer::Linear).is_err());
+        assert!(read_ppm(&b"P6 0 1 255\n"[..], Transfer::Linear).is_err());
+        assert!(read_ppm(&b"P6 4294967295 4294967295 255"[..], Transfer::Linear).is_err());
+        assert!(read_ppm(&b"P3 65536 65536 255 0 0 0"[..], Transfer::Linear).is_err());
+    }
+}
@@ -0,0 +1,180 @@
+use std::sync::Arc;
+
+use crate::aabb::*;
+use crate::hittable::*;
+use crate::material::*;
+use crate::ray::*;
+use crate::vec3::*;
+
+/// Parallelogram with a corner at `q` and sides `u` and `v`, facing along `u x v`. `(u, v)` are
+/// the coordinates of the hit point along the sides, from 0 to 1.
+pub struct Quad {
+    q: Point3,
+    u: Vec3,
+    v: Vec3,
+    normal: Vec3,
+    /// Plane offset, `dot(normal, q)`.
+    d: f64,
+    /// `n / dot(n, n)` with `n = u x v`, to recover the coordinates of a point along the sides.
+    w: Vec3,
+    material: Arc<dyn Material>,
+}
+
+impl Quad {
+    pub fn from(q: Point3, u: Vec3, v: Vec3, material: Arc<dyn Material>) -> Quad {
+        let n = Vec3::cross(&u, &v);
+        let normal = n.unit_vector();
+        Quad {
+            q,
+            u,
+            v,
+            normal,
+            d: Vec3::dot(&normal, &q),
+            w: n / Vec3::dot(&n, &n),
+            material,
+        }
+    }
+}
+
+impl Hittable for Quad {
+    fn hit(&self, r: &Ray, t_min: f64, t_max: f64) -> Option<HitRecord<'_>> {
+        let denom = Vec3::dot(&self.normal, &r.direction());
+        // No hit if the ray is parallel to the plane.
+        if denom.abs() < 1e-8 {
+            return None;
+        }
+        let t = (self.d - Vec3::dot(&self.normal, &r.origin())) / denom;
+        if t <= t_min || t_max <= t {
+            return None;
+        }
+
+        let planar_hitpt_vector = r.at(t) - self.q;
+        let alpha = Vec3::dot(&self.w, &Vec3::cross(&planar_hitpt_vector, &self.v));
+        let beta = Vec3::dot(&self.w, &Vec3::cross(&self.u, &planar_hitpt_vector));
+        if !(0f64..=1f64).contains(&alpha) || !(0f64..=1f64).contains(&beta) {
+            return None;
+        }
+        Some(HitRecord::from(
+            r,
+            t,
+            self.normal,
+            alpha,
+            beta,
+            self.material.as_ref(),
+        ))
+    }
+
+    fn bounding_box(&self) -> Option<Aabb> {
+        let corners = [
+            self.q,
+            self.q + self.u,
+            self.q + self.v,
+            self.q + self.u + self.v,
+        ];
+        Some(Aabb::from_points(corners).pad(1e-4))
+    }
+}
+
+/// Infinite plane through `point`. `(u, v)` are the coordinates of the hit point in an
+/// orthonormal basis of the plane centered on `point`, so they are not limited to `[0, 1]`.
+pub struct Plane {
+    point: Point3,
+    normal: Vec3,
+    tangent: Vec3,
+    bitangent: Vec3,
+    material: Arc<dyn Material>,
+}
+
+impl Plane {
+    pub fn from(point: Point3, normal: Vec3, material: Arc<dyn Material>) -> Plane {
+        let normal = normal.unit_vector();
+        let (tangent, bitangent) = normal.orthonormal_basis();
+        Plane {
+            point,
+            normal,
+            tangent,
+            bitangent,
+            material,
+        }
+    }
+}
+
+impl Hittable for Plane {
+    fn hit(&self, r: &Ray, t_min: f64, t_max: f64) -> Option<HitRecord<'_>> {
+        let denom = Vec3::dot(&self.normal, &r.direction());
+        if denom.abs() < 1e-8 {
+            return None;
+        }
+        let t = Vec3::dot(&self.normal, &(self.point - r.origin())) / denom;
+        if t <= t_min || t_max <= t {
+            return None;
+        }
+        let offset = r.at(t) - self.point;
+        Some(HitRecord::from(
+            r,
+            t,
+            self.normal,
+            Vec3::dot(&offset, &self.tangent),
+            Vec3::dot(&offset, &self.bitangent),
+            self.material.as_ref(),
+        ))
+    }
+
+    /// Planes are unbounded.
+    fn bounding_box(&self) -> Option<Aabb> {
+        None
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn quad_intersection() {
+        let material = Arc::new(Lambertian::from(Color::from(0.5, 0.5, 0.5)));
+        // A slanted parallelogram in the z = -2 plane.
+        let quad = Quad::from(
+            Point3::from(-1f64, -1f64, -2f64),
+            Vec3::from(2f64, 0f64, 0f64),
+            Vec3::from(1f64, 2f64, 0f64),
+            material,
+        );
+        let r = Ray::from(Point3::new(), Vec3::from(0f64, 0f64, -1f64));
+        let rec = quad.hit(&r, 0.001, f64::INFINITY).unwrap();
+        assert_eq!(2f64, rec.t);
+        assert_eq!((0.25, 0.5), (rec.u, rec.v));
+        assert_eq!(Vec3::from(0f64, 0f64, 1f64), rec.normal);
+        assert!(rec.front_face);
+
+        let outside = Ray::from(
+            Point3::from(-1.5, 0f64, 0f64),
+            Vec3::from(0f64, 0f64, -1f64),
+        );
+        assert!(quad.hit(&outside, 0.001, f64::INFINITY).is_none());
+        assert_eq!(
+            Point3::from(-1f64, -1f64, -2f64 - 0.5e-4),
+            quad.bounding_box().unwrap().min()
+        );
+    }
+
+    #[test]
+    fn plane_intersection() {
+        let material = Arc::new(Lambertian::from(Color::from(0.5, 0.5, 0.5)));
+        let plane = Plane::from(
+            Point3::from(0f64, -1f64, 0f64),
+            Vec3::from(0f64, 2f64, 0f64),
+            material,
+        );
+        assert!(plane.bounding_box().is_none());
+
+        let r = Ray::from(Point3::new(), Vec3::from(100f64, -1f64, 0f64));
+        let rec = plane.hit(&r, 0.001, f64::INFINITY).unwrap();
+        assert_eq!(1f64, rec.t);
+        assert_eq!(Vec3::from(0f64, 1f64, 0f64), rec.normal);
+        assert!((rec.u * rec.u + rec.v * rec.v - 100f64 * 100f64).abs() < 1e-9);
+
+        let away = Ray::from(Point3::new(), Vec3::from(0f64, 1f64, 0f64));
+        assert!(plane.hit(&away, 0.001, f64::INFINITY).is_none());
+    }
+}
@@ -2,13 +2,20 @@ use crate::vec3::*;
 pub struct Ray {
     orig: Point3,
     dir: Vec3,
+    time: f64,
 }
 
 impl Ray {
     pub fn from(origin: Point3, direction: Vec3) -> Ray {
+        Ray::from_time(origin, direction, 0f64)
+    }
+
+    /// A ray cast at `time`, which moving objects use to decide where they are.
+    pub fn from_time(origin: Point3, direction: Vec3, time: f64) -> Ray {
         Ray {
             orig: origin,
             dir: direction,
+            time,
         }
     }
 
@@ -20,6 +27,10 @@ impl Ray {
         self.dir
     }
 
+    pub fn time(&self) -> f64 {
+        self.time
+    }
+
     pub fn at(&self, t: f64) -> Point3 {
         self.orig + t * self.dir
     }
@@ -0,0 +1,217 @@
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::sync::Mutex;
+use std::thread;
+
+use crate::camera::*;
+use crate::hittable::*;
+use crate::image::*;
+use crate::integrator::*;
+use crate::rng;
+use crate::sampler::*;
+use crate::vec3::*;
+
+pub struct RenderSettings {
+    pub width: u32,
+    pub height: u32,
+    pub samples_per_pixel: u32,
+    /// Edge length in pixels of the square tiles handed out to the worker threads.
+    pub tile_size: u32,
+    /// Number of worker threads, or 0 to use every available core.
+    pub threads: usize,
+    pub seed: u64,
+    /// Also store the distance (`Z`) and normal (`N.X`, `N.Y`, `N.Z`) seen through the center
+    /// of each pixel as extra image channels, from the center of the lens at shutter open.
+    pub aovs: bool,
+    /// Called with the number of tiles left each time one is finished, counting down to 0.
+    pub progress: Option<fn(usize)>,
+}
+
+impl RenderSettings {
+    fn thread_count(&self) -> usize {
+        match self.threads {
+            0 => thread::available_parallelism().map_or(1, |n| n.get()),
+            n => n,
+        }
+    }
+}
+
+struct Tile {
+    x0: u32,
+    y0: u32,
+    x1: u32,
+    y1: u32,
+}
+
+/// Renders the image in parallel, each pixel holding the average of its samples.
+///
+/// Each pixel reseeds the generator of the thread rendering it from the settings' seed, so the
+/// output only depends on the seed and not on the thread count or tile scheduling.
+pub fn render(
+    settings: &RenderSettings,
+    camera: &Camera,
+    world: &dyn Hittable,
+    integrator: &PathTracer,
+    sampler: &dyn Sampler,
+) -> Image {
+    let (width, height) = (settings.width, settings.height);
+    let tile_size = settings.tile_size.max(1);
+
+    let mut tiles = Vec::new();
+    for y0 in (0..height).step_by(tile_size as usize) {
+        for x0 in (0..width).step_by(tile_size as usize) {
+            tiles.push(Tile {
+                x0,
+                y0,
+                x1: (x0 + tile_size).min(width),
+                y1: (y0 + tile_size).min(height),
+            });
+        }
+    }
+
+    let mut image = Image::new(width, height);
+    let aov_channels = if settings.aovs {
+        ["Z", "N.X", "N.Y", "N.Z"].map(|name| image.add_channel(name))
+    } else {
+        [0; 4]
+    };
+    let framebuffer = Mutex::new(image);
+    let next_tile = AtomicUsize::new(0);
+    let finished_tiles = AtomicUsize::new(0);
+
+    thread::scope(|scope| {
+        for _ in 0..settings.thread_count().min(tiles.len()) {
+            scope.spawn(|| loop {
+                let index = next_tile.fetch_add(1, Ordering::Relaxed);
+                let Some(tile) = tiles.get(index) else {
+                    break;
+                };
+
+                let tile_len = ((tile.x1 - tile.x0) * (tile.y1 - tile.y0)) as usize;
+                let mut pixels = Vec::with_capacity(tile_len);
+                let mut aovs = Vec::with_capacity(if settings.aovs { tile_len } else { 0 });
+                for y in tile.y0..tile.y1 {
+                    // The camera's v axis points up while rows are stored from the top.
+                    let j = height - 1 - y;
+                    for i in tile.x0..tile.x1 {
+                        rng::seed(pixel_seed(settings.seed, i, j));
+                        let mut pixel_color = Color::new();
+                        for s in 0..settings.samples_per_pixel {
+                            let (du, dv) = sampler.sample(i, j, s, settings.samples_per_pixel);
+                            let u = (i as f64 + du) / (width as f64 - 1f64);
+                            let v = (j as f64 + dv) / (height as f64 - 1f64);
+
+                            let r = camera.get_ray(u, v);
+                            pixel_color += integrator.ray_color(&r, world);
+                        }
+                        pixels.push(pixel_color / settings.samples_per_pixel.max(1) as f64);
+
+                        if settings.aovs {
+                            let u = (i as f64 + 0.5) / (width as f64 - 1f64);
+                            let v = (j as f64 + 0.5) / (height as f64 - 1f64);
+                            let r = camera.get_pinhole_ray(u, v);
+                            aovs.push(match world.hit(&r, 0.001, f64::INFINITY) {
+                                Some(rec) => [
+                                    (rec.t * r.direction().length()) as f32,
+                                    *rec.normal.x() as f32,
+                                    *rec.normal.y() as f32,
+                                    *rec.normal.z() as f32,
+                                ],
+                                None => [f32::INFINITY, 0f32, 0f32, 0f32],
+                            });
+                        }
+                    }
+                }
+
+                let mut framebuffer = framebuffer.lock().unwrap();
+                let mut pixels = pixels.into_iter();
+                let mut aovs = aovs.into_iter();
+                for y in tile.y0..tile.y1 {
+                    for x in tile.x0..tile.x1 {
+                        framebuffer.set_pixel(x, y, pixels.next().unwrap());
+                        if let Some(values) = aovs.next() {
+                            for (&channel, value) in aov_channels.iter().zip(values) {
+                                framebuffer.set_channel_value(channel, x, y, value);
+                            }
+                        }
+                    }
+                }
+                // Reporting under the lock keeps the counts in order across threads.
+                let finished = finished_tiles.fetch_add(1, Ordering::Relaxed) + 1;
+                if let Some(progress) = settings.progress {
+                    progress(tiles.len() - finished);
+                }
+                drop(framebuffer);
+            });
+        }
+    });
+
+    framebuffer.into_inner().unwrap()
+}
+
+#[cfg(test)]
+mod test {
+    use std::sync::Arc;
+
+    use super::*;
+    use crate::hittable_list::HittableList;
+    use crate::material::*;
+    use crate::sphere::Sphere;
+
+    #[test]
+    fn deterministic_across_thread_counts() {
+        let mut world = HittableList::new();
+        let ground = Arc::new(Lambertian::from(Color::from(0.5, 0.5, 0.5)));
+        let glass = Arc::new(Dielectric::from(1.5));
+        world.add(Arc::new(Sphere::from(
+            Point3::from(0f64, -100.5, -1f64),
+            100f64,
+            ground,
+        )));
+        world.add(Arc::new(Sphere::from(
+            Point3::from(0f64, 0f64, -1f64),
+            0.5,
+            glass,
+        )));
+
+        let camera = Camera::from(
+            Point3::new(),
+            Point3::from(0f64, 0f64, -1f64),
+            Vec3::from(0f64, 1f64, 0f64),
+            90f64,
+            1.5,
+            0.1,
+            1f64,
+        );
+        let integrator = PathTracer::from(8, 2);
+        let mut settings = RenderSettings {
+            width: 24,
+            height: 16,
+            samples_per_pixel: 4,
+            tile_size: 5,
+            threads: 1,
+            seed: 42,
+            aovs: true,
+            progress: None,
+        };
+
+        let single = render(&settings, &camera, &world, &integrator, &UniformSampler);
+        settings.threads = 4;
+        let multi = render(&settings, &camera, &world, &integrator, &UniformSampler);
+        assert_eq!((24, 16), (single.width(), single.height()));
+        assert_eq!(4, single.channels().len());
+        assert_eq!(f32::INFINITY, single.channel("Z").unwrap().data[0]);
+        assert!(single == multi);
+
+        settings.seed = 43;
+        assert!(single != render(&settings, &camera, &world, &integrator, &UniformSampler));
+
+        // Each of the 5x4 tiles is reported once, counting down.
+        static REMAINING: Mutex<Vec<usize>> = Mutex::new(Vec::new());
+        settings.progress = Some(|remaining| REMAINING.lock().unwrap().push(remaining));
+        render(&settings, &camera, &world, &integrator, &UniformSampler);
+        assert_eq!(
+            (0..20).rev().collect::<Vec<_>>(),
+            *REMAINING.lock().unwrap()
+        );
+    }
+}
@@ -0,0 +1,51 @@
+use std::cell::Cell;
+
+/// SplitMix64: a tiny, fast generator that is good enough for Monte Carlo sampling.
+#[derive(Debug, Clone, Copy)]
+pub struct Rng {
+    state: u64,
+}
+
+impl Rng {
+    pub fn from(seed: u64) -> Rng {
+        Rng { state: seed }
+    }
+
+    pub fn next_u64(&mut self) -> u64 {
+        self.state = self.state.wrapping_add(0x9E37_79B9_7F4A_7C15);
+        let mut z = self.state;
+        z = (z ^ (z >> 30)).wrapping_mul(0xBF58_476D_1CE4_E5B9);
+        z = (z ^ (z >> 27)).wrapping_mul(0x94D0_49BB_1331_11EB);
+        z ^ (z >> 31)
+    }
+
+    /// Returns a uniformly distributed double in `[0, 1)`.
+    pub fn next_double(&mut self) -> f64 {
+        // The top 53 bits fill the mantissa exactly.
+        (self.next_u64() >> 11) as f64 * (1f64 / (1u64 << 53) as f64)
+    }
+}
+
+thread_local! {
+    static THREAD_RNG: Cell<Rng> = const { Cell::new(Rng { state: 0 }) };
+}
+
+/// Reseeds the generator of the calling thread.
+pub fn seed(seed: u64) {
+    THREAD_RNG.with(|rng| rng.set(Rng::from(seed)));
+}
+
+/// Returns a uniformly distributed double in `[0, 1)` from the calling thread's generator.
+pub fn random_double() -> f64 {
+    THREAD_RNG.with(|cell| {
+        let mut rng = cell.get();
+        let value = rng.next_double();
+        cell.set(rng);
+        value
+    })
+}
+
+/// Returns a uniformly distributed double in `[min, max)`.
+pub fn random_double_range(min: f64, max: f64) -> f64 {
+    min + (max - min) * random_double()
+}
@@ -0,0 +1,204 @@
+use crate::rng::*;
+
+/// Chooses where inside a pixel each of its samples is taken.
+pub trait Sampler: Send + Sync {
+    /// Returns the offset in `[0, 1)^2` of sample `index` out of `count` for pixel `(i, j)`.
+    fn sample(&self, i: u32, j: u32, index: u32, count: u32) -> (f64, f64);
+}
+
+/// Independent uniformly distributed samples.
+pub struct UniformSampler;
+
+impl Sampler for UniformSampler {
+    fn sample(&self, _i: u32, _j: u32, _index: u32, _count: u32) -> (f64, f64) {
+        (random_double(), random_double())
+    }
+}
+
+/// Jittered samples, one per cell of the largest square grid that fits `count`.
+///
+/// Samples left over once the grid is full are uniformly distributed.
+pub struct StratifiedSampler;
+
+impl Sampler for StratifiedSampler {
+    fn sample(&self, _i: u32, _j: u32, index: u32, count: u32) -> (f64, f64) {
+        let n = (count as f64).sqrt() as u32;
+        if index >= n * n {
+            return (random_double(), random_double());
+        }
+        let (x, y) = (index % n, index / n);
+        (
+            (x as f64 + random_double()) / n as f64,
+            (y as f64 + random_double()) / n as f64,
+        )
+    }
+}
+
+/// The 2D Halton sequence in bases 2 and 3, with a per pixel Cranley-Patterson rotation so
+/// neighbouring pixels do not share the same pattern.
+pub struct HaltonSampler {
+    seed: u64,
+}
+
+impl HaltonSampler {
+    pub fn from(seed: u64) -> HaltonSampler {
+        HaltonSampler { seed }
+    }
+
+    /// Mirrors the base `base` digits of `index` around the radix point.
+    pub fn radical_inverse(base: u32, mut index: u32) -> f64 {
+        let inv_base = 1f64 / base as f64;
+        let mut inv_base_n = 1f64;
+        let mut reversed = 0f64;
+        while index > 0 {
+            inv_base_n *= inv_base;
+            reversed += (index % base) as f64 * inv_base_n;
+            index /= base;
+        }
+        reversed
+    }
+}
+
+impl Sampler for HaltonSampler {
+    fn sample(&self, i: u32, j: u32, index: u32, _count: u32) -> (f64, f64) {
+        let mut rng = Rng::from(pixel_seed(self.seed, i, j));
+        let (du, dv) = (rng.next_double(), rng.next_double());
+        (
+            (HaltonSampler::radical_inverse(2, index) + du).fract(),
+            (HaltonSampler::radical_inverse(3, index) + dv).fract(),
+        )
+    }
+}
+
+/// The first two dimensions of the Sobol sequence, a (0, 2)-sequence, with per pixel random
+/// digit scrambling which keeps every power of two prefix stratified.
+pub struct SobolSampler {
+    seed: u64,
+}
+
+impl SobolSampler {
+    pub fn from(seed: u64) -> SobolSampler {
+        SobolSampler { seed }
+    }
+
+    /// Returns the unscrambled sample `index` as 32 bit fixed point numbers.
+    pub fn sobol_2d(index: u32) -> (u32, u32) {
+        let mut n = index;
+        let mut v = 1u32 << 31;
+        let mut y = 0u32;
+        while n != 0 {
+            if n & 1 != 0 {
+                y ^= v;
+            }
+            n >>= 1;
+            v ^= v >> 1;
+        }
+        (index.reverse_bits(), y)
+    }
+}
+
+impl Sampler for SobolSampler {
+    fn sample(&self, i: u32, j: u32, index: u32, _count: u32) -> (f64, f64) {
+        let scramble = Rng::from(pixel_seed(self.seed, i, j)).next_u64();
+        let (x, y) = SobolSampler::sobol_2d(index);
+        (
+            to_unit(x ^ scramble as u32),
+            to_unit(y ^ (scramble >> 32) as u32),
+        )
+    }
+}
+
+/// Names the available samplers, so they can be picked at run time.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum SamplerKind {
+    Uniform,
+    Stratified,
+    Halton,
+    Sobol,
+}
+
+impl SamplerKind {
+    pub const NAMES: [&'static str; 4] = ["uniform", "stratified", "halton", "sobol"];
+
+    pub fn from_name(name: &str) -> Option<SamplerKind> {
+        match name {
+            "uniform" => Some(SamplerKind::Uniform),
+            "stratified" => Some(SamplerKind::Stratified),
+            "halton" => Some(SamplerKind::Halton),
+            "sobol" => Some(SamplerKind::Sobol),
+            _ => None,
+        }
+    }
+
+    pub fn build(&self, seed: u64) -> Box<dyn Sampler> {
+        match self {
+            SamplerKind::Uniform => Box::new(UniformSampler),
+            SamplerKind::Stratified => Box::new(StratifiedSampler),
+            SamplerKind::Halton => Box::new(HaltonSampler::from(seed)),
+            SamplerKind::Sobol => Box::new(SobolSampler::from(seed)),
+        }
+    }
+}
+
+fn to_unit(bits: u32) -> f64 {
+    bits as f64 / (1u64 << 32) as f64
+}
+
+/// Derives an independent seed for pixel `(i, j)` from a global seed.
+pub fn pixel_seed(seed: u64, i: u32, j: u32) -> u64 {
+    let mut rng = Rng::from(seed ^ ((j as u64) << 32 | i as u64));
+    rng.next_u64()
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    fn in_unit_square((u, v): (f64, f64)) -> bool {
+        (0f64..1f64).contains(&u) && (0f64..1f64).contains(&v)
+    }
+
+    #[test]
+    fn stratified_covers_every_cell() {
+        let mut seen = [false; 16];
+        for index in 0..16 {
+            let s = StratifiedSampler.sample(3, 4, index, 16);
+            assert!(in_unit_square(s));
+            seen[(s.0 * 4f64) as usize + 4 * (s.1 * 4f64) as usize] = true;
+        }
+        assert!(seen.iter().all(|&cell| cell));
+        assert!(in_unit_square(StratifiedSampler.sample(0, 0, 17, 18)));
+        assert!(in_unit_square(UniformSampler.sample(0, 0, 0, 1)));
+    }
+
+    #[test]
+    fn halton_sequence() {
+        assert_eq!(0f64, HaltonSampler::radical_inverse(2, 0));
+        assert_eq!(0.5, HaltonSampler::radical_inverse(2, 1));
+        assert_eq!(0.25, HaltonSampler::radical_inverse(2, 2));
+        assert_eq!(0.75, HaltonSampler::radical_inverse(2, 3));
+        assert!((HaltonSampler::radical_inverse(3, 1) - 1f64 / 3f64).abs() < 1e-15);
+        assert!((HaltonSampler::radical_inverse(3, 5) - 7f64 / 9f64).abs() < 1e-15);
+
+        let sampler = HaltonSampler::from(7);
+        for index in 0..64 {
+            assert!(in_unit_square(sampler.sample(10, 20, index, 64)));
+        }
+        assert_eq!(sampler.sample(10, 20, 5, 64), sampler.sample(10, 20, 5, 64));
+    }
+
+    #[test]
+    fn sobol_is_a_0_2_net() {
+        // Every power of two prefix puts exactly one point in each elementary interval.
+        let sampler = SobolSampler::from(3);
+        for (rows, cols) in [(1, 16), (2, 8), (4, 4), (8, 2), (16, 1)] {
+            let mut seen = [false; 16];
+            for index in 0..16 {
+                let (u, v) = sampler.sample(5, 6, index, 16);
+                let cell = (u * cols as f64) as usize + cols * (v * rows as f64) as usize;
+                assert!(!seen[cell]);
+                seen[cell] = true;
+            }
+        }
+    }
+}
@@ -0,0 +1,1442 @@
+use std::collections::HashMap;
+use std::fmt::Display;
+use std::path::Path;
+use std::sync::Arc;
+
+use crate::aarect::*;
+use crate::background::*;
+use crate::camera::*;
+use crate::cli::*;
+use crate::color::*;
+use crate::cuboid::*;
+use crate::exr::*;
+use crate::hittable::*;
+use crate::hittable_list::*;
+use crate::image::*;
+use crate::instance::*;
+use crate::material::*;
+use crate::moving_sphere::*;
+use crate::noise::*;
+use crate::obj::*;
+use crate::quad::*;
+use crate::rng::*;
+use crate::sampler::*;
+use crate::sphere::*;
+use crate::texture::*;
+use crate::toml::{self, Item, Position, Table, Value};
+use crate::transform::*;
+use crate::triangle::*;
+use crate::vec3::*;
+
+/// A world together with the camera framing it and the render settings it asks for.
+pub struct Scene {
+    pub world: HittableList,
+    pub background: Background,
+    pub camera: CameraSettings,
+    /// Settings from the scene file, overridden by those given on the command line.
+    pub render: Options,
+}
+
+/// A problem in a scene file, with where it was found when it is known.
+#[derive(Debug, Clone, PartialEq)]
+pub struct SceneError {
+    pub message: String,
+    pub position: Option<Position>,
+}
+
+impl Display for SceneError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self.position {
+            Some(position) => write!(f, "{position}: {}", self.message),
+            None => f.write_str(&self.message),
+        }
+    }
+}
+
+impl std::error::Error for SceneError {}
+
+impl From<toml::ParseError> for SceneError {
+    fn from(err: toml::ParseError) -> SceneError {
+        SceneError {
+            message: err.message,
+            position: Some(err.position),
+        }
+    }
+}
+
+fn error<T>(message: impl Into<String>, position: Position) -> Result<T, SceneError> {
+    Err(SceneError {
+        message: message.into(),
+        position: Some(position),
+    })
+}
+
+impl Scene {
+    pub const BUILTIN_NAMES: [&'static str; 3] = ["three-spheres", "random-spheres", "cornell-box"];
+
+    /// Returns the built-in scene called `name`, if any.
+    pub fn builtin(name: &str) -> Option<Scene> {
+        match name {
+            "three-spheres" => Some(Scene::three_spheres()),
+            "random-spheres" => Some(Scene::random_spheres()),
+            "cornell-box" => Some(Scene::cornell_box()),
+            _ => None,
+        }
+    }
+
+    /// Diffuse, glass and metal spheres sitting on a large ground sphere.
+    pub fn three_spheres() -> Scene {
+        let material_ground = Arc::new(Lambertian::from(Color::from(0.8, 0.8, 0f64)));
+        let material_center = Arc::new(Lambertian::from(Color::from(0.1, 0.2, 0.5)));
+        let material_left = Arc::new(Dielectric::from(1.5));
+        let material_right = Arc::new(Metal::from(Color::from(0.8, 0.6, 0.2), 0f64));
+
+        let mut world = HittableList::new();
+        world.add(Arc::new(Sphere::from(
+            Point3::from(0f64, -100.5, -1f64),
+            100f64,
+            material_ground,
+        )));
+        world.add(Arc::new(Sphere::from(
+            Point3::from(0f64, 0f64, -1f64),
+            0.5,
+            material_center,
+        )));
+        world.add(Arc::new(Sphere::from(
+            Point3::from(-1f64, 0f64, -1f64),
+            0.5,
+            material_left,
+        )));
+        world.add(Arc::new(Sphere::from(
+            Point3::from(1f64, 0f64, -1f64),
+            0.5,
+            material_right,
+        )));
+
+        Scene {
+            world,
+            background: Background::default(),
+            camera: CameraSettings::default(),
+            render: Options::default(),
+        }
+    }
+
+    /// The cover of "Ray Tracing in One Weekend": a field of small random spheres around three
+    /// large ones. The layout is drawn from the calling thread's generator.
+    pub fn random_spheres() -> Scene {
+        let mut world = HittableList::new();
+
+        let ground_material = Arc::new(Lambertian::from(Color::from(0.5, 0.5, 0.5)));
+        world.add(Arc::new(Sphere::from(
+            Point3::from(0f64, -1000f64, 0f64),
+            1000f64,
+            ground_material,
+        )));
+
+        for a in -11..11 {
+            for b in -11..11 {
+                let choose_mat = random_double();
+                let center = Point3::from(
+                    a as f64 + 0.9 * random_double(),
+                    0.2,
+                    b as f64 + 0.9 * random_double(),
+                );
+
+                if (center - Point3::from(4f64, 0.2, 0f64)).length() > 0.9 {
+                    let sphere_material: Arc<dyn Material> = if choose_mat < 0.8 {
+                        // diffuse
+                        let albedo = Color::random() * Color::random();
+                        Arc::new(Lambertian::from(albedo))
+                    } else if choose_mat < 0.95 {
+                        // metal
+                        let albedo = Color::random_range(0.5, 1f64);
+                        let fuzz = random_double_range(0f64, 0.5);
+                        Arc::new(Metal::from(albedo, fuzz))
+                    } else {
+                        // glass
+                        Arc::new(Dielectric::from(1.5))
+                    };
+                    world.add(Arc::new(Sphere::from(center, 0.2, sphere_material)));
+                }
+            }
+        }
+
+        let material1 = Arc::new(Dielectric::from(1.5));
+        world.add(Arc::new(Sphere::from(
+            Point3::from(0f64, 1f64, 0f64),
+            1f64,
+            material1,
+        )));
+
+        let material2 = Arc::new(Lambertian::from(Color::from(0.4, 0.2, 0.1)));
+        world.add(Arc::new(Sphere::from(
+            Point3::from(-4f64, 1f64, 0f64),
+            1f64,
+            material2,
+        )));
+
+        let material3 = Arc::new(Metal::from(Color::from(0.7, 0.6, 0.5), 0f64));
+        world.add(Arc::new(Sphere::from(
+            Point3::from(4f64, 1f64, 0f64),
+            1f64,
+            material3,
+        )));
+
+        Scene {
+            world,
+            background: Background::default(),
+            camera: CameraSettings {
+                lookfrom: Point3::from(13f64, 2f64, 3f64),
+                lookat: Point3::from(0f64, 0f64, 0f64),
+                vup: Vec3::from(0f64, 1f64, 0f64),
+                vfov: 20f64,
+                aperture: 0.1,
+                focus_dist: Some(10f64),
+                shutter: (0f64, 0f64),
+            },
+            render: Options::default(),
+        }
+    }
+
+    /// The Cornell box: a closed room with a red and a green wall, lit only by an area light in
+    /// the ceiling, holding two white boxes.
+    pub fn cornell_box() -> Scene {
+        let red = Arc::new(Lambertian::from(Color::from(0.65, 0.05, 0.05)));
+        let white = Arc::new(Lambertian::from(Color::from(0.73, 0.73, 0.73)));
+        let green = Arc::new(Lambertian::from(Color::from(0.12, 0.45, 0.15)));
+        let light = Arc::new(DiffuseLight::from(Color::from(15f64, 15f64, 15f64)));
+
+        let mut world = HittableList::new();
+        world.add(Arc::new(YzRect::from(
+            0f64, 555f64, 0f64, 555f64, 555f64, green,
+        )));
+        world.add(Arc::new(YzRect::from(
+            0f64, 555f64, 0f64, 555f64, 0f64, red,
+        )));
+        world.add(Arc::new(XzRect::from(
+            213f64, 343f64, 227f64, 332f64, 554f64, light,
+        )));
+        world.add(Arc::new(XzRect::from(
+            0f64,
+            555f64,
+            0f64,
+            555f64,
+            0f64,
+            white.clone(),
+        )));
+        world.add(Arc::new(XzRect::from(
+            0f64,
+            555f64,
+            0f64,
+            555f64,
+            555f64,
+            white.clone(),
+        )));
+        world.add(Arc::new(XyRect::from(
+            0f64,
+            555f64,
+            0f64,
+            555f64,
+            555f64,
+            white.clone(),
+        )));
+
+        let tall = Arc::new(Cuboid::from(
+            Point3::new(),
+            Point3::from(165f64, 330f64, 165f64),
+            white.clone(),
+        ));
+        world.add(Arc::new(Instance::from(
+            tall,
+            Transform::translate(Vec3::from(265f64, 0f64, 295f64)) * Transform::rotate_y(15f64),
+        )));
+        let short = Arc::new(Cuboid::from(
+            Point3::new(),
+            Point3::from(165f64, 165f64, 165f64),
+            white,
+        ));
+        world.add(Arc::new(Instance::from(
+            short,
+            Transform::translate(Vec3::from(130f64, 0f64, 65f64)) * Transform::rotate_y(-18f64),
+        )));
+
+        Scene {
+            world,
+            background: Background::Solid(Color::new()),
+            camera: CameraSettings {
+                lookfrom: Point3::from(278f64, 278f64, -800f64),
+                lookat: Point3::from(278f64, 278f64, 0f64),
+                vfov: 40f64,
+                ..CameraSettings::default()
+            },
+            render: Options {
+                aspect_ratio: Some(1f64),
+                width: Some(600),
+                samples_per_pixel: Some(200),
+                ..Options::default()
+            },
+        }
+    }
+
+    /// Reads a scene file. See [`Scene::parse`] for the format.
+    pub fn load<P: AsRef<Path>>(path: P) -> Result<Scene, SceneError> {
+        let text = std::fs::read_to_string(path.as_ref()).map_err(|err| SceneError {
+            message: format!("cannot read the scene: {err}"),
+            position: None,
+        })?;
+        let dir = path.as_ref().parent().unwrap_or(Path::new(""));
+        Scene::parse_in(&text, dir)
+    }
+
+    /// Builds a scene from its TOML description:
+    ///
+    /// ```toml
+    /// [render]                    # optional, the command line's image and rendering settings
+    /// width = 400
+    /// aspect_ratio = "16:9"
+    /// samples_per_pixel = 100
+    /// exr_type = "float"          # for .exr output, with exr_compression and aovs
+    ///
+    /// [camera]                    # optional, the defaults look down -z from the origin
+    /// lookfrom = [13, 2, 3]
+    /// lookat = [0, 0, 0]
+    /// vfov = 20
+    /// shutter = [0, 1]            # open and close times, for motion blur
+    ///
+    /// [background]                # optional, the default is a white to blue sky
+    /// type = "solid"              # also gradient (bottom, top) and environment (texture)
+    /// color = [0, 0, 0]
+    ///
+    /// [textures.tiles]            # named textures: solid, checker, uv_checker, image, noise
+    /// type = "checker"
+    /// scale = 0.5
+    /// even = [0.2, 0.3, 0.1]      # a color, a texture name or an inline texture
+    /// odd = { type = "noise", noise = "perlin", style = "marble", scale = 4 }
+    ///
+    /// [textures.wood]
+    /// type = "image"
+    /// file = "wood.png"           # relative to the scene file
+    /// lod = 1                     # optional, the mipmap level read, blurring the image
+    ///
+    /// [materials.glass]           # named materials, shared between objects
+    /// type = "dielectric"
+    /// ir = 1.5
+    ///
+    /// [materials.floor]
+    /// type = "lambertian"
+    /// albedo = "tiles"            # a color or a texture
+    ///
+    /// [materials.lamp]
+    /// type = "diffuse_light"      # emits light rather than reflecting it
+    /// emit = [15, 15, 15]         # a color or a texture
+    ///
+    /// [[objects]]
+    /// type = "sphere"
+    /// center = [0, 1, 0]
+    /// radius = 1
+    /// material = "glass"          # a name, or an inline table such as
+    ///                             # { type = "metal", albedo = [0.7, 0.6, 0.5], fuzz = 0 }
+    ///
+    /// [[objects]]
+    /// type = "moving_sphere"
+    /// center = [0, 0, 0]
+    /// center_end = [0, 0.5, 0]    # where the sphere is at the end of `time`
+    /// radius = 0.2
+    /// material = "glass"
+    ///
+    /// [[objects]]
+    /// type = "triangle"
+    /// vertices = [[0, 0, 0], [1, 0, 0], [0, 1, 0]]
+    /// material = "glass"
+    ///
+    /// [[objects]]
+    /// type = "quad"               # parallelogram with a corner and two sides
+    /// corner = [-1, 0, -1]
+    /// u = [2, 0, 0]
+    /// v = [0, 2, 0]
+    /// material = "glass"
+    ///
+    /// [[objects]]
+    /// type = "xz_rect"            # also xy_rect and yz_rect
+    /// x = [0, 555]
+    /// z = [0, 555]
+    /// y = 555
+    /// material = "glass"
+    ///
+    /// [[objects]]
+    /// type = "box"
+    /// min = [0, 0, 0]
+    /// max = [1, 1, 1]
+    /// material = "glass"
+    ///
+    /// [[objects]]
+    /// type = "plane"
+    /// point = [0, 0, 0]
+    /// normal = [0, 1, 0]
+    /// material = "glass"
+    ///
+    /// [[objects]]
+    /// type = "mesh"
+    /// file = "bunny.obj"          # relative to the scene file
+    /// material = "glass"          # optional, overrides the MTL materials
+    /// transform = { scale = 2, rotate = [0, 15, 0], translate = [265, 0, 295] }
+    /// ```
+    ///
+    /// Any object can be given a `transform`, applying `scale`, `rotate` (degrees around x, then y,
+    /// then z) and `translate` in that order. Meshes loaded several times from the same file share
+    /// their geometry.
+    ///
+    /// Objects can move while the camera shutter, set with `shutter = [open, close]` under
+    /// `[camera]`, is open. A `moving_sphere` goes from `center` to `center_end`, and any object
+    /// given a `transform_end` is interpolated from its `transform` to it, with the parts left out
+    /// of `transform_end` kept still. Both move over `time = [start, end]`, which defaults to
+    /// `[0, 1]`, and hold still before and after it.
+    ///
+    /// Sections are read in order, so textures must come before the materials and background
+    /// using them, and materials before the objects. Unknown keys are errors, so that typos do not
+    /// go unnoticed.
+    pub fn parse(text: &str) -> Result<Scene, SceneError> {
+        Scene::parse_in(text, Path::new(""))
+    }
+
+    /// Like [`Scene::parse`], with files named in the scene relative to `dir`.
+    fn parse_in(text: &str, dir: &Path) -> Result<Scene, SceneError> {
+        let root = toml::parse(text)?;
+        let mut scene = Scene {
+            world: HittableList::new(),
+            background: Background::default(),
+            camera: CameraSettings::default(),
+            render: Options::default(),
+        };
+        let mut loader = Loader {
+            dir,
+            textures: HashMap::new(),
+            materials: HashMap::new(),
+            meshes: HashMap::new(),
+        };
+
+        for (key, item) in root.iter() {
+            match key {
+                "render" => scene.render = render_options(Fields::of(item)?)?,
+                "camera" => scene.camera = camera_settings(Fields::of(item)?)?,
+                "background" => scene.background = loader.background(Fields::of(item)?)?,
+                "textures" => {
+                    for (name, item) in Fields::of(item)?.table.iter() {
+                        let texture = loader.texture(Fields::of(item)?)?;
+                        loader.textures.insert(name.to_string(), texture);
+                    }
+                }
+                "materials" => {
+                    for (name, item) in Fields::of(item)?.table.iter() {
+                        let material = loader.material(Fields::of(item)?)?;
+                        loader.materials.insert(name.to_string(), material);
+                    }
+                }
+                "objects" => {
+                    let Value::Array(objects) = &item.value else {
+                        return error("'objects' must be an array of tables", item.position);
+                    };
+                    for object in objects {
+                        for object in loader.object(Fields::of(object)?)? {
+                            scene.world.add(object);
+                        }
+                    }
+                }
+                _ => {
+                    return error(
+                        format!(
+                            "unknown section '{key}', expected render, camera, background, \
+                             textures, materials or objects"
+                        ),
+                        item.position,
+                    )
+                }
+            }
+        }
+
+        if (scene.camera.lookfrom - scene.camera.lookat).near_zero() {
+            return error(
+                "the camera cannot look at its own position",
+                root.get("camera")
+                    .map_or(Position::default(), |c| c.position),
+            );
+        }
+        Ok(scene)
+    }
+}
+
+/// The entries of a table, keeping track of those that were read so the others can be reported.
+struct Fields<'a> {
+    table: &'a Table,
+    position: Position,
+    used: Vec<&'a str>,
+}
+
+impl<'a> Fields<'a> {
+    fn of(item: &'a Item) -> Result<Fields<'a>, SceneError> {
+        match &item.value {
+            Value::Table(table) => Ok(Fields {
+                table,
+                position: item.position,
+                used: Vec::new(),
+            }),
+            value => error(
+                format!("expected a table, found {}", value.type_name()),
+                item.position,
+            ),
+        }
+    }
+
+    fn get(&mut self, key: &'a str) -> Option<&'a Item> {
+        self.used.push(key);
+        self.table.get(key)
+    }
+
+    fn required<T>(&self, key: &str, value: Option<T>) -> Result<T, SceneError> {
+        match value {
+            Some(value) => Ok(value),
+            None => error(format!("missing key '{key}'"), self.position),
+        }
+    }
+
+    fn string(&mut self, key: &'a str) -> Result<Option<&'a str>, SceneError> {
+        match self.get(key) {
+            None => Ok(None),
+            Some(Item {
+                value: Value::String(s),
+                ..
+            }) => Ok(Some(s)),
+            Some(item) => expected(key, "a string", item),
+        }
+    }
+
+    fn boolean(&mut self, key: &'a str) -> Result<Option<bool>, SceneError> {
+        match self.get(key) {
+            None => Ok(None),
+            Some(Item {
+                value: Value::Boolean(b),
+                ..
+            }) => Ok(Some(*b)),
+            Some(item) => expected(key, "true or false", item),
+        }
+    }
+
+    fn integer(&mut self, key: &'a str) -> Result<Option<i64>, SceneError> {
+        match self.get(key) {
+            None => Ok(None),
+            Some(Item {
+                value: Value::Integer(n),
+                ..
+            }) => Ok(Some(*n)),
+            Some(item) => expected(key, "an integer", item),
+        }
+    }
+
+    /// An integer that fits the unsigned type `T`.
+    fn non_negative_integer<T: TryFrom<i64>>(
+        &mut self,
+        key: &'a str,
+    ) -> Result<Option<T>, SceneError> {
+        match self.integer(key)?.map(T::try_from) {
+            None => Ok(None),
+            Some(Ok(n)) => Ok(Some(n)),
+            Some(Err(_)) => expected(key, "a non negative integer", self.table.get(key).unwrap()),
+        }
+    }
+
+    fn positive_integer(&mut self, key: &'a str) -> Result<Option<u32>, SceneError> {
+        match self.integer(key)? {
+            None => Ok(None),
+            Some(n) if n > 0 && n <= u32::MAX as i64 => Ok(Some(n as u32)),
+            Some(_) => expected(key, "a positive integer", self.table.get(key).unwrap()),
+        }
+    }
+
+    fn number(&mut self, key: &'a str) -> Result<Option<f64>, SceneError> {
+        match self.get(key) {
+            None => Ok(None),
+            Some(item) => match number(item) {
+                Some(x) => Ok(Some(x)),
+                None => expected(key, "a number", item),
+            },
+        }
+    }
+
+    fn positive_number(&mut self, key: &'a str) -> Result<Option<f64>, SceneError> {
+        match self.number(key)? {
+            Some(x) if x <= 0f64 => {
+                expected(key, "a positive number", self.table.get(key).unwrap())
+            }
+            x => Ok(x),
+        }
+    }
+
+    fn vector(&mut self, key: &'a str) -> Result<Option<Vec3>, SceneError> {
+        match self.get(key) {
+            None => Ok(None),
+            Some(item) => match vector(item) {
+                Some(v) => Ok(Some(v)),
+                None => expected(key, "an array of three numbers", item),
+            },
+        }
+    }
+
+    /// An interval given as `[min, max]`.
+    fn range(&mut self, key: &'a str) -> Result<Option<(f64, f64)>, SceneError> {
+        match self.get(key) {
+            None => Ok(None),
+            Some(item) => match &item.value {
+                Value::Array(bounds) if bounds.len() == 2 => {
+                    match (number(&bounds[0]), number(&bounds[1])) {
+                        (Some(min), Some(max)) if min < max => Ok(Some((min, max))),
+                        _ => expected(key, "an interval [min, max] with min < max", item),
+                    }
+                }
+                _ => expected(key, "an interval [min, max] with min < max", item),
+            },
+        }
+    }
+
+    /// Fails on the first entry that was never read.
+    fn finish(self) -> Result<(), SceneError> {
+        match self.table.iter().find(|(key, _)| !self.used.contains(key)) {
+            Some((key, item)) => error(format!("unknown key '{key}'"), item.position),
+            None => Ok(()),
+        }
+    }
+}
+
+fn number(item: &Item) -> Option<f64> {
+    match item.value {
+        Value::Integer(n) => Some(n as f64),
+        Value::Float(x) => Some(x),
+        _ => None,
+    }
+}
+
+fn vector(item: &Item) -> Option<Vec3> {
+    match &item.value {
+        Value::Array(components) if components.len() == 3 => {
+            let c = components
+                .iter()
+                .map(number)
+                .collect::<Option<Vec<f64>>>()?;
+            Some(Vec3::from(c[0], c[1], c[2]))
+        }
+        _ => None,
+    }
+}
+
+fn expected<T>(key: &str, what: &str, item: &Item) -> Result<T, SceneError> {
+    error(
+        format!(
+            "'{key}' must be {what}, not {}",
+            match &item.value {
+                Value::String(s) => format!("\"{s}\""),
+                Value::Integer(n) => n.to_string(),
+                Value::Float(x) => x.to_string(),
+                value => value.type_name().to_string(),
+            }
+        ),
+        item.position,
+    )
+}
+
+fn one_of<'a>(key: &str, item: &Item, names: &[&'a str]) -> Result<&'a str, SceneError> {
+    match &item.value {
+        Value::String(s) => match names.iter().find(|&&name| name == s) {
+            Some(name) => Ok(name),
+            None => expected(key, &format!("one of {}", names.join(", ")), item),
+        },
+        _ => expected(key, &format!("one of {}", names.join(", ")), item),
+    }
+}
+
+fn render_options(mut fields: Fields) -> Result<Options, SceneError> {
+    let mut options = Options {
+        width: fields.positive_integer("width")?,
+        height: fields.positive_integer("height")?,
+        samples_per_pixel: fields.positive_integer("samples_per_pixel")?,
+        max_depth: fields.positive_integer("max_depth")?,
+        rr_depth: fields.non_negative_integer("rr_depth")?,
+        seed: fields.non_negative_integer("seed")?,
+        threads: fields.non_negative_integer("threads")?,
+        tile_size: fields.positive_integer("tile_size")?,
+        aovs: fields.boolean("aovs")?,
+        ..Options::default()
+    };
+    if let Some(item) = fields.get("aspect_ratio") {
+        let ratio = match &item.value {
+            Value::String(s) => match s.split_once(':') {
+                Some((w, h)) => match (w.trim().parse::<f64>(), h.trim().parse::<f64>()) {
+                    (Ok(w), Ok(h)) => w / h,
+                    _ => f64::NAN,
+                },
+                None => f64::NAN,
+            },
+            _ => number(item).unwrap_or(f64::NAN),
+        };
+        if !(ratio.is_finite() && ratio > 0f64) {
+            return expected(
+                "aspect_ratio",
+                "a positive ratio such as 1.5 or \"16:9\"",
+                item,
+            );
+        }
+        options.aspect_ratio = Some(ratio);
+    }
+    if let Some(item) = fields.get("sampler") {
+        options.sampler = SamplerKind::from_name(one_of("sampler", item, &SamplerKind::NAMES)?);
+    }
+    if let Some(output) = fields.string("output")? {
+        if ImageFormat::from_path(output).is_none() {
+            let item = fields.table.get("output").unwrap();
+            return expected("output", "a file ending in .ppm, .pfm, .png or .exr", item);
+        }
+        options.output = Some(output.to_string());
+    }
+    if let Some(item) = fields.get("exr_type") {
+        options.exr_pixel_type =
+            ExrPixelType::from_name(one_of("exr_type", item, &ExrPixelType::NAMES)?);
+    }
+    if let Some(item) = fields.get("exr_compression") {
+        options.exr_compression =
+            ExrCompression::from_name(one_of("exr_compression", item, &ExrCompression::NAMES)?);
+    }
+
+    let exposure = fields.positive_number("exposure")?;
+    options.tone_map = match fields.get("tone_map") {
+        None => exposure.map(ToneMap::Exposure),
+        Some(item) => match one_of("tone_map", item, &["clamp", "reinhard", "aces", "exposure"])? {
+            "exposure" => Some(ToneMap::Exposure(exposure.unwrap_or(1f64))),
+            _ if exposure.is_some() => {
+                return error(
+                    "'exposure' only applies to tone_map = \"exposure\"",
+                    item.position,
+                )
+            }
+            "reinhard" => Some(ToneMap::Reinhard),
+            "aces" => Some(ToneMap::Aces),
+            _ => Some(ToneMap::Clamp),
+        },
+    };
+    let gamma = fields.positive_number("gamma")?;
+    options.transfer = match fields.get("transfer") {
+        None => gamma.map(Transfer::Gamma),
+        Some(item) => match one_of("transfer", item, &["linear", "srgb", "gamma"])? {
+            "gamma" => Some(Transfer::Gamma(gamma.unwrap_or(2.2))),
+            _ if gamma.is_some() => {
+                return error(
+                    "'gamma' only applies to transfer = \"gamma\"",
+                    item.position,
+                )
+            }
+            "linear" => Some(Transfer::Linear),
+            _ => Some(Transfer::Srgb),
+        },
+    };
+
+    fields.finish()?;
+    Ok(options)
+}
+
+fn camera_settings(mut fields: Fields) -> Result<CameraSettings, SceneError> {
+    let default = CameraSettings::default();
+    let settings = CameraSettings {
+        lookfrom: fields.vector("lookfrom")?.unwrap_or(default.lookfrom),
+        lookat: fields.vector("lookat")?.unwrap_or(default.lookat),
+        vup: fields.vector("vup")?.unwrap_or(default.vup),
+        vfov: fields.number("vfov")?.unwrap_or(default.vfov),
+        aperture: fields.number("aperture")?.unwrap_or(default.aperture),
+        focus_dist: fields.positive_number("focus_dist")?.or(default.focus_dist),
+        shutter: fields.range("shutter")?.unwrap_or(default.shutter),
+    };
+    if settings.vup.near_zero() {
+        return expected("vup", "a non zero vector", fields.table.get("vup").unwrap());
+    }
+    if !(settings.vfov > 0f64 && settings.vfov < 180f64) {
+        let item = fields.table.get("vfov").unwrap();
+        return expected("vfov", "an angle between 0 and 180 degrees", item);
+    }
+    if settings.aperture < 0f64 {
+        let item = fields.table.get("aperture").unwrap();
+        return expected("aperture", "a non negative number", item);
+    }
+    fields.finish()?;
+    Ok(settings)
+}
+
+/// A mesh file and the name of the material overriding its own, if any.
+type MeshKey = (String, Option<String>);
+
+/// State shared while building the objects of a scene.
+struct Loader<'a> {
+    /// Directory that files named in the scene are relative to.
+    dir: &'a Path,
+    textures: HashMap<String, Arc<dyn Texture>>,
+    materials: HashMap<String, Arc<dyn Material>>,
+    /// Meshes already read, by file and material name, so that objects using the same file share
+    /// one copy of the geometry.
+    meshes: HashMap<MeshKey, Vec<Arc<dyn Hittable>>>,
+}
+
+impl Loader<'_> {
+    fn background(&self, mut fields: Fields) -> Result<Background, SceneError> {
+        let kind = fields.string("type")?;
+        let background = match fields.required("type", kind)? {
+            "solid" => {
+                let color = fields.vector("color")?;
+                Background::Solid(fields.required("color", color)?)
+            }
+            // Defaults to the sky of the built-in scenes.
+            "gradient" => Background::Gradient {
+                bottom: fields
+                    .vector("bottom")?
+                    .unwrap_or(Color::from(1f64, 1f64, 1f64)),
+                top: fields.vector("top")?.unwrap_or(Color::from(0.5, 0.7, 1f64)),
+            },
+            "environment" => {
+                let texture = fields.get("texture");
+                let texture =
+                    self.texture_value("texture", fields.required("texture", texture)?)?;
+                Background::Environment(texture)
+            }
+            kind => {
+                return error(
+                    format!(
+                        "unknown background type '{kind}', expected solid, gradient or environment"
+                    ),
+                    fields.table.get("type").unwrap().position,
+                )
+            }
+        };
+        fields.finish()?;
+        Ok(background)
+    }
+
+    /// Builds a texture, whose parts may be colors, names or textures of their own.
+    fn texture(&self, mut fields: Fields) -> Result<Arc<dyn Texture>, SceneError> {
+        let kind = fields.string("type")?;
+        let texture: Arc<dyn Texture> = match fields.required("type", kind)? {
+            "solid" => {
+                let color = fields.vector("color")?;
+                Arc::new(SolidColor::from(fields.required("color", color)?))
+            }
+            "checker" => {
+                let scale = fields.positive_number("scale")?.unwrap_or(1f64);
+                let even = fields.get("even");
+                let even = self.texture_value("even", fields.required("even", even)?)?;
+                let odd = fields.get("odd");
+                let odd = self.texture_value("odd", fields.required("odd", odd)?)?;
+                Arc::new(CheckerTexture::from(scale, even, odd))
+            }
+            "uv_checker" => {
+                let width = fields.positive_integer("width")?;
+                let width = fields.required("width", width)?;
+                let height = fields.positive_integer("height")?;
+                let height = fields.required("height", height)?;
+                let even = fields.get("even");
+                let even = self.texture_value("even", fields.required("even", even)?)?;
+                let odd = fields.get("odd");
+                let odd = self.texture_value("odd", fields.required("odd", odd)?)?;
+                Arc::new(UvCheckerTexture::from(width, height, even, odd))
+            }
+            "image" => {
+                let file = fields.string("file")?;
+                let file = fields.required("file", file)?;
+                let lod = fields.number("lod")?.unwrap_or(0f64);
+                if lod < 0f64 {
+                    let item = fields.table.get("lod").unwrap();
+                    return expected("lod", "a non negative number", item);
+                }
+                let texture =
+                    ImageTexture::load(self.dir.join(file)).map_err(|err| SceneError {
+                        message: format!("cannot load {file}: {err}"),
+                        position: Some(fields.table.get("file").unwrap().position),
+                    })?;
+                Arc::new(texture.with_lod(lod))
+            }
+            "noise" => {
+                let scale = fields.positive_number("scale")?.unwrap_or(1f64);
+                let style = match fields.get("style") {
+                    None => NoiseStyle::Smooth,
+                    Some(item) => match one_of("style", item, &["smooth", "turbulence", "marble"])?
+                    {
+                        "turbulence" => NoiseStyle::Turbulence,
+                        "marble" => NoiseStyle::Marble,
+                        _ => NoiseStyle::Smooth,
+                    },
+                };
+                let seed = fields.non_negative_integer("seed")?.unwrap_or(0);
+                let noise: Arc<dyn Noise> = match fields.get("noise") {
+                    None => Arc::new(Perlin::from(seed)),
+                    Some(item) => match one_of("noise", item, &["perlin", "simplex", "worley"])? {
+                        "simplex" => Arc::new(Simplex::from(seed)),
+                        "worley" => Arc::new(Worley::from(seed)),
+                        _ => Arc::new(Perlin::from(seed)),
+                    },
+                };
+                Arc::new(NoiseTexture::from(noise, scale, style))
+            }
+            kind => {
+                return error(
+                    format!(
+                        "unknown texture type '{kind}', expected solid, checker, uv_checker, \
+                         image or noise"
+                    ),
+                    fields.table.get("type").unwrap().position,
+                )
+            }
+        };
+        fields.finish()?;
+        Ok(texture)
+    }
+
+    /// Resolves an entry holding a color, the name of a texture or an inline texture.
+    fn texture_value(&self, key: &str, item: &Item) -> Result<Arc<dyn Texture>, SceneError> {
+        match &item.value {
+            Value::String(name) => match self.textures.get(name) {
+                Some(texture) => Ok(texture.clone()),
+                None => error(format!("unknown texture '{name}'"), item.position),
+            },
+            Value::Table(_) => self.texture(Fields::of(item)?),
+            _ => match vector(item) {
+                Some(color) => Ok(Arc::new(SolidColor::from(color))),
+                None => expected(key, "a color, a texture name or a texture table", item),
+            },
+        }
+    }
+
+    fn material(&self, mut fields: Fields) -> Result<Arc<dyn Material>, SceneError> {
+        let kind = fields.string("type")?;
+        let material: Arc<dyn Material> = match fields.required("type", kind)? {
+            "lambertian" => {
+                let albedo = fields.get("albedo");
+                let albedo = self.texture_value("albedo", fields.required("albedo", albedo)?)?;
+                Arc::new(Lambertian::from_texture(albedo))
+            }
+            "metal" => {
+                let albedo = fields.vector("albedo")?;
+                let fuzz = fields.number("fuzz")?.unwrap_or(0f64);
+                if !(0f64..=1f64).contains(&fuzz) {
+                    let item = fields.table.get("fuzz").unwrap();
+                    return expected("fuzz", "a number between 0 and 1", item);
+                }
+                Arc::new(Metal::from(fields.required("albedo", albedo)?, fuzz))
+            }
+            "dielectric" => {
+                let ir = fields.positive_number("ir")?;
+                Arc::new(Dielectric::from(fields.required("ir", ir)?))
+            }
+            "diffuse_light" => {
+                let emit = fields.get("emit");
+                let emit = self.texture_value("emit", fields.required("emit", emit)?)?;
+                Arc::new(DiffuseLight::from_texture(emit))
+            }
+            kind => {
+                return error(
+                    format!(
+                        "unknown material type '{kind}', expected lambertian, metal, dielectric or \
+                         diffuse_light"
+                    ),
+                    fields.table.get("type").unwrap().position,
+                )
+            }
+        };
+        fields.finish()?;
+        Ok(material)
+    }
+
+    /// Resolves the `material` entry of an object, either a name or an inline definition.
+    fn object_material(&self, item: &Item) -> Result<Arc<dyn Material>, SceneError> {
+        match item {
+            Item {
+                value: Value::String(name),
+                position,
+            } => match self.materials.get(name) {
+                Some(material) => Ok(material.clone()),
+                None => error(format!("unknown material '{name}'"), *position),
+            },
+            item => self.material(Fields::of(item)?),
+        }
+    }
+
+    /// Builds an object, which may take several hittables for meshes with many materials.
+    fn object(&mut self, mut fields: Fields) -> Result<Vec<Arc<dyn Hittable>>, SceneError> {
+        let mut objects: Vec<Arc<dyn Hittable>> = Vec::new();
+        let kind = fields.string("type")?;
+        match fields.required("type", kind)? {
+            "sphere" => {
+                let center = fields.vector("center")?;
+                let center = fields.required("center", center)?;
+                let radius = fields.positive_number("radius")?;
+                let radius = fields.required("radius", radius)?;
+                let material = fields.get("material");
+                let material = self.object_material(fields.required("material", material)?)?;
+                objects.push(Arc::new(Sphere::from(center, radius, material)));
+            }
+            "moving_sphere" => {
+                let center = fields.vector("center")?;
+                let center = fields.required("center", center)?;
+                let center_end = fields.vector("center_end")?;
+                let center_end = fields.required("center_end", center_end)?;
+                let radius = fields.positive_number("radius")?;
+                let radius = fields.required("radius", radius)?;
+                let (time0, time1) = fields.range("time")?.unwrap_or((0f64, 1f64));
+                let material = fields.get("material");
+                let material = self.object_material(fields.required("material", material)?)?;
+                objects.push(Arc::new(MovingSphere::from(
+                    center, center_end, time0, time1, radius, material,
+                )));
+            }
+            "triangle" => {
+                let item = fields.get("vertices");
+                let item = fields.required("vertices", item)?;
+                let vertices = match &item.value {
+                    Value::Array(items) if items.len() == 3 => {
+                        items.iter().map(vector).collect::<Option<Vec<Vec3>>>()
+                    }
+                    _ => None,
+                };
+                let Some(vertices) = vertices else {
+                    return expected("vertices", "an array of three points", item);
+                };
+                let material = fields.get("material");
+                let material = self.object_material(fields.required("material", material)?)?;
+                objects.push(Arc::new(Triangle::from(
+                    vertices[0],
+                    vertices[1],
+                    vertices[2],
+                    material,
+                )));
+            }
+            "quad" => {
+                let corner = fields.vector("corner")?;
+                let corner = fields.required("corner", corner)?;
+                let u = fields.vector("u")?;
+                let u = fields.required("u", u)?;
+                let v = fields.vector("v")?;
+                let v = fields.required("v", v)?;
+                if Vec3::cross(&u, &v).near_zero() {
+                    return error("the sides of a quad must not be parallel", fields.position);
+                }
+                let material = fields.get("material");
+                let material = self.object_material(fields.required("material", material)?)?;
+                objects.push(Arc::new(Quad::from(corner, u, v, material)));
+            }
+            kind @ ("xy_rect" | "xz_rect" | "yz_rect") => {
+                // The two axes the rectangle spans, then the one it is perpendicular to.
+                let axes = match kind {
+                    "xy_rect" => ["x", "y", "z"],
+                    "xz_rect" => ["x", "z", "y"],
+                    _ => ["y", "z", "x"],
+                };
+                let a = fields.range(axes[0])?;
+                let (a0, a1) = fields.required(axes[0], a)?;
+                let b = fields.range(axes[1])?;
+                let (b0, b1) = fields.required(axes[1], b)?;
+                let k = fields.number(axes[2])?;
+                let k = fields.required(axes[2], k)?;
+                let material = fields.get("material");
+                let material = self.object_material(fields.required("material", material)?)?;
+                match kind {
+                    "xy_rect" => objects.push(Arc::new(XyRect::from(a0, a1, b0, b1, k, material))),
+                    "xz_rect" => objects.push(Arc::new(XzRect::from(a0, a1, b0, b1, k, material))),
+                    _ => objects.push(Arc::new(YzRect::from(a0, a1, b0, b1, k, material))),
+                }
+            }
+            "box" => {
+                let min = fields.vector("min")?;
+                let min = fields.required("min", min)?;
+                let max = fields.vector("max")?;
+                let max = fields.required("max", max)?;
+                if !(min.x() < max.x() && min.y() < max.y() && min.z() < max.z()) {
+                    let item = fields.table.get("max").unwrap();
+                    return expected("max", "a corner above 'min' on every axis", item);
+                }
+                let material = fields.get("material");
+                let material = self.object_material(fields.required("material", material)?)?;
+                objects.push(Arc::new(Cuboid::from(min, max, material)));
+            }
+            "plane" => {
+                let point = fields.vector("point")?;
+                let point = fields.required("point", point)?;
+                let normal = fields.vector("normal")?;
+                let normal = fields.required("normal", normal)?;
+                if normal.near_zero() {
+                    let item = fields.table.get("normal").unwrap();
+                    return expected("normal", "a non zero vector", item);
+                }
+                let material = fields.get("material");
+                let material = self.object_material(fields.required("material", material)?)?;
+                objects.push(Arc::new(Plane::from(point, normal, material)));
+            }
+            "mesh" => {
+                let file = fields.string("file")?;
+                let file = fields.required("file", file)?;
+                let material_item = fields.get("material");
+                let material = match material_item {
+                    Some(item) => Some(self.object_material(item)?),
+                    None => None,
+                };
+                // Inline materials are never shared, so neither is the geometry using them.
+                let key = match material_item {
+                    None => Some((file.to_string(), None)),
+                    Some(Item {
+                        value: Value::String(name),
+                        ..
+                    }) => Some((file.to_string(), Some(name.clone()))),
+                    Some(_) => None,
+                };
+                match key.as_ref().and_then(|key| self.meshes.get(key)) {
+                    Some(meshes) => objects.extend(meshes.iter().cloned()),
+                    None => {
+                        let load_error = |err| SceneError {
+                            message: format!("cannot load {file}: {err}"),
+                            position: Some(fields.table.get("file").unwrap().position),
+                        };
+                        let obj = load_obj(self.dir.join(file)).map_err(load_error)?;
+                        let default = ObjMaterial::new("default");
+                        // Meshes sharing an MTL material share its texture too.
+                        let mut converted: HashMap<&str, Arc<dyn Material>> = HashMap::new();
+                        for mesh in &obj.meshes {
+                            let name = mesh.material.as_deref().unwrap_or_default();
+                            let material = match (&material, converted.get(name)) {
+                                (Some(material), _) | (None, Some(material)) => material.clone(),
+                                (None, None) => {
+                                    let obj_material = obj.material(name).unwrap_or(&default);
+                                    let material =
+                                        obj_material.to_material().map_err(load_error)?;
+                                    converted.insert(name, material.clone());
+                                    material
+                                }
+                            };
+                            objects.push(Arc::new(TriangleMesh::from_obj(mesh, material)));
+                        }
+                        if let Some(key) = key {
+                            self.meshes.insert(key, objects.clone());
+                        }
+                    }
+                }
+            }
+            kind => {
+                return error(
+                    format!(
+                        "unknown object type '{kind}', expected sphere, moving_sphere, triangle, \
+                         quad, xy_rect, xz_rect, yz_rect, box, plane or mesh"
+                    ),
+                    fields.table.get("type").unwrap().position,
+                )
+            }
+        }
+
+        let start = match fields.get("transform") {
+            Some(item) => Some(transform(Fields::of(item)?, TransformComponents::new())?),
+            None => None,
+        };
+        if let Some(item) = fields.get("transform_end") {
+            let start = start.unwrap_or_default();
+            let end = transform(Fields::of(item)?, start)?;
+            let (time0, time1) = fields.range("time")?.unwrap_or((0f64, 1f64));
+            let Some(animation) = AnimatedTransform::from(start, end, time0, time1) else {
+                return error(
+                    "the scale must not change sign between 'transform' and 'transform_end'",
+                    item.position,
+                );
+            };
+            objects = objects
+                .into_iter()
+                .map(|object| Arc::new(Instance::animated(object, animation)) as Arc<dyn Hittable>)
+                .collect();
+        } else if let Some(start) = start {
+            let transform = start.to_transform().unwrap();
+            objects = objects
+                .into_iter()
+                .map(|object| Arc::new(Instance::from(object, transform)) as Arc<dyn Hittable>)
+                .collect();
+        }
+        fields.finish()?;
+        Ok(objects)
+    }
+}
+
+/// Reads `{ scale, rotate, translate }`, applied in that order, with the parts left out taken
+/// from `base`. `scale` is a factor or one per axis, and `rotate` holds angles in degrees around
+/// the x, y and z axes, applied in that order.
+fn transform(
+    mut fields: Fields,
+    base: TransformComponents,
+) -> Result<TransformComponents, SceneError> {
+    let mut components = base;
+    if let Some(item) = fields.get("scale") {
+        let factors = match number(item) {
+            Some(factor) => Some(Vec3::from(factor, factor, factor)),
+            None => vector(item),
+        };
+        match factors {
+            Some(factors) if (0..3).all(|axis| factors[axis] != 0f64) => components.scale = factors,
+            _ => return expected("scale", "a non zero factor or three of them", item),
+        }
+    }
+    if let Some(angles) = fields.vector("rotate")? {
+        components.rotate = angles;
+    }
+    if let Some(offset) = fields.vector("translate")? {
+        components.translate = offset;
+    }
+    fields.finish()?;
+    Ok(components)
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::ray::*;
+
+    #[test]
+    fn loads_scene_files() {
+        let scene = Scene::parse(include_str!("../scenes/three-spheres.toml")).unwrap();
+        assert_eq!(4, scene.world.len());
+        assert_eq!(CameraSettings::default(), scene.camera);
+        assert_eq!(Some(16f64 / 9f64), scene.render.aspect_ratio);
+        assert_eq!(Some(100), scene.render.samples_per_pixel);
+
+        // The Cornell box file matches the built-in scene, lit by its light alone.
+        let scene = Scene::parse(include_str!("../scenes/cornell-box.toml")).unwrap();
+        assert_eq!(Scene::cornell_box().world.len(), scene.world.len());
+        assert_eq!(Scene::cornell_box().camera, scene.camera);
+        let up = Ray::from(Point3::new(), Vec3::from(0f64, 1f64, 0f64));
+        assert_eq!(Color::new(), scene.background.value(&up));
+        let r = Ray::from(
+            Point3::from(278f64, 278f64, 278f64),
+            Vec3::from(0f64, 1f64, 0f64),
+        );
+        let rec = scene.world.hit(&r, 0.001, f64::INFINITY).unwrap();
+        assert_eq!(
+            Color::from(15f64, 15f64, 15f64),
+            rec.material.emitted(rec.u, rec.v, &rec.p)
+        );
+        assert!(rec.material.scatter(&r, &rec).is_none());
+
+        let scene = Scene::parse(
+            "[render]\ntone_map = \"exposure\"\nexposure = 2\n\
+             exr_type = \"float\"\nexr_compression = \"none\"\naovs = false\n\
+             threads = 2\ntile_size = 32\nseed = 5\n\
+             [camera]\nlookfrom = [13, 2, 3]\nfocus_dist = 10\n\
+             [[objects]]\ntype = \"sphere\"\ncenter = [0, 0, 0]\nradius = 1\n\
+             material = { type = \"metal\", albedo = [1, 1, 1] }\n",
+        )
+        .unwrap();
+        assert_eq!(1, scene.world.len());
+        assert_eq!(Point3::from(13f64, 2f64, 3f64), scene.camera.lookfrom);
+        assert_eq!(Some(10f64), scene.camera.focus_dist);
+        assert_eq!(Some(ToneMap::Exposure(2f64)), scene.render.tone_map);
+        assert_eq!(Some(ExrPixelType::Float), scene.render.exr_pixel_type);
+        assert_eq!(Some(ExrCompression::None), scene.render.exr_compression);
+        assert_eq!(Some(false), scene.render.aovs);
+        assert_eq!(Some(2), scene.render.threads);
+        assert_eq!(Some(32), scene.render.tile_size);
+        assert_eq!(Some(5), scene.render.seed);
+
+        let scene = Scene::parse(
+            "[materials.white]\ntype = \"lambertian\"\nalbedo = [0.73, 0.73, 0.73]\n\
+             [[objects]]\ntype = \"xz_rect\"\nx = [0, 555]\nz = [0, 555]\ny = 0\n\
+             material = \"white\"\n\
+             [[objects]]\ntype = \"quad\"\ncorner = [0, 0, 0]\nu = [1, 0, 0]\n\
+             v = [0, 1, 0]\nmaterial = \"white\"\n\
+             [[objects]]\ntype = \"box\"\nmin = [0, 0, 0]\nmax = [1, 1, 1]\n\
+             material = \"white\"\n\
+             [[objects]]\ntype = \"plane\"\npoint = [0, 0, 0]\nnormal = [0, 1, 0]\n\
+             material = \"white\"\n",
+        )
+        .unwrap();
+        assert_eq!(4, scene.world.len());
+    }
+
+    #[test]
+    fn transforms_objects() {
+        let dir = std::env::temp_dir().join("ray_tracing_scene_test");
+        std::fs::create_dir_all(&dir).unwrap();
+        std::fs::write(
+            dir.join("triangle.obj"),
+            "v 0 0 0\nv 1 0 0\nv 0 1 0\nf 1 2 3\n",
+        )
+        .unwrap();
+        std::fs::write(
+            dir.join("scene.toml"),
+            "[[objects]]\ntype = \"mesh\"\nfile = \"triangle.obj\"\n\
+             [[objects]]\ntype = \"mesh\"\nfile = \"triangle.obj\"\n\
+             transform = { scale = [2, 2, 1], rotate = [0, 0, 90], translate = [0, 0, -5] }\n",
+        )
+        .unwrap();
+        let scene = Scene::load(dir.join("scene.toml")).unwrap();
+        assert_eq!(2, scene.world.len());
+
+        // The copy is twice as large, turned a quarter around z and moved back.
+        let r = Ray::from(
+            Point3::from(-0.5, 1f64, 1f64),
+            Vec3::from(0f64, 0f64, -1f64),
+        );
+        let rec = scene.world.hit(&r, 0.001, f64::INFINITY).unwrap();
+        assert!((rec.t - 6f64).abs() < 1e-9);
+        let r = Ray::from(Point3::from(0.5, 0.25, 1f64), Vec3::from(0f64, 0f64, -1f64));
+        assert_eq!(1f64, scene.world.hit(&r, 0.001, f64::INFINITY).unwrap().t);
+    }
+
+    #[test]
+    fn loads_textures() {
+        let dir = std::env::temp_dir().join("ray_tracing_texture_test");
+        std::fs::create_dir_all(&dir).unwrap();
+        // A single pixel image, decoded from sRGB.
+        std::fs::write(dir.join("red.ppm"), b"P3\n1 1\n255\n255 0 0\n").unwrap();
+        std::fs::write(
+            dir.join("scene.toml"),
+            "[textures.white]\ntype = \"solid\"\ncolor = [1, 1, 1]\n\
+             [background]\ntype = \"environment\"\ntexture = \"white\"\n\
+             [textures.checker]\ntype = \"checker\"\nscale = 2\neven = \"white\"\n\
+             odd = { type = \"image\", file = \"red.ppm\", lod = 1 }\n\
+             [materials.floor]\ntype = \"lambertian\"\nalbedo = \"checker\"\n\
+             [[objects]]\ntype = \"plane\"\npoint = [0, 0, 0]\nnormal = [0, 1, 0]\n\
+             material = \"floor\"\n\
+             [[objects]]\ntype = \"sphere\"\ncenter = [0, 0, -10]\nradius = 1\n\
+             material = { type = \"lambertian\", albedo = { type = \"noise\", \
+             noise = \"simplex\", style = \"marble\", scale = 4 } }\n",
+        )
+        .unwrap();
+        let scene = Scene::load(dir.join("scene.toml")).unwrap();
+        assert_eq!(2, scene.world.len());
+
+        let albedo = |x: f64, z: f64| {
+            let r = Ray::from(Point3::from(x, 1f64, z), Vec3::from(0f64, -1f64, 0f64));
+            let rec = scene.world.hit(&r, 0.001, f64::INFINITY).unwrap();
+            rec.material.scatter(&r, &rec).unwrap().0
+        };
+        assert_eq!(Color::from(1f64, 1f64, 1f64), albedo(1f64, 1f64));
+        assert_eq!(Color::from(1f64, 0f64, 0f64), albedo(3f64, 1f64));
+        let up = Ray::from(Point3::new(), Vec3::from(0f64, 1f64, 0f64));
+        assert_eq!(Color::from(1f64, 1f64, 1f64), scene.background.value(&up));
+    }
+
+    #[test]
+    fn animates_objects() {
+        let scene = Scene::parse(
+            "[camera]\nshutter = [0, 1]\n\
+             [materials.white]\ntype = \"lambertian\"\nalbedo = [0.73, 0.73, 0.73]\n\
+             [[objects]]\ntype = \"moving_sphere\"\ncenter = [0, 0, -5]\n\
+             center_end = [0, 4, -5]\nradius = 1\nmaterial = \"white\"\n\
+             [[objects]]\ntype = \"box\"\nmin = [-1, -1, -1]\nmax = [1, 1, 1]\n\
+             material = \"white\"\ntime = [0, 2]\n\
+             transform = { translate = [10, 0, -5] }\n\
+             transform_end = { translate = [10, 8, -5], rotate = [0, 45, 0] }\n",
+        )
+        .unwrap();
+        assert_eq!((0f64, 1f64), scene.camera.shutter);
+
+        let direction = Vec3::from(0f64, 0f64, -1f64);
+        let hit = |origin: Point3, time: f64| {
+            scene
+                .world
+                .hit(
+                    &Ray::from_time(origin, direction, time),
+                    0.001,
+                    f64::INFINITY,
+                )
+                .map(|rec| rec.t)
+        };
+        assert_eq!(Some(4f64), hit(Point3::from(0f64, 0f64, 0f64), 0f64));
+        assert_eq!(None, hit(Point3::from(0f64, 0f64, 0f64), 1f64));
+        assert_eq!(Some(4f64), hit(Point3::from(0f64, 4f64, 0f64), 1f64));
+
+        // Halfway through its time the box is 4 up and turned 22.5 degrees, then stays at the end.
+        let t = hit(Point3::from(10f64, 4f64, 0f64), 1f64).unwrap();
+        assert!((t - (5f64 - 1f64 / 22.5f64.to_radians().cos())).abs() < 1e-9);
+        assert_eq!(None, hit(Point3::from(10f64, 0f64, 0f64), 3f64));
+        assert!(hit(Point3::from(10f64, 8f64, 0f64), 3f64).is_some());
+    }
+
+    #[test]
+    fn reports_errors() {
+        let error = |text: &str| Scene::parse(text).err().unwrap().to_string();
+        let sphere = "[[objects]]\ntype = \"sphere\"\ncenter = [0, 0, 0]\n";
+        assert_eq!(
+            "1:1: missing key 'radius'",
+            error(&format!("{sphere}material = \"glass\""))
+        );
+        assert_eq!(
+            "4:12: unknown material 'glass'",
+            error(&format!("{sphere}material = \"glass\"\nradius = 1"))
+        );
+        assert_eq!(
+            "6:8: unknown key 'fuzz'",
+            error(&format!(
+                "{sphere}material = {{ type = \"dielectric\", ir = 1.5 }}\nradius = 1\nfuzz = 0"
+            ))
+        );
+        assert_eq!(
+            "2:8: unknown object type 'cube', expected sphere, moving_sphere, triangle, quad, \
+             xy_rect, xz_rect, yz_rect, box, plane or mesh",
+            error("[[objects]]\ntype = \"cube\"")
+        );
+        assert_eq!(
+            "3:8: 'vfov' must be an angle between 0 and 180 degrees, not 270",
+            error("\n[camera]\nvfov = 270")
+        );
+        assert_eq!(
+            "2:12: 'aperture' must be a non negative number, not -0.5",
+            error("[camera]\naperture = -0.5")
+        );
+        assert_eq!(
+            "3:8: 'fuzz' must be a number between 0 and 1, not 1.5",
+            error("[materials.steel]\nalbedo = [1, 1, 1]\nfuzz = 1.5\ntype = \"metal\"")
+        );
+        assert_eq!(
+            "2:10: 'albedo' must be an array of three numbers, not \"red\"",
+            error("[materials.red]\nalbedo = \"red\"\ntype = \"metal\"")
+        );
+        assert_eq!(
+            "2:10: unknown texture 'red'",
+            error("[materials.red]\nalbedo = \"red\"\ntype = \"lambertian\"")
+        );
+        assert_eq!(
+            "2:9: 'style' must be one of smooth, turbulence, marble, not \"wood\"",
+            error("[textures.wood]\nstyle = \"wood\"\ntype = \"noise\"")
+        );
+        assert_eq!(
+            "2:8: 'seed' must be a non negative integer, not -1",
+            error("[textures.clouds]\nseed = -1\ntype = \"noise\"")
+        );
+        assert_eq!(
+            "1:1: unknown section 'light', expected render, camera, background, textures, \
+             materials or objects",
+            error("[light]")
+        );
+        assert_eq!(
+            "2:8: unknown background type 'sky', expected solid, gradient or environment",
+            error("[background]\ntype = \"sky\"")
+        );
+        assert_eq!("1:5: expected a value, found '='", error("a = = 1"));
+        assert_eq!(
+            "2:12: 'rr_depth' must be a non negative integer, not -1",
+            error("[render]\nrr_depth = -1")
+        );
+        assert_eq!(
+            "2:8: 'seed' must be a non negative integer, not -7",
+            error("[render]\nseed = -7")
+        );
+        assert_eq!(
+            "3:5: 'x' must be an interval [min, max] with min < max, not an array",
+            error("[[objects]]\ntype = \"xy_rect\"\nx = [1, 0]")
+        );
+        assert_eq!(
+            "4:7: 'max' must be a corner above 'min' on every axis, not an array",
+            error("[[objects]]\ntype = \"box\"\nmin = [0, 0, 0]\nmax = [1, 0, 1]")
+        );
+        assert_eq!(
+            "6:17: the scale must not change sign between 'transform' and 'transform_end'",
+            error(&format!(
+                "{sphere}radius = 1\nmaterial = {{ type = \"dielectric\", ir = 1.5 }}\n\
+                 transform_end = {{ scale = [-1, 1, 1] }}"
+            ))
+        );
+    }
+}
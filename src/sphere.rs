@@ -0,0 +1,157 @@
+use std::f64::consts::PI;
+use std::sync::Arc;
+
+use crate::aabb::*;
+use crate::hittable::*;
+use crate::material::*;
+use crate::ray::*;
+use crate::vec3::*;
+
+pub struct Sphere {
+    center: Point3,
+    radius: f64,
+    material: Arc<dyn Material>,
+}
+
+impl Sphere {
+    pub fn from(center: Point3, radius: f64, material: Arc<dyn Material>) -> Sphere {
+        Sphere {
+            center,
+            radius,
+            material,
+        }
+    }
+
+    pub fn center(&self) -> Point3 {
+        self.center
+    }
+
+    pub fn radius(&self) -> f64 {
+        self.radius
+    }
+
+    /// Maps a point on the unit sphere to `(u, v)` in `[0, 1]`, with `u` going around the y axis
+    /// starting from -x and `v` going from the bottom pole to the top pole.
+    pub fn get_sphere_uv(p: &Point3) -> (f64, f64) {
+        let theta = (-p.y()).acos();
+        let phi = (-p.z()).atan2(*p.x()) + PI;
+        (phi / (2f64 * PI), theta / PI)
+    }
+}
+
+impl Hittable for Sphere {
+    fn hit(&self, r: &Ray, t_min: f64, t_max: f64) -> Option<HitRecord<'_>> {
+        hit_sphere(
+            self.center,
+            self.radius,
+            self.material.as_ref(),
+            r,
+            t_min,
+            t_max,
+        )
+    }
+
+    fn bounding_box(&self) -> Option<Aabb> {
+        let r = Vec3::from(self.radius, self.radius, self.radius);
+        Some(Aabb::from(self.center - r, self.center + r))
+    }
+}
+
+/// Intersects the sphere at `center`, shared by the still and moving spheres.
+pub(crate) fn hit_sphere<'a>(
+    center: Point3,
+    radius: f64,
+    material: &'a dyn Material,
+    r: &Ray,
+    t_min: f64,
+    t_max: f64,
+) -> Option<HitRecord<'a>> {
+    let oc: Vec3 = r.origin() - center;
+    let a = r.direction().length_squared();
+    let half_b = Vec3::dot(&oc, &r.direction());
+    let c = oc.length_squared() - radius * radius;
+
+    let discriminant = half_b * half_b - a * c;
+    if discriminant < 0f64 {
+        return None;
+    }
+    let sqrtd = discriminant.sqrt();
+
+    // Find the nearest root that lies in the acceptable range.
+    let mut root = (-half_b - sqrtd) / a;
+    if root <= t_min || t_max <= root {
+        root = (-half_b + sqrtd) / a;
+        if root <= t_min || t_max <= root {
+            return None;
+        }
+    }
+
+    let outward_normal = (r.at(root) - center) / radius;
+    let (u, v) = Sphere::get_sphere_uv(&outward_normal);
+    Some(HitRecord::from(r, root, outward_normal, u, v, material))
+}
+
+#[cfg(test)]
+mod test {
+    use std::sync::Arc;
+
+    use super::Sphere;
+    use crate::hittable::Hittable;
+    use crate::material::Lambertian;
+    use crate::ray::Ray;
+    use crate::vec3::*;
+
+    #[test]
+    fn hit_from_outside_and_inside() {
+        let material = Arc::new(Lambertian::from(Color::from(0.5, 0.5, 0.5)));
+        let sphere = Sphere::from(Point3::from(0f64, 0f64, -1f64), 0.5, material);
+
+        let r = Ray::from(Point3::new(), Vec3::from(0f64, 0f64, -1f64));
+        let rec = sphere.hit(&r, 0f64, f64::INFINITY).unwrap();
+        assert_eq!(0.5, rec.t);
+        assert_eq!(Point3::from(0f64, 0f64, -0.5), rec.p);
+        assert_eq!(Vec3::from(0f64, 0f64, 1f64), rec.normal);
+        assert!(rec.front_face);
+
+        // Starting at the center the ray leaves through the back, seeing the inside of the surface.
+        let r = Ray::from(sphere.center(), Vec3::from(0f64, 0f64, -1f64));
+        let rec = sphere.hit(&r, 0f64, f64::INFINITY).unwrap();
+        assert_eq!(0.5, rec.t);
+        assert_eq!(Vec3::from(0f64, 0f64, 1f64), rec.normal);
+        assert!(!rec.front_face);
+    }
+
+    #[test]
+    fn respects_interval() {
+        let material = Arc::new(Lambertian::from(Color::from(0.5, 0.5, 0.5)));
+        let sphere = Sphere::from(Point3::from(0f64, 0f64, -1f64), 0.5, material);
+        let r = Ray::from(Point3::new(), Vec3::from(0f64, 0f64, -1f64));
+
+        assert!(sphere.hit(&r, 0f64, 0.4).is_none());
+        assert_eq!(1.5, sphere.hit(&r, 0.6, f64::INFINITY).unwrap().t);
+        assert!(sphere.hit(&r, 1.6, f64::INFINITY).is_none());
+
+        let miss = Ray::from(Point3::new(), Vec3::from(0f64, 1f64, 0f64));
+        assert!(sphere.hit(&miss, 0f64, f64::INFINITY).is_none());
+    }
+
+    #[test]
+    fn uv_mapping() {
+        assert_eq!(
+            (0.5, 0.5),
+            Sphere::get_sphere_uv(&Point3::from(1f64, 0f64, 0f64))
+        );
+        assert_eq!(
+            (0.5, 1f64),
+            Sphere::get_sphere_uv(&Point3::from(0f64, 1f64, 0f64))
+        );
+        assert_eq!(
+            (0.5, 0f64),
+            Sphere::get_sphere_uv(&Point3::from(0f64, -1f64, 0f64))
+        );
+        assert_eq!(
+            (0.25, 0.5),
+            Sphere::get_sphere_uv(&Point3::from(0f64, 0f64, 1f64))
+        );
+    }
+}
@@ -0,0 +1,368 @@
+use std::path::Path;
+use std::sync::Arc;
+
+use crate::color::*;
+use crate::image::*;
+use crate::noise::*;
+use crate::vec3::*;
+
+/// A color varying over a surface, looked up by the surface coordinates `(u, v)` and the point
+/// `p` that was hit.
+pub trait Texture: Send + Sync {
+    fn value(&self, u: f64, v: f64, p: &Point3) -> Color;
+}
+
+/// The same color everywhere.
+pub struct SolidColor {
+    color: Color,
+}
+
+impl SolidColor {
+    pub fn from(color: Color) -> SolidColor {
+        SolidColor { color }
+    }
+}
+
+impl Texture for SolidColor {
+    fn value(&self, _u: f64, _v: f64, _p: &Point3) -> Color {
+        self.color
+    }
+}
+
+/// A solid checker pattern of cubes `scale` wide, alternating between two textures in space. It
+/// follows the geometry rather than the surface coordinates, so it needs no `(u, v)` mapping.
+pub struct CheckerTexture {
+    inv_scale: f64,
+    even: Arc<dyn Texture>,
+    odd: Arc<dyn Texture>,
+}
+
+impl CheckerTexture {
+    pub fn from(scale: f64, even: Arc<dyn Texture>, odd: Arc<dyn Texture>) -> CheckerTexture {
+        CheckerTexture {
+            inv_scale: 1f64 / scale,
+            even,
+            odd,
+        }
+    }
+}
+
+impl Texture for CheckerTexture {
+    fn value(&self, u: f64, v: f64, p: &Point3) -> Color {
+        let x = (self.inv_scale * p.x()).floor() as i64;
+        let y = (self.inv_scale * p.y()).floor() as i64;
+        let z = (self.inv_scale * p.z()).floor() as i64;
+        if (x + y + z).rem_euclid(2) == 0 {
+            self.even.value(u, v, p)
+        } else {
+            self.odd.value(u, v, p)
+        }
+    }
+}
+
+/// A checker pattern in surface coordinates, with `width` squares across `u` and `height`
+/// squares across `v`.
+pub struct UvCheckerTexture {
+    width: f64,
+    height: f64,
+    even: Arc<dyn Texture>,
+    odd: Arc<dyn Texture>,
+}
+
+impl UvCheckerTexture {
+    pub fn from(
+        width: u32,
+        height: u32,
+        even: Arc<dyn Texture>,
+        odd: Arc<dyn Texture>,
+    ) -> UvCheckerTexture {
+        UvCheckerTexture {
+            width: width as f64,
+            height: height as f64,
+            even,
+            odd,
+        }
+    }
+}
+
+impl Texture for UvCheckerTexture {
+    fn value(&self, u: f64, v: f64, p: &Point3) -> Color {
+        let x = (u * self.width).floor() as i64;
+        let y = (v * self.height).floor() as i64;
+        if (x + y).rem_euclid(2) == 0 {
+            self.even.value(u, v, p)
+        } else {
+            self.odd.value(u, v, p)
+        }
+    }
+}
+
+/// An image wrapped around the surface, repeating outside `[0, 1]`. `v` goes up from the bottom
+/// row of the image.
+///
+/// The renderer does not track how much of the texture a pixel covers, so the mipmap level to
+/// read is fixed per texture with [`ImageTexture::with_lod`]. Level 0, the default, is the full
+/// resolution image; each further level halves it, which blurs textures seen from far away.
+pub struct ImageTexture {
+    levels: Vec<Image>,
+    lod: f64,
+}
+
+impl ImageTexture {
+    /// # Panics
+    ///
+    /// If the image is empty.
+    pub fn from(image: Image) -> ImageTexture {
+        assert!(image.width() > 0 && image.height() > 0);
+        ImageTexture {
+            levels: vec![image],
+            lod: 0f64,
+        }
+    }
+
+    /// Reads mipmap level `lod`, building the mipmaps down to it. Fractional levels blend the two
+    /// closest ones, and levels past the last 1x1 mipmap read that one.
+    pub fn with_lod(mut self, lod: f64) -> ImageTexture {
+        self.lod = lod.max(0f64);
+        while (self.levels.len() as f64) <= self.lod.ceil() {
+            let last = self.levels.last().unwrap();
+            if last.width() == 1 && last.height() == 1 {
+                break;
+            }
+            let next = downsample(last);
+            self.levels.push(next);
+        }
+        self
+    }
+
+    /// Reads an image file, decoding 8 bit formats as sRGB.
+    pub fn load<P: AsRef<Path>>(path: P) -> std::io::Result<ImageTexture> {
+        let image = Image::load(path, Transfer::Srgb)?;
+        if image.width() == 0 || image.height() == 0 {
+            return Err(invalid_data("the image is empty"));
+        }
+        Ok(ImageTexture::from(image))
+    }
+
+    /// The mipmaps built so far, starting with the full resolution image.
+    pub fn levels(&self) -> &[Image] {
+        &self.levels
+    }
+
+    /// Looks the color up in mipmap `level`, where 0 is the full resolution image and each
+    /// further level halves it. Fractional levels blend the two closest ones, and levels that
+    /// were not built read the smallest one there is.
+    pub fn sample(&self, u: f64, v: f64, level: f64) -> Color {
+        let last = (self.levels.len() - 1) as f64;
+        let level = level.clamp(0f64, last);
+        let lower = level.floor();
+        let fine = self.bilinear(lower as usize, u, v);
+        if level == lower {
+            return fine;
+        }
+        let coarse = self.bilinear(lower as usize + 1, u, v);
+        let s = level - lower;
+        (1f64 - s) * fine + s * coarse
+    }
+
+    /// Interpolates between the four texels around `(u, v)`.
+    fn bilinear(&self, level: usize, u: f64, v: f64) -> Color {
+        let image = &self.levels[level];
+        let (width, height) = (image.width() as i64, image.height() as i64);
+        // Texel centers sit at half integer coordinates.
+        let x = u * width as f64 - 0.5;
+        let y = (1f64 - v) * height as f64 - 0.5;
+        let (x0, y0) = (x.floor(), y.floor());
+        let (fx, fy) = (x - x0, y - y0);
+        let texel = |x: f64, y: f64| {
+            let x = (x as i64).rem_euclid(width) as u32;
+            let y = (y as i64).rem_euclid(height) as u32;
+            image.get_pixel(x, y)
+        };
+        let top = (1f64 - fx) * texel(x0, y0) + fx * texel(x0 + 1f64, y0);
+        let bottom = (1f64 - fx) * texel(x0, y0 + 1f64) + fx * texel(x0 + 1f64, y0 + 1f64);
+        (1f64 - fy) * top + fy * bottom
+    }
+}
+
+impl Texture for ImageTexture {
+    fn value(&self, u: f64, v: f64, _p: &Point3) -> Color {
+        if !(u.is_finite() && v.is_finite()) {
+            return Color::new();
+        }
+        self.sample(u, v, self.lod)
+    }
+}
+
+/// Halves both sides of `image`, rounding up, by averaging blocks of 2x2 pixels.
+fn downsample(image: &Image) -> Image {
+    let width = image.width().div_ceil(2);
+    let height = image.height().div_ceil(2);
+    let mut result = Image::new(width, height);
+    for y in 0..height {
+        for x in 0..width {
+            let mut sum = Color::new();
+            for (dx, dy) in [(0, 0), (1, 0), (0, 1), (1, 1)] {
+                let sx = (2 * x + dx).min(image.width() - 1);
+                let sy = (2 * y + dy).min(image.height() - 1);
+                sum += image.get_pixel(sx, sy);
+            }
+            result.set_pixel(x, y, sum / 4f64);
+        }
+    }
+    result
+}
+
+/// How [`NoiseTexture`] turns noise into a color.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum NoiseStyle {
+    /// The noise itself, mapped from its range to `[0, 1]`.
+    Smooth,
+    /// Several octaves of noise, giving a cloudy look. Bright spots are clamped to `1`.
+    Turbulence,
+    /// Stripes along z disturbed by turbulence, looking like veined marble.
+    Marble,
+}
+
+/// Gray patterns made of noise, with features about `1 / scale` wide.
+pub struct NoiseTexture {
+    noise: Arc<dyn Noise>,
+    scale: f64,
+    style: NoiseStyle,
+}
+
+impl NoiseTexture {
+    /// Octaves summed by the turbulent styles.
+    const DEPTH: u32 = 7;
+
+    pub fn from(noise: Arc<dyn Noise>, scale: f64, style: NoiseStyle) -> NoiseTexture {
+        NoiseTexture {
+            noise,
+            scale,
+            style,
+        }
+    }
+}
+
+impl Texture for NoiseTexture {
+    fn value(&self, _u: f64, _v: f64, p: &Point3) -> Color {
+        let p = self.scale * *p;
+        let (low, high) = self.noise.range();
+        // Octaves seldom line up, so turbulence scaled by the largest noise value rarely passes 1.
+        let turbulence =
+            || turbulence(self.noise.as_ref(), &p, NoiseTexture::DEPTH) / low.abs().max(high.abs());
+        let gray = match self.style {
+            NoiseStyle::Smooth => (self.noise.noise(&p) - low) / (high - low),
+            NoiseStyle::Turbulence => turbulence().min(1f64),
+            NoiseStyle::Marble => 0.5 * (1f64 + (p.z() + 10f64 * turbulence()).sin()),
+        };
+        Color::from(gray, gray, gray)
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    fn solid(r: f64, g: f64, b: f64) -> Arc<dyn Texture> {
+        Arc::new(SolidColor::from(Color::from(r, g, b)))
+    }
+
+    #[test]
+    fn checker_patterns() {
+        let white = Color::from(1f64, 1f64, 1f64);
+        let checker = CheckerTexture::from(0.5, solid(1f64, 1f64, 1f64), solid(0f64, 0f64, 0f64));
+        let p = |x, y, z| Point3::from(x, y, z);
+        assert_eq!(white, checker.value(0f64, 0f64, &p(0.1, 0.1, 0.1)));
+        assert_eq!(Color::new(), checker.value(0f64, 0f64, &p(0.6, 0.1, 0.1)));
+        assert_eq!(Color::new(), checker.value(0f64, 0f64, &p(-0.1, 0.1, 0.1)));
+        assert_eq!(white, checker.value(0f64, 0f64, &p(-0.1, -0.1, 0.1)));
+
+        let checker =
+            UvCheckerTexture::from(4, 2, solid(1f64, 1f64, 1f64), solid(0f64, 0f64, 0f64));
+        assert_eq!(white, checker.value(0.1, 0.1, &Point3::new()));
+        assert_eq!(Color::new(), checker.value(0.3, 0.1, &Point3::new()));
+        assert_eq!(Color::new(), checker.value(0.1, 0.6, &Point3::new()));
+        assert_eq!(white, checker.value(0.3, 0.6, &Point3::new()));
+    }
+
+    #[test]
+    fn image_lookup() {
+        // Black on the left, white on the right, red in the bottom right corner.
+        let mut image = Image::new(4, 2);
+        for y in 0..2 {
+            for x in 2..4 {
+                image.set_pixel(x, y, Color::from(1f64, 1f64, 1f64));
+            }
+        }
+        image.set_pixel(3, 1, Color::from(1f64, 0f64, 0f64));
+        let texture = ImageTexture::from(image.clone());
+        assert_eq!(1, texture.levels().len());
+        let p = Point3::new();
+        assert_eq!(Color::new(), texture.value(0.125, 0.75, &p));
+        assert_eq!(
+            Color::from(1f64, 0f64, 0f64),
+            texture.value(0.875, 0.25, &p)
+        );
+        // Halfway between texel centers, and wrapping around the left edge.
+        assert_eq!(Color::from(0.5, 0.5, 0.5), texture.value(0.5, 0.75, &p));
+        assert_eq!(Color::from(0.5, 0.5, 0.5), texture.value(1f64, 0.75, &p));
+
+        // Mipmaps are built down to the level read, and stop at 1x1.
+        let texture = ImageTexture::from(image.clone()).with_lod(5f64);
+        let sizes: Vec<(u32, u32)> = texture
+            .levels()
+            .iter()
+            .map(|level| (level.width(), level.height()))
+            .collect();
+        assert_eq!(vec![(4, 2), (2, 1), (1, 1)], sizes);
+        let average = Color::from(0.5, 0.375, 0.375);
+        assert_eq!(average, texture.levels()[2].get_pixel(0, 0));
+        assert_eq!(average, texture.value(0.3, 0.3, &p));
+
+        let texture = ImageTexture::from(image).with_lod(0.5);
+        assert_eq!(2, texture.levels().len());
+        let blend =
+            0.5 * texture.sample(0.125, 0.75, 0f64) + 0.5 * texture.sample(0.125, 0.75, 1f64);
+        assert_eq!(blend, texture.value(0.125, 0.75, &p));
+    }
+
+    #[test]
+    fn noise_styles() {
+        let noise: Arc<dyn Noise> = Arc::new(Perlin::from(1));
+        let p = Point3::from(0.3, 0.2, 0.1);
+        let smooth = NoiseTexture::from(noise.clone(), 4f64, NoiseStyle::Smooth);
+        let expected = 0.5 * (1f64 + noise.noise(&(4f64 * p)));
+        assert_eq!(
+            Color::from(expected, expected, expected),
+            smooth.value(0f64, 0f64, &p)
+        );
+
+        let marble = NoiseTexture::from(noise, 4f64, NoiseStyle::Marble);
+        let color = marble.value(0f64, 0f64, &p);
+        assert!((0f64..=1f64).contains(color.x()));
+        assert_eq!(color.x(), color.z());
+
+        // Every noise and style stays a valid gray.
+        let noises: [Arc<dyn Noise>; 3] = [
+            Arc::new(Perlin::from(1)),
+            Arc::new(Simplex::from(1)),
+            Arc::new(Worley::from(1)),
+        ];
+        let styles = [
+            NoiseStyle::Smooth,
+            NoiseStyle::Turbulence,
+            NoiseStyle::Marble,
+        ];
+        for noise in noises {
+            for style in styles {
+                let texture = NoiseTexture::from(noise.clone(), 1f64, style);
+                for i in 0..500 {
+                    let p = Point3::from(i as f64 * 0.37, i as f64 * 0.11, -(i as f64) * 0.23);
+                    let gray = *texture.value(0f64, 0f64, &p).x();
+                    assert!((0f64..=1f64).contains(&gray), "{style:?}: {gray}");
+                }
+            }
+        }
+    }
+}
@@ -0,0 +1,624 @@
+//! A parser for the subset of TOML used by scene files: comments, `[tables]`,
+//! `[[arrays.of.tables]]`, dotted and quoted keys, strings, integers, floats, booleans, arrays
+//! and inline tables. Dates and multi-line strings are not supported.
+
+use std::fmt::Display;
+
+/// A 1-based line and column in the source text.
+#[derive(Debug, Clone, Copy, PartialEq, Default)]
+pub struct Position {
+    pub line: usize,
+    pub column: usize,
+}
+
+impl Display for Position {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{}:{}", self.line, self.column)
+    }
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum Value {
+    String(String),
+    Integer(i64),
+    Float(f64),
+    Boolean(bool),
+    Array(Vec<Item>),
+    Table(Table),
+}
+
+impl Value {
+    /// Describes the kind of value, for error messages.
+    pub fn type_name(&self) -> &'static str {
+        match self {
+            Value::String(_) => "a string",
+            Value::Integer(_) => "an integer",
+            Value::Float(_) => "a float",
+            Value::Boolean(_) => "a boolean",
+            Value::Array(_) => "an array",
+            Value::Table(_) => "a table",
+        }
+    }
+}
+
+/// A value and where it was defined.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Item {
+    pub value: Value,
+    pub position: Position,
+}
+
+/// Key value pairs in definition order.
+#[derive(Debug, Clone, PartialEq, Default)]
+pub struct Table {
+    entries: Vec<(String, Item)>,
+}
+
+impl Table {
+    pub fn get(&self, key: &str) -> Option<&Item> {
+        self.entries.iter().find(|(k, _)| k == key).map(|(_, v)| v)
+    }
+
+    fn get_mut(&mut self, key: &str) -> Option<&mut Item> {
+        self.entries
+            .iter_mut()
+            .find(|(k, _)| k == key)
+            .map(|(_, v)| v)
+    }
+
+    pub fn iter(&self) -> impl Iterator<Item = (&str, &Item)> {
+        self.entries.iter().map(|(k, v)| (k.as_str(), v))
+    }
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct ParseError {
+    pub message: String,
+    pub position: Position,
+}
+
+impl Display for ParseError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{}: {}", self.position, self.message)
+    }
+}
+
+impl std::error::Error for ParseError {}
+
+type Key = Vec<(String, Position)>;
+
+struct Parser<'a> {
+    chars: std::iter::Peekable<std::str::Chars<'a>>,
+    position: Position,
+    /// Arrays and inline tables open around the value being parsed.
+    depth: usize,
+}
+
+/// The deepest arrays and inline tables may nest, so a hostile file cannot overflow the stack.
+const MAX_DEPTH: usize = 128;
+
+impl Parser<'_> {
+    fn peek(&mut self) -> Option<char> {
+        self.chars.peek().copied()
+    }
+
+    fn bump(&mut self) -> Option<char> {
+        let c = self.chars.next()?;
+        if c == '\n' {
+            self.position.line += 1;
+            self.position.column = 1;
+        } else {
+            self.position.column += 1;
+        }
+        Some(c)
+    }
+
+    fn error<T>(&self, message: impl Into<String>) -> Result<T, ParseError> {
+        Err(ParseError {
+            message: message.into(),
+            position: self.position,
+        })
+    }
+
+    fn expect(&mut self, expected: char) -> Result<(), ParseError> {
+        match self.peek() {
+            Some(c) if c == expected => {
+                self.bump();
+                Ok(())
+            }
+            Some(c) => self.error(format!("expected '{expected}', found '{c}'")),
+            None => self.error(format!("expected '{expected}', found the end of the file")),
+        }
+    }
+
+    /// Skips spaces and tabs, but not newlines.
+    fn skip_whitespace(&mut self) {
+        while matches!(self.peek(), Some(' ' | '\t')) {
+            self.bump();
+        }
+    }
+
+    fn skip_comment(&mut self) {
+        if self.peek() == Some('#') {
+            while !matches!(self.peek(), Some('\n') | None) {
+                self.bump();
+            }
+        }
+    }
+
+    /// Skips whitespace, comments and newlines.
+    fn skip_blank(&mut self) {
+        loop {
+            self.skip_whitespace();
+            self.skip_comment();
+            match self.peek() {
+                Some('\n' | '\r') => {
+                    self.bump();
+                }
+                _ => return,
+            }
+        }
+    }
+
+    /// Expects the end of a line, allowing a trailing comment.
+    fn end_of_line(&mut self) -> Result<(), ParseError> {
+        self.skip_whitespace();
+        self.skip_comment();
+        match self.peek() {
+            Some('\r') => {
+                self.bump();
+                self.expect('\n')
+            }
+            Some('\n') => {
+                self.bump();
+                Ok(())
+            }
+            None => Ok(()),
+            Some(c) => self.error(format!("expected the end of the line, found '{c}'")),
+        }
+    }
+
+    fn key(&mut self) -> Result<Key, ParseError> {
+        let mut key = Vec::new();
+        loop {
+            self.skip_whitespace();
+            let position = self.position;
+            let part = match self.peek() {
+                Some('"') => self.basic_string()?,
+                Some('\'') => self.literal_string()?,
+                Some(c) if c.is_ascii_alphanumeric() || c == '_' || c == '-' => {
+                    let mut part = String::new();
+                    while let Some(c) = self
+                        .peek()
+                        .filter(|c| c.is_ascii_alphanumeric() || *c == '_' || *c == '-')
+                    {
+                        part.push(c);
+                        self.bump();
+                    }
+                    part
+                }
+                Some(c) => return self.error(format!("expected a key, found '{c}'")),
+                None => return self.error("expected a key, found the end of the file"),
+            };
+            key.push((part, position));
+            self.skip_whitespace();
+            if self.peek() != Some('.') {
+                return Ok(key);
+            }
+            self.bump();
+        }
+    }
+
+    fn basic_string(&mut self) -> Result<String, ParseError> {
+        let start = self.position;
+        self.expect('"')?;
+        let mut s = String::new();
+        loop {
+            match self.bump() {
+                Some('"') => return Ok(s),
+                Some('\\') => {
+                    let escaped = match self.bump() {
+                        Some('n') => '\n',
+                        Some('t') => '\t',
+                        Some('r') => '\r',
+                        Some('"') => '"',
+                        Some('\\') => '\\',
+                        Some('u') => {
+                            let mut code = String::new();
+                            for _ in 0..4 {
+                                code.extend(self.bump());
+                            }
+                            match u32::from_str_radix(&code, 16).ok().and_then(char::from_u32) {
+                                Some(c) => c,
+                                None => {
+                                    return self.error(format!("invalid unicode escape \\u{code}"))
+                                }
+                            }
+                        }
+                        Some(c) => return self.error(format!("invalid escape sequence \\{c}")),
+                        None => return error_at("unterminated string".to_string(), start),
+                    };
+                    s.push(escaped);
+                }
+                Some('\n') | None => return error_at("unterminated string".to_string(), start),
+                Some(c) => s.push(c),
+            }
+        }
+    }
+
+    fn literal_string(&mut self) -> Result<String, ParseError> {
+        let start = self.position;
+        self.expect('\'')?;
+        let mut s = String::new();
+        loop {
+            match self.bump() {
+                Some('\'') => return Ok(s),
+                Some('\n') | None => return error_at("unterminated string".to_string(), start),
+                Some(c) => s.push(c),
+            }
+        }
+    }
+
+    /// Opens an array or inline table.
+    fn nest(&mut self) -> Result<(), ParseError> {
+        if self.depth == MAX_DEPTH {
+            return self.error("arrays and inline tables are nested too deeply");
+        }
+        self.depth += 1;
+        self.bump();
+        Ok(())
+    }
+
+    fn value(&mut self) -> Result<Item, ParseError> {
+        self.skip_whitespace();
+        let position = self.position;
+        let value = match self.peek() {
+            Some('"') => Value::String(self.basic_string()?),
+            Some('\'') => Value::String(self.literal_string()?),
+            Some('[') => {
+                self.nest()?;
+                let mut items = Vec::new();
+                loop {
+                    self.skip_blank();
+                    if self.peek() == Some(']') {
+                        self.bump();
+                        break;
+                    }
+                    items.push(self.value()?);
+                    self.skip_blank();
+                    match self.peek() {
+                        Some(',') => {
+                            self.bump();
+                        }
+                        Some(']') => {}
+                        _ => return self.error("expected ',' or ']' in array"),
+                    }
+                }
+                self.depth -= 1;
+                Value::Array(items)
+            }
+            Some('{') => {
+                self.nest()?;
+                let mut table = Table::default();
+                self.skip_whitespace();
+                if self.peek() == Some('}') {
+                    self.bump();
+                } else {
+                    loop {
+                        let key = self.key()?;
+                        self.expect('=')?;
+                        let item = self.value()?;
+                        insert(&mut table, &key, item)?;
+                        self.skip_whitespace();
+                        match self.bump() {
+                            Some(',') => {}
+                            Some('}') => break,
+                            _ => return self.error("expected ',' or '}' in inline table"),
+                        }
+                    }
+                }
+                self.depth -= 1;
+                Value::Table(table)
+            }
+            Some(c) if c.is_ascii_alphanumeric() || c == '+' || c == '-' => {
+                let mut word = String::new();
+                while let Some(c) = self
+                    .peek()
+                    .filter(|c| c.is_ascii_alphanumeric() || matches!(c, '+' | '-' | '.' | '_'))
+                {
+                    word.push(c);
+                    self.bump();
+                }
+                match parse_scalar(&word) {
+                    Some(value) => value,
+                    None => {
+                        return Err(ParseError {
+                            message: format!("invalid value '{word}'"),
+                            position,
+                        })
+                    }
+                }
+            }
+            Some(c) => return self.error(format!("expected a value, found '{c}'")),
+            None => return self.error("expected a value, found the end of the file"),
+        };
+        Ok(Item { value, position })
+    }
+}
+
+fn parse_scalar(word: &str) -> Option<Value> {
+    match word {
+        "true" => return Some(Value::Boolean(true)),
+        "false" => return Some(Value::Boolean(false)),
+        "inf" | "+inf" => return Some(Value::Float(f64::INFINITY)),
+        "-inf" => return Some(Value::Float(f64::NEG_INFINITY)),
+        _ => {}
+    }
+    // Underscores may only separate digits.
+    if word.contains("__") || word.starts_with('_') || word.ends_with('_') {
+        return None;
+    }
+    let digits = word.replace('_', "");
+    if !digits
+        .trim_start_matches(['+', '-'])
+        .starts_with(|c: char| c.is_ascii_digit())
+    {
+        return None;
+    }
+    if let Ok(integer) = digits.parse::<i64>() {
+        return Some(Value::Integer(integer));
+    }
+    digits
+        .parse::<f64>()
+        .ok()
+        .filter(|f| f.is_finite() && !digits.ends_with('.'))
+        .map(Value::Float)
+}
+
+fn error_at<T>(message: String, position: Position) -> Result<T, ParseError> {
+    Err(ParseError { message, position })
+}
+
+/// Walks down `path` from `root`, creating missing tables and entering the last element of
+/// arrays of tables.
+fn table_at<'a>(
+    root: &'a mut Table,
+    path: &[(String, Position)],
+) -> Result<&'a mut Table, ParseError> {
+    let mut table = root;
+    for (name, position) in path {
+        if table.get(name).is_none() {
+            table.entries.push((
+                name.clone(),
+                Item {
+                    value: Value::Table(Table::default()),
+                    position: *position,
+                },
+            ));
+        }
+        let item = table.get_mut(name).unwrap();
+        table = match &mut item.value {
+            Value::Table(table) => table,
+            Value::Array(items) => match items.last_mut() {
+                Some(Item {
+                    value: Value::Table(table),
+                    ..
+                }) => table,
+                _ => return error_at(format!("'{name}' is not a table"), *position),
+            },
+            _ => return error_at(format!("'{name}' is not a table"), *position),
+        };
+    }
+    Ok(table)
+}
+
+fn insert(table: &mut Table, key: &Key, item: Item) -> Result<(), ParseError> {
+    let (last, position) = key.last().unwrap();
+    let table = table_at(table, &key[..key.len() - 1])?;
+    if table.get(last).is_some() {
+        return error_at(format!("duplicate key '{last}'"), *position);
+    }
+    table.entries.push((last.clone(), item));
+    Ok(())
+}
+
+/// Parses a whole document into its root table.
+pub fn parse(text: &str) -> Result<Table, ParseError> {
+    let mut parser = Parser {
+        chars: text.chars().peekable(),
+        position: Position { line: 1, column: 1 },
+        depth: 0,
+    };
+    let mut root = Table::default();
+    let mut current: Key = Vec::new();
+    let mut defined: Vec<String> = Vec::new();
+
+    loop {
+        parser.skip_blank();
+        match parser.peek() {
+            None => return Ok(root),
+            Some('[') => {
+                let position = parser.position;
+                parser.bump();
+                let array = parser.peek() == Some('[');
+                if array {
+                    parser.bump();
+                }
+                let key = parser.key()?;
+                parser.expect(']')?;
+                if array {
+                    parser.expect(']')?;
+                }
+                parser.end_of_line()?;
+
+                let (last, last_position) = key.last().unwrap().clone();
+                let parent = table_at(&mut root, &key[..key.len() - 1])?;
+                let new_table = Item {
+                    value: Value::Table(Table::default()),
+                    position,
+                };
+                if array {
+                    match parent.get_mut(&last) {
+                        None => parent.entries.push((
+                            last,
+                            Item {
+                                value: Value::Array(vec![new_table]),
+                                position,
+                            },
+                        )),
+                        Some(Item {
+                            value: Value::Array(items),
+                            ..
+                        }) if matches!(items.first().map(|i| &i.value), Some(Value::Table(_))) => {
+                            items.push(new_table)
+                        }
+                        Some(_) => {
+                            return error_at(
+                                format!("'{last}' is not an array of tables"),
+                                last_position,
+                            )
+                        }
+                    }
+                } else {
+                    let name: Vec<&str> = key.iter().map(|(k, _)| k.as_str()).collect();
+                    let name = name.join(".");
+                    if defined.contains(&name) {
+                        return error_at(format!("table [{name}] is defined twice"), position);
+                    }
+                    defined.push(name);
+                    match parent.get_mut(&last) {
+                        None => parent.entries.push((last, new_table)),
+                        Some(Item {
+                            value: Value::Table(_),
+                            position: p,
+                        }) => *p = position,
+                        Some(_) => {
+                            return error_at(format!("'{last}' is not a table"), last_position)
+                        }
+                    }
+                }
+                current = key;
+            }
+            Some(_) => {
+                let key = parser.key()?;
+                parser.expect('=')?;
+                let item = parser.value()?;
+                parser.end_of_line()?;
+                let table = table_at(&mut root, &current)?;
+                insert(table, &key, item)?;
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn parses_documents() {
+        let text = r#"
+# A comment
+title = "three \"spheres\""
+path = 'C:\scenes'
+count = 1_000
+ratio = -1.5e2
+on = true
+
+[camera]
+lookfrom = [0, 1.5,
+    -2,  # trailing comma below
+]
+lens = { aperture = 0.1, focus.dist = 10 }
+
+[[objects]]
+type = "sphere"
+
+[[objects]]
+type = "box"
+size.x = 2
+"#;
+        let root = parse(text).unwrap();
+        assert_eq!(
+            Value::String("three \"spheres\"".to_string()),
+            root.get("title").unwrap().value
+        );
+        assert_eq!(
+            Value::String("C:\\scenes".to_string()),
+            root.get("path").unwrap().value
+        );
+        assert_eq!(Value::Integer(1000), root.get("count").unwrap().value);
+        assert_eq!(Value::Float(-150f64), root.get("ratio").unwrap().value);
+        assert_eq!(Value::Boolean(true), root.get("on").unwrap().value);
+
+        let Value::Table(camera) = &root.get("camera").unwrap().value else {
+            panic!("camera is not a table");
+        };
+        assert_eq!(
+            Position { line: 9, column: 1 },
+            root.get("camera").unwrap().position
+        );
+        let Value::Array(lookfrom) = &camera.get("lookfrom").unwrap().value else {
+            panic!("lookfrom is not an array");
+        };
+        assert_eq!(3, lookfrom.len());
+        assert_eq!(
+            Position {
+                line: 11,
+                column: 5
+            },
+            lookfrom[2].position
+        );
+        let Value::Table(lens) = &camera.get("lens").unwrap().value else {
+            panic!("lens is not a table");
+        };
+        assert!(lens.get("focus").is_some());
+
+        let Value::Array(objects) = &root.get("objects").unwrap().value else {
+            panic!("objects is not an array");
+        };
+        assert_eq!(2, objects.len());
+        let Value::Table(second) = &objects[1].value else {
+            panic!("object is not a table");
+        };
+        assert_eq!(
+            Value::String("box".to_string()),
+            second.get("type").unwrap().value
+        );
+        assert_eq!(
+            Position {
+                line: 18,
+                column: 1
+            },
+            objects[1].position
+        );
+    }
+
+    #[test]
+    fn reports_positions() {
+        let error = |text: &str| parse(text).unwrap_err().to_string();
+        assert_eq!("2:1: duplicate key 'a'", error("a = 1\na = 3"));
+        assert_eq!(
+            "1:5: expected a value, found the end of the file",
+            error("a = ")
+        );
+        assert_eq!("2:7: invalid value '1.2.3'", error("\nvalue=1.2.3"));
+        assert_eq!("1:5: unterminated string", error("a = \"x\nb"));
+        assert_eq!(
+            "1:9: expected the end of the line, found '2'",
+            error("a = 1.0 2")
+        );
+        assert_eq!("3:1: table [t] is defined twice", error("[t]\na=1\n[t]"));
+        assert_eq!("2:1: 'a' is not a table", error("a = 1\na.b = 2"));
+        assert_eq!("1:8: expected ',' or ']' in array", error("a = [1 2]"));
+
+        let nested = |depth: usize| format!("a = {}{}", "[".repeat(depth), "]".repeat(depth));
+        assert!(parse(&nested(MAX_DEPTH)).is_ok());
+        assert_eq!(
+            "1:133: arrays and inline tables are nested too deeply",
+            error(&nested(MAX_DEPTH + 1))
+        );
+        assert!(parse(&format!("a = {}", "[".repeat(200_000))).is_err());
+        assert!(parse(&format!("a = {}", "{b=".repeat(200_000))).is_err());
+    }
+}
@@ -0,0 +1,502 @@
+use std::ops::Mul;
+
+use crate::aabb::*;
+use crate::ray::*;
+use crate::vec3::*;
+
+/// Row-major 4x4 matrix acting on column vectors.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Matrix4 {
+    pub m: [[f64; 4]; 4],
+}
+
+impl Default for Matrix4 {
+    fn default() -> Matrix4 {
+        Matrix4::identity()
+    }
+}
+
+impl Matrix4 {
+    pub fn identity() -> Matrix4 {
+        let mut m = [[0f64; 4]; 4];
+        for (i, row) in m.iter_mut().enumerate() {
+            row[i] = 1f64;
+        }
+        Matrix4 { m }
+    }
+
+    pub fn from(m: [[f64; 4]; 4]) -> Matrix4 {
+        Matrix4 { m }
+    }
+
+    pub fn transpose(&self) -> Matrix4 {
+        let mut m = [[0f64; 4]; 4];
+        for (i, row) in m.iter_mut().enumerate() {
+            for (j, value) in row.iter_mut().enumerate() {
+                *value = self.m[j][i];
+            }
+        }
+        Matrix4 { m }
+    }
+
+    /// Gauss-Jordan elimination with partial pivoting; `None` if the matrix is singular.
+    pub fn inverse(&self) -> Option<Matrix4> {
+        let mut a = self.m;
+        let mut inv = Matrix4::identity().m;
+        for column in 0..4 {
+            let pivot = (column..4)
+                .max_by(|&i, &j| a[i][column].abs().total_cmp(&a[j][column].abs()))
+                .unwrap();
+            if a[pivot][column].abs() < 1e-12 {
+                return None;
+            }
+            a.swap(column, pivot);
+            inv.swap(column, pivot);
+
+            let scale = 1f64 / a[column][column];
+            for j in 0..4 {
+                a[column][j] *= scale;
+                inv[column][j] *= scale;
+            }
+            for row in 0..4 {
+                if row != column {
+                    let factor = a[row][column];
+                    for j in 0..4 {
+                        a[row][j] -= factor * a[column][j];
+                        inv[row][j] -= factor * inv[column][j];
+                    }
+                }
+            }
+        }
+        Some(Matrix4 { m: inv })
+    }
+
+    /// Transforms a point, applying the translation.
+    pub fn point(&self, p: &Point3) -> Point3 {
+        let m = &self.m;
+        let x = m[0][0] * p.x() + m[0][1] * p.y() + m[0][2] * p.z() + m[0][3];
+        let y = m[1][0] * p.x() + m[1][1] * p.y() + m[1][2] * p.z() + m[1][3];
+        let z = m[2][0] * p.x() + m[2][1] * p.y() + m[2][2] * p.z() + m[2][3];
+        let w = m[3][0] * p.x() + m[3][1] * p.y() + m[3][2] * p.z() + m[3][3];
+        if w == 1f64 {
+            Point3::from(x, y, z)
+        } else {
+            Point3::from(x, y, z) / w
+        }
+    }
+
+    /// Transforms a direction, ignoring the translation.
+    pub fn vector(&self, v: &Vec3) -> Vec3 {
+        let m = &self.m;
+        Vec3::from(
+            m[0][0] * v.x() + m[0][1] * v.y() + m[0][2] * v.z(),
+            m[1][0] * v.x() + m[1][1] * v.y() + m[1][2] * v.z(),
+            m[2][0] * v.x() + m[2][1] * v.y() + m[2][2] * v.z(),
+        )
+    }
+}
+
+impl Mul for Matrix4 {
+    type Output = Matrix4;
+
+    fn mul(self, rhs: Self) -> Self::Output {
+        let mut m = [[0f64; 4]; 4];
+        for (i, row) in m.iter_mut().enumerate() {
+            for (j, value) in row.iter_mut().enumerate() {
+                *value = (0..4).map(|k| self.m[i][k] * rhs.m[k][j]).sum();
+            }
+        }
+        Matrix4 { m }
+    }
+}
+
+/// An invertible affine transform, kept together with its inverse.
+///
+/// Transforms compose like matrices: `a * b` applies `b` first, then `a`.
+#[derive(Debug, Clone, Copy, PartialEq, Default)]
+pub struct Transform {
+    matrix: Matrix4,
+    inverse: Matrix4,
+}
+
+impl Transform {
+    pub fn new() -> Transform {
+        Transform::default()
+    }
+
+    /// Returns `None` if `matrix` is not invertible.
+    pub fn from(matrix: Matrix4) -> Option<Transform> {
+        Some(Transform {
+            inverse: matrix.inverse()?,
+            matrix,
+        })
+    }
+
+    pub fn translate(offset: Vec3) -> Transform {
+        let mut matrix = Matrix4::identity();
+        let mut inverse = Matrix4::identity();
+        for axis in 0..3 {
+            matrix.m[axis][3] = offset[axis as i32];
+            inverse.m[axis][3] = -offset[axis as i32];
+        }
+        Transform { matrix, inverse }
+    }
+
+    /// Scales by a factor along each axis. Returns `None` if a factor is zero.
+    pub fn scale(factors: Vec3) -> Option<Transform> {
+        let mut matrix = Matrix4::identity();
+        let mut inverse = Matrix4::identity();
+        for axis in 0..3 {
+            let factor = factors[axis as i32];
+            if factor == 0f64 {
+                return None;
+            }
+            matrix.m[axis][axis] = factor;
+            inverse.m[axis][axis] = 1f64 / factor;
+        }
+        Some(Transform { matrix, inverse })
+    }
+
+    /// Rotates counter clockwise by `degrees` around `axis`, looking down the axis towards the
+    /// origin.
+    pub fn rotate(axis: Vec3, degrees: f64) -> Transform {
+        let a = axis.unit_vector();
+        let (sin, cos) = degrees.to_radians().sin_cos();
+        let (x, y, z) = (*a.x(), *a.y(), *a.z());
+        let matrix = Matrix4::from([
+            [
+                cos + x * x * (1f64 - cos),
+                x * y * (1f64 - cos) - z * sin,
+                x * z * (1f64 - cos) + y * sin,
+                0f64,
+            ],
+            [
+                y * x * (1f64 - cos) + z * sin,
+                cos + y * y * (1f64 - cos),
+                y * z * (1f64 - cos) - x * sin,
+                0f64,
+            ],
+            [
+                z * x * (1f64 - cos) - y * sin,
+                z * y * (1f64 - cos) + x * sin,
+                cos + z * z * (1f64 - cos),
+                0f64,
+            ],
+            [0f64, 0f64, 0f64, 1f64],
+        ]);
+        // Rotations are orthogonal.
+        Transform {
+            inverse: matrix.transpose(),
+            matrix,
+        }
+    }
+
+    pub fn rotate_x(degrees: f64) -> Transform {
+        Transform::rotate(Vec3::from(1f64, 0f64, 0f64), degrees)
+    }
+
+    pub fn rotate_y(degrees: f64) -> Transform {
+        Transform::rotate(Vec3::from(0f64, 1f64, 0f64), degrees)
+    }
+
+    pub fn rotate_z(degrees: f64) -> Transform {
+        Transform::rotate(Vec3::from(0f64, 0f64, 1f64), degrees)
+    }
+
+    pub fn matrix(&self) -> &Matrix4 {
+        &self.matrix
+    }
+
+    pub fn inverse(&self) -> Transform {
+        Transform {
+            matrix: self.inverse,
+            inverse: self.matrix,
+        }
+    }
+
+    /// The inverse transpose, which keeps normals perpendicular to transformed surfaces.
+    pub fn normal_matrix(&self) -> Matrix4 {
+        self.inverse.transpose()
+    }
+
+    pub fn point(&self, p: &Point3) -> Point3 {
+        self.matrix.point(p)
+    }
+
+    pub fn vector(&self, v: &Vec3) -> Vec3 {
+        self.matrix.vector(v)
+    }
+
+    /// Transforms a surface normal. The result is not normalized.
+    pub fn normal(&self, n: &Vec3) -> Vec3 {
+        self.normal_matrix().vector(n)
+    }
+
+    /// Transforms a ray. The direction is not normalized, so distances `t` along the ray are the
+    /// same before and after.
+    pub fn ray(&self, r: &Ray) -> Ray {
+        Ray::from_time(
+            self.point(&r.origin()),
+            self.vector(&r.direction()),
+            r.time(),
+        )
+    }
+
+    /// The box around the transformed corners of `bbox`.
+    pub fn bounding_box(&self, bbox: &Aabb) -> Aabb {
+        let (min, max) = (bbox.min(), bbox.max());
+        Aabb::from_points((0..8).map(|corner| {
+            self.point(&Point3::from(
+                if corner & 1 == 0 { *min.x() } else { *max.x() },
+                if corner & 2 == 0 { *min.y() } else { *max.y() },
+                if corner & 4 == 0 { *min.z() } else { *max.z() },
+            ))
+        }))
+    }
+}
+
+impl Mul for Transform {
+    type Output = Transform;
+
+    fn mul(self, rhs: Self) -> Self::Output {
+        Transform {
+            matrix: self.matrix * rhs.matrix,
+            inverse: rhs.inverse * self.inverse,
+        }
+    }
+}
+
+/// A transform split into parts that can be interpolated: `scale` factors along each axis, then
+/// `rotate` by angles in degrees around the x, y and z axes in that order, then `translate`.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct TransformComponents {
+    pub scale: Vec3,
+    pub rotate: Vec3,
+    pub translate: Vec3,
+}
+
+impl Default for TransformComponents {
+    fn default() -> TransformComponents {
+        TransformComponents::new()
+    }
+}
+
+impl TransformComponents {
+    /// The identity.
+    pub fn new() -> TransformComponents {
+        TransformComponents {
+            scale: Vec3::from(1f64, 1f64, 1f64),
+            rotate: Vec3::new(),
+            translate: Vec3::new(),
+        }
+    }
+
+    /// Interpolates each part linearly, `s = 0` giving `self` and `s = 1` giving `other`.
+    pub fn lerp(&self, other: &TransformComponents, s: f64) -> TransformComponents {
+        TransformComponents {
+            scale: self.scale + s * (other.scale - self.scale),
+            rotate: self.rotate + s * (other.rotate - self.rotate),
+            translate: self.translate + s * (other.translate - self.translate),
+        }
+    }
+
+    /// Returns `None` if a scale factor is zero.
+    pub fn to_transform(&self) -> Option<Transform> {
+        Some(
+            Transform::translate(self.translate)
+                * Transform::rotate_z(*self.rotate.z())
+                * Transform::rotate_y(*self.rotate.y())
+                * Transform::rotate_x(*self.rotate.x())
+                * Transform::scale(self.scale)?,
+        )
+    }
+}
+
+/// A transform moving from `start` at `time0` to `end` at `time1`, holding still outside that
+/// interval.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct AnimatedTransform {
+    start: TransformComponents,
+    end: TransformComponents,
+    time0: f64,
+    time1: f64,
+}
+
+impl AnimatedTransform {
+    /// Returns `None` if a scale factor would pass through zero on the way.
+    pub fn from(
+        start: TransformComponents,
+        end: TransformComponents,
+        time0: f64,
+        time1: f64,
+    ) -> Option<AnimatedTransform> {
+        let keeps_sign = (0..3).all(|axis| start.scale[axis] * end.scale[axis] > 0f64);
+        keeps_sign.then_some(AnimatedTransform {
+            start,
+            end,
+            time0,
+            time1,
+        })
+    }
+
+    pub fn start(&self) -> &TransformComponents {
+        &self.start
+    }
+
+    pub fn end(&self) -> &TransformComponents {
+        &self.end
+    }
+
+    pub fn at(&self, time: f64) -> Transform {
+        let s = if self.time1 > self.time0 {
+            ((time - self.time0) / (self.time1 - self.time0)).clamp(0f64, 1f64)
+        } else if time < self.time0 {
+            0f64
+        } else {
+            1f64
+        };
+        self.start.lerp(&self.end, s).to_transform().unwrap()
+    }
+
+    /// A box containing `bbox` at every time. The motion is sampled, and every sampled box is
+    /// grown by the furthest a corner travels between two samples, which covers the curved paths
+    /// rotations take in between.
+    pub fn bounding_box(&self, bbox: &Aabb) -> Aabb {
+        const STEPS: usize = 32;
+        let (min, max) = (bbox.min(), bbox.max());
+        let corners: Vec<Point3> = (0..8)
+            .map(|corner| {
+                Point3::from(
+                    if corner & 1 == 0 { *min.x() } else { *max.x() },
+                    if corner & 2 == 0 { *min.y() } else { *max.y() },
+                    if corner & 4 == 0 { *min.z() } else { *max.z() },
+                )
+            })
+            .collect();
+
+        let mut result = Aabb::new();
+        let mut previous: Option<Vec<Point3>> = None;
+        let mut slack = 0f64;
+        for step in 0..=STEPS {
+            let s = step as f64 / STEPS as f64;
+            let transform = self.start.lerp(&self.end, s).to_transform().unwrap();
+            let moved: Vec<Point3> = corners.iter().map(|p| transform.point(p)).collect();
+            if let Some(previous) = &previous {
+                for (a, b) in previous.iter().zip(&moved) {
+                    slack = slack.max((*a - *b).length());
+                }
+            }
+            result = Aabb::surrounding_box(&result, &Aabb::from_points(moved.iter().copied()));
+            previous = Some(moved);
+        }
+        let slack = Vec3::from(slack, slack, slack);
+        Aabb::from(result.min() - slack, result.max() + slack)
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    fn assert_near(expected: Vec3, actual: Vec3) {
+        assert!((expected - actual).near_zero(), "{expected} != {actual}");
+    }
+
+    #[test]
+    fn transform_points_and_normals() {
+        let p = Point3::from(1f64, 0f64, 0f64);
+        assert_near(
+            Point3::from(0f64, 0f64, -1f64),
+            Transform::rotate_y(90f64).point(&p),
+        );
+        assert_near(
+            Point3::from(0f64, 1f64, 0f64),
+            Transform::rotate_z(90f64).point(&p),
+        );
+
+        // Scale first, then rotate, then translate.
+        let transform = Transform::translate(Vec3::from(0f64, 5f64, 0f64))
+            * Transform::rotate_z(90f64)
+            * Transform::scale(Vec3::from(2f64, 1f64, 1f64)).unwrap();
+        assert_near(Point3::from(0f64, 7f64, 0f64), transform.point(&p));
+        assert_near(Vec3::from(0f64, 2f64, 0f64), transform.vector(&p));
+        assert_near(p, transform.inverse().point(&transform.point(&p)));
+
+        // A normal stays perpendicular to the surface under non uniform scaling.
+        let scale = Transform::scale(Vec3::from(1f64, 4f64, 1f64)).unwrap();
+        let tangent = Vec3::from(1f64, -1f64, 0f64);
+        let normal = Vec3::from(1f64, 1f64, 0f64);
+        let dot = Vec3::dot(&scale.vector(&tangent), &scale.normal(&normal));
+        assert!(dot.abs() < 1e-12);
+
+        assert!(Transform::scale(Vec3::from(1f64, 0f64, 1f64)).is_none());
+        assert!(Transform::from(Matrix4::from([[0f64; 4]; 4])).is_none());
+    }
+
+    #[test]
+    fn matrix_inverse() {
+        let matrix = Matrix4::from([
+            [2f64, 0f64, 1f64, 3f64],
+            [1f64, 1f64, 0f64, -1f64],
+            [0f64, 3f64, 1f64, 2f64],
+            [0f64, 0f64, 0f64, 1f64],
+        ]);
+        let product = matrix * matrix.inverse().unwrap();
+        for i in 0..4 {
+            for j in 0..4 {
+                let expected = if i == j { 1f64 } else { 0f64 };
+                assert!((product.m[i][j] - expected).abs() < 1e-12);
+            }
+        }
+
+        let transform = Transform::rotate_x(30f64);
+        let bbox = Aabb::from(
+            Point3::from(-1f64, -1f64, -1f64),
+            Point3::from(1f64, 1f64, 1f64),
+        );
+        let rotated = transform.bounding_box(&bbox);
+        let extent = 30f64.to_radians().cos() + 30f64.to_radians().sin();
+        assert_near(Point3::from(-1f64, -extent, -extent), rotated.min());
+    }
+
+    #[test]
+    fn animated_transform() {
+        let start = TransformComponents::new();
+        let end = TransformComponents {
+            scale: Vec3::from(3f64, 1f64, 1f64),
+            rotate: Vec3::from(0f64, 90f64, 0f64),
+            translate: Vec3::from(0f64, 4f64, 0f64),
+        };
+        let animation = AnimatedTransform::from(start, end, 1f64, 3f64).unwrap();
+        let p = Point3::from(1f64, 0f64, 0f64);
+        assert_near(p, animation.at(0f64).point(&p));
+        assert_near(p, animation.at(1f64).point(&p));
+        assert_near(
+            Point3::from(0f64, 4f64, -3f64),
+            animation.at(3f64).point(&p),
+        );
+        assert_near(
+            Point3::from(0f64, 4f64, -3f64),
+            animation.at(5f64).point(&p),
+        );
+        let halfway = Transform::translate(Vec3::from(0f64, 2f64, 0f64))
+            * Transform::rotate_y(45f64)
+            * Transform::scale(Vec3::from(2f64, 1f64, 1f64)).unwrap();
+        assert_near(halfway.point(&p), animation.at(2f64).point(&p));
+
+        // The box holds the transformed box at any time.
+        let bbox = Aabb::from(Point3::new(), Point3::from(1f64, 1f64, 1f64));
+        let moving = animation.bounding_box(&bbox);
+        for step in 0..=100 {
+            let time = 1f64 + 2f64 * step as f64 / 100f64;
+            let at = animation.at(time).bounding_box(&bbox);
+            assert_eq!(moving, Aabb::surrounding_box(&moving, &at));
+        }
+
+        let flipped = TransformComponents {
+            scale: Vec3::from(-1f64, 1f64, 1f64),
+            ..start
+        };
+        assert!(AnimatedTransform::from(start, flipped, 0f64, 1f64).is_none());
+    }
+}
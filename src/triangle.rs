@@ -0,0 +1,318 @@
+use std::sync::Arc;
+
+use crate::aabb::*;
+use crate::bvh::*;
+use crate::hittable::*;
+use crate::material::*;
+use crate::obj::*;
+use crate::ray::*;
+use crate::vec3::*;
+
+/// Möller–Trumbore ray-triangle intersection. Returns `t` and the barycentric coordinates of
+/// `p1` and `p2` at the hit point.
+pub fn intersect_triangle(
+    r: &Ray,
+    p0: Point3,
+    p1: Point3,
+    p2: Point3,
+    t_min: f64,
+    t_max: f64,
+) -> Option<(f64, f64, f64)> {
+    let edge1 = p1 - p0;
+    let edge2 = p2 - p0;
+    let pvec = Vec3::cross(&r.direction(), &edge2);
+    let det = Vec3::dot(&edge1, &pvec);
+
+    // The ray is parallel to the triangle, or the triangle is degenerate.
+    if det.abs() < 1e-12 * edge1.length_squared().max(edge2.length_squared()) {
+        return None;
+    }
+    let inv_det = 1f64 / det;
+
+    let tvec = r.origin() - p0;
+    let b1 = Vec3::dot(&tvec, &pvec) * inv_det;
+    if !(0f64..=1f64).contains(&b1) {
+        return None;
+    }
+
+    let qvec = Vec3::cross(&tvec, &edge1);
+    let b2 = Vec3::dot(&r.direction(), &qvec) * inv_det;
+    if b2 < 0f64 || b1 + b2 > 1f64 {
+        return None;
+    }
+
+    let t = Vec3::dot(&edge2, &qvec) * inv_det;
+    if t <= t_min || t_max <= t {
+        return None;
+    }
+    Some((t, b1, b2))
+}
+
+/// A single flat triangle. The front face is the one from which the vertices appear counter
+/// clockwise, and `(u, v)` are the barycentric coordinates of `p1` and `p2`.
+pub struct Triangle {
+    p0: Point3,
+    p1: Point3,
+    p2: Point3,
+    material: Arc<dyn Material>,
+}
+
+impl Triangle {
+    pub fn from(p0: Point3, p1: Point3, p2: Point3, material: Arc<dyn Material>) -> Triangle {
+        Triangle {
+            p0,
+            p1,
+            p2,
+            material,
+        }
+    }
+}
+
+impl Hittable for Triangle {
+    fn hit(&self, r: &Ray, t_min: f64, t_max: f64) -> Option<HitRecord<'_>> {
+        let (t, b1, b2) = intersect_triangle(r, self.p0, self.p1, self.p2, t_min, t_max)?;
+        let outward_normal = Vec3::cross(&(self.p1 - self.p0), &(self.p2 - self.p0)).unit_vector();
+        Some(HitRecord::from(
+            r,
+            t,
+            outward_normal,
+            b1,
+            b2,
+            self.material.as_ref(),
+        ))
+    }
+
+    fn bounding_box(&self) -> Option<Aabb> {
+        Some(Aabb::from_points([self.p0, self.p1, self.p2]).pad(1e-4))
+    }
+}
+
+/// Triangles sharing a vertex buffer, with optional per-vertex normals and texture coordinates.
+///
+/// With normals, the shading normal is interpolated across each triangle while the front face is
+/// still decided by the geometric one. Without texture coordinates, `(u, v)` are barycentric.
+/// The triangles are kept in their own [`Bvh`].
+pub struct TriangleMesh {
+    positions: Vec<Point3>,
+    normals: Vec<Vec3>,
+    uvs: Vec<(f64, f64)>,
+    triangles: Vec<[u32; 3]>,
+    material: Arc<dyn Material>,
+    bvh: Bvh,
+}
+
+impl TriangleMesh {
+    /// `normals` and `uvs` are either empty or hold one entry per position. Zero normals, as in
+    /// an OBJ `vn 0 0 0`, are ignored in favor of the others or the face normal.
+    ///
+    /// # Panics
+    ///
+    /// If the attributes do not match the positions or an index is out of range.
+    pub fn from(
+        positions: Vec<Point3>,
+        normals: Vec<Vec3>,
+        uvs: Vec<(f64, f64)>,
+        triangles: Vec<[u32; 3]>,
+        material: Arc<dyn Material>,
+    ) -> TriangleMesh {
+        assert!(normals.is_empty() || normals.len() == positions.len());
+        assert!(uvs.is_empty() || uvs.len() == positions.len());
+        assert!(triangles
+            .iter()
+            .flatten()
+            .all(|&i| (i as usize) < positions.len()));
+        let boxes: Vec<Aabb> = triangles
+            .iter()
+            .map(|t| Aabb::from_points(t.map(|i| positions[i as usize])).pad(1e-4))
+            .collect();
+        TriangleMesh {
+            normals: normals
+                .iter()
+                .map(|n| {
+                    if n.near_zero() {
+                        Vec3::new()
+                    } else {
+                        n.unit_vector()
+                    }
+                })
+                .collect(),
+            uvs,
+            material,
+            bvh: Bvh::build(&boxes),
+            positions,
+            triangles,
+        }
+    }
+
+    pub fn from_obj(mesh: &ObjMesh, material: Arc<dyn Material>) -> TriangleMesh {
+        TriangleMesh::from(
+            mesh.positions.clone(),
+            mesh.normals.clone(),
+            mesh.uvs.clone(),
+            mesh.triangles.clone(),
+            material,
+        )
+    }
+
+    pub fn positions(&self) -> &[Point3] {
+        &self.positions
+    }
+
+    pub fn triangles(&self) -> &[[u32; 3]] {
+        &self.triangles
+    }
+
+    pub fn len(&self) -> usize {
+        self.triangles.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.triangles.is_empty()
+    }
+
+    pub fn stats(&self) -> BvhStats {
+        self.bvh.stats()
+    }
+
+    /// Intersects the triangle at `index` alone.
+    pub fn hit_triangle(
+        &self,
+        index: usize,
+        r: &Ray,
+        t_min: f64,
+        t_max: f64,
+    ) -> Option<HitRecord<'_>> {
+        let [i0, i1, i2] = self.triangles[index].map(|i| i as usize);
+        let (p0, p1, p2) = (self.positions[i0], self.positions[i1], self.positions[i2]);
+        let (t, b1, b2) = intersect_triangle(r, p0, p1, p2, t_min, t_max)?;
+        let b0 = 1f64 - b1 - b2;
+
+        let (u, v) = if self.uvs.is_empty() {
+            (b1, b2)
+        } else {
+            let (uv0, uv1, uv2) = (self.uvs[i0], self.uvs[i1], self.uvs[i2]);
+            (
+                b0 * uv0.0 + b1 * uv1.0 + b2 * uv2.0,
+                b0 * uv0.1 + b1 * uv1.1 + b2 * uv2.1,
+            )
+        };
+
+        let outward_normal = Vec3::cross(&(p1 - p0), &(p2 - p0)).unit_vector();
+        let mut rec = HitRecord::from(r, t, outward_normal, u, v, self.material.as_ref());
+        let shading_normal = (!self.normals.is_empty())
+            .then(|| b0 * self.normals[i0] + b1 * self.normals[i1] + b2 * self.normals[i2])
+            // Opposite vertex normals can cancel out, leaving only the face normal to go by.
+            .filter(|n| !n.near_zero());
+        if let Some(shading_normal) = shading_normal {
+            let shading_normal = shading_normal.unit_vector();
+            // Keep the shading normal on the side the ray came from.
+            rec.normal = if rec.front_face {
+                shading_normal
+            } else {
+                -shading_normal
+            };
+        }
+        Some(rec)
+    }
+}
+
+impl Hittable for TriangleMesh {
+    fn hit(&self, r: &Ray, t_min: f64, t_max: f64) -> Option<HitRecord<'_>> {
+        self.bvh.hit(r, t_min, t_max, |index, t_max| {
+            self.hit_triangle(index, r, t_min, t_max)
+        })
+    }
+
+    fn bounding_box(&self) -> Option<Aabb> {
+        self.bvh.bounding_box()
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn triangle_intersection() {
+        let material = Arc::new(Lambertian::from(Color::from(0.5, 0.5, 0.5)));
+        let triangle = Triangle::from(
+            Point3::from(0f64, 0f64, -1f64),
+            Point3::from(1f64, 0f64, -1f64),
+            Point3::from(0f64, 1f64, -1f64),
+            material,
+        );
+        let r = Ray::from(Point3::from(0.25, 0.5, 0f64), Vec3::from(0f64, 0f64, -1f64));
+        let rec = triangle.hit(&r, 0.001, f64::INFINITY).unwrap();
+        assert_eq!(1f64, rec.t);
+        assert!(rec.front_face);
+        assert_eq!(Vec3::from(0f64, 0f64, 1f64), rec.normal);
+        assert_eq!((0.25, 0.5), (rec.u, rec.v));
+
+        // Outside the edge, behind the origin, and parallel to the plane.
+        let miss = Ray::from(Point3::from(0.75, 0.5, 0f64), Vec3::from(0f64, 0f64, -1f64));
+        assert!(triangle.hit(&miss, 0.001, f64::INFINITY).is_none());
+        assert!(triangle.hit(&r, 0.001, 0.5).is_none());
+        let parallel = Ray::from(
+            Point3::from(-1f64, 0.1, -1f64),
+            Vec3::from(1f64, 0f64, 0f64),
+        );
+        assert!(triangle.hit(&parallel, 0.001, f64::INFINITY).is_none());
+    }
+
+    #[test]
+    fn mesh_interpolation() {
+        let material = Arc::new(Lambertian::from(Color::from(0.5, 0.5, 0.5)));
+        // A unit square in the z = -1 plane, with normals tilted outwards along x.
+        let mesh = TriangleMesh::from(
+            vec![
+                Point3::from(0f64, 0f64, -1f64),
+                Point3::from(1f64, 0f64, -1f64),
+                Point3::from(1f64, 1f64, -1f64),
+                Point3::from(0f64, 1f64, -1f64),
+            ],
+            vec![
+                Vec3::from(-1f64, 0f64, 1f64),
+                Vec3::from(1f64, 0f64, 1f64),
+                Vec3::from(1f64, 0f64, 1f64),
+                Vec3::from(-1f64, 0f64, 1f64),
+            ],
+            vec![(0f64, 0f64), (2f64, 0f64), (2f64, 2f64), (0f64, 2f64)],
+            vec![[0, 1, 2], [0, 2, 3]],
+            material,
+        );
+        assert_eq!(2, mesh.len());
+
+        let r = Ray::from(Point3::from(0.5, 0.25, 0f64), Vec3::from(0f64, 0f64, -1f64));
+        let rec = mesh.hit(&r, 0.001, f64::INFINITY).unwrap();
+        assert_eq!((1f64, 0.5), (rec.u, rec.v));
+        assert!((rec.normal - Vec3::from(0f64, 0f64, 1f64)).near_zero());
+
+        // From behind, the shading normal flips with the face.
+        let r = Ray::from(Point3::from(0.75, 0.5, -2f64), Vec3::from(0f64, 0f64, 1f64));
+        let rec = mesh.hit(&r, 0.001, f64::INFINITY).unwrap();
+        assert!(!rec.front_face);
+        assert!(*rec.normal.z() < 0f64 && *rec.normal.x() < 0f64);
+
+        // Missing or cancelling vertex normals fall back to the face normal.
+        let mesh = TriangleMesh::from(
+            vec![
+                Point3::from(0f64, 0f64, -1f64),
+                Point3::from(1f64, 0f64, -1f64),
+                Point3::from(0f64, 1f64, -1f64),
+            ],
+            vec![
+                Vec3::new(),
+                Vec3::from(1f64, 0f64, 0f64),
+                Vec3::from(-1f64, 0f64, 0f64),
+            ],
+            Vec::new(),
+            vec![[0, 1, 2]],
+            Arc::new(Lambertian::from(Color::from(0.5, 0.5, 0.5))),
+        );
+        for x in [0.1, 0.25] {
+            let r = Ray::from(Point3::from(x, x, 0f64), Vec3::from(0f64, 0f64, -1f64));
+            let rec = mesh.hit(&r, 0.001, f64::INFINITY).unwrap();
+            assert_eq!(Vec3::from(0f64, 0f64, 1f64), rec.normal);
+        }
+    }
+}
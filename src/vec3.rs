@@ -1,7 +1,9 @@
 use core::ops::*;
 use std::fmt::Display;
 
-#[derive(Debug)]
+use crate::rng::*;
+
+#[derive(Debug, Clone, Copy, Default)]
 pub struct Vec3 {
     e: (f64, f64, f64),
 }
@@ -9,16 +11,6 @@ pub struct Vec3 {
 pub type Point3 = Vec3;
 pub type Color = Vec3;
 
-impl Clone for Vec3 {
-    fn clone(&self) -> Self {
-        Self {
-            e: (*self.x(), *self.y(), *self.z()),
-        }
-    }
-}
-
-impl Copy for Vec3 {}
-
 impl Vec3 {
     pub fn new() -> Vec3 {
         Vec3 {
@@ -89,7 +81,7 @@ impl Index<i32> for Vec3 {
     type Output = f64;
 
     fn index(&self, index: i32) -> &Self::Output {
-        assert!(index <= 2 && index >= 0);
+        assert!((0..=2).contains(&index));
         match index {
             0 => self.x(),
             1 => self.y(),
@@ -185,11 +177,125 @@ impl Vec3 {
     pub fn unit_vector(&self) -> Self {
         *self / self.length()
     }
+
+    /// Returns true if the vector is close to zero in all dimensions.
+    pub fn near_zero(&self) -> bool {
+        const S: f64 = 1e-8;
+        self.x().abs() < S && self.y().abs() < S && self.z().abs() < S
+    }
+
+    /// Mirrors `v` around the surface with unit normal `n`.
+    pub fn reflect(v: &Vec3, n: &Vec3) -> Vec3 {
+        *v - 2f64 * Vec3::dot(v, n) * *n
+    }
+
+    /// Bends the unit vector `uv` through a surface with unit normal `n` following Snell's law,
+    /// where `etai_over_etat` is the ratio of the refractive indices.
+    pub fn refract(uv: &Vec3, n: &Vec3, etai_over_etat: f64) -> Vec3 {
+        let cos_theta = Vec3::dot(&-*uv, n).min(1f64);
+        let r_out_perp = etai_over_etat * (*uv + cos_theta * *n);
+        let r_out_parallel = -(1f64 - r_out_perp.length_squared()).abs().sqrt() * *n;
+        r_out_perp + r_out_parallel
+    }
+}
+
+/// Random sampling helpers. They all draw from the calling thread's generator, so the same
+/// [`seed`] always yields the same sequence of vectors.
+impl Vec3 {
+    /// Returns a vector with each component uniformly distributed in `[0, 1)`.
+    pub fn random() -> Vec3 {
+        Vec3::from(random_double(), random_double(), random_double())
+    }
+
+    /// Returns a vector with each component uniformly distributed in `[min, max)`.
+    pub fn random_range(min: f64, max: f64) -> Vec3 {
+        Vec3::from(
+            random_double_range(min, max),
+            random_double_range(min, max),
+            random_double_range(min, max),
+        )
+    }
+
+    /// Rejection samples a point inside the unit sphere.
+    pub fn random_in_unit_sphere() -> Vec3 {
+        loop {
+            let p = Vec3::from(
+                random_double_range(-1f64, 1f64),
+                random_double_range(-1f64, 1f64),
+                random_double_range(-1f64, 1f64),
+            );
+            if p.length_squared() < 1f64 {
+                return p;
+            }
+        }
+    }
+
+    /// Returns a uniformly distributed direction.
+    pub fn random_unit_vector() -> Vec3 {
+        Vec3::random_in_unit_sphere().unit_vector()
+    }
+
+    /// Returns a point uniformly distributed in the half of the unit ball on the side of `normal`.
+    /// It is not a unit vector; normalize it for a uniformly distributed direction.
+    pub fn random_in_hemisphere(normal: &Vec3) -> Vec3 {
+        let in_unit_sphere = Vec3::random_in_unit_sphere();
+        if Vec3::dot(&in_unit_sphere, normal) > 0f64 {
+            in_unit_sphere
+        } else {
+            -in_unit_sphere
+        }
+    }
+
+    /// Returns a unit direction around +z distributed proportionally to the cosine of its angle
+    /// with +z, by projecting a point of the unit disk up onto the hemisphere.
+    pub fn random_cosine_direction() -> Vec3 {
+        let d = Vec3::random_in_unit_disk();
+        let z = (1f64 - d.length_squared()).max(0f64).sqrt();
+        Vec3::from(*d.x(), *d.y(), z)
+    }
+
+    /// Returns a cosine weighted unit direction in the hemisphere around the unit vector `normal`.
+    pub fn random_cosine_hemisphere(normal: &Vec3) -> Vec3 {
+        let (tangent, bitangent) = normal.orthonormal_basis();
+        let d = Vec3::random_cosine_direction();
+        *d.x() * tangent + *d.y() * bitangent + *d.z() * *normal
+    }
+
+    /// Returns two unit vectors completing the unit vector `self` to an orthonormal basis.
+    pub fn orthonormal_basis(&self) -> (Vec3, Vec3) {
+        // Duff et al., "Building an Orthonormal Basis, Revisited".
+        let sign = 1f64.copysign(*self.z());
+        let a = -1f64 / (sign + self.z());
+        let b = self.x() * self.y() * a;
+        (
+            Vec3::from(
+                1f64 + sign * self.x() * self.x() * a,
+                sign * b,
+                -sign * self.x(),
+            ),
+            Vec3::from(b, sign + self.y() * self.y() * a, -self.y()),
+        )
+    }
+
+    /// Rejection samples a point inside the unit disk on the z = 0 plane.
+    pub fn random_in_unit_disk() -> Vec3 {
+        loop {
+            let p = Vec3::from(
+                random_double_range(-1f64, 1f64),
+                random_double_range(-1f64, 1f64),
+                0f64,
+            );
+            if p.length_squared() < 1f64 {
+                return p;
+            }
+        }
+    }
 }
 
 #[cfg(test)]
 mod test {
     use super::Vec3;
+    use crate::rng;
 
     #[test]
     fn basic_ops() {
@@ -273,4 +379,33 @@ mod test {
             Vec3::from(1f64, 2f64, 2f64).unit_vector()
         )
     }
+
+    #[test]
+    fn random_sampling() {
+        rng::seed(7);
+        let first = (Vec3::random(), Vec3::random_unit_vector());
+        rng::seed(7);
+        assert_eq!(first, (Vec3::random(), Vec3::random_unit_vector()));
+
+        let normal = Vec3::from(1f64, 2f64, -2f64).unit_vector();
+        let (tangent, bitangent) = normal.orthonormal_basis();
+        assert!(Vec3::dot(&tangent, &normal).abs() < 1e-12);
+        assert!(Vec3::dot(&bitangent, &normal).abs() < 1e-12);
+        assert!(Vec3::dot(&tangent, &bitangent).abs() < 1e-12);
+
+        for _ in 0..100 {
+            let v = Vec3::random_range(-2f64, 3f64);
+            assert!((0..3).all(|i| (-2f64..3f64).contains(&v[i])));
+            assert!(Vec3::random_in_unit_sphere().length_squared() < 1f64);
+            assert!((Vec3::random_unit_vector().length() - 1f64).abs() < 1e-12);
+            assert!(Vec3::dot(&Vec3::random_in_hemisphere(&normal), &normal) >= 0f64);
+
+            let disk = Vec3::random_in_unit_disk();
+            assert!(disk.length_squared() < 1f64 && *disk.z() == 0f64);
+
+            let cosine = Vec3::random_cosine_hemisphere(&normal);
+            assert!((cosine.length() - 1f64).abs() < 1e-12);
+            assert!(Vec3::dot(&cosine, &normal) >= 0f64);
+        }
+    }
 }